@@ -0,0 +1,126 @@
+use std::env;
+use std::fs::File;
+use std::io::BufReader;
+use std::process;
+
+use chess_engine_rust::chess::book::builder::BookBuilder;
+use chess_engine_rust::chess::pgn::{PgnGame, PgnReader};
+
+const USAGE: &str = "Usage: build_book <output> <pgn>... [--max-ply N] [--min-count N] \
+                     [--min-elo N] [--winners-only]";
+
+struct Args {
+    output: String,
+    pgn_files: Vec<String>,
+    max_ply: usize,
+    min_count: u32,
+    min_elo: Option<u32>,
+    winners_only: bool,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut args = env::args().skip(1);
+
+    let mut parsed = Args {
+        output: String::new(),
+        pgn_files: Vec::new(),
+        max_ply: 24,
+        min_count: 3,
+        min_elo: None,
+        winners_only: false,
+    };
+
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("Missing value for {arg}"));
+        let invalid = || format!("Invalid value for {arg}");
+
+        match arg.as_str() {
+            "--max-ply" => parsed.max_ply = value()?.parse().map_err(|_| invalid())?,
+            "--min-count" => parsed.min_count = value()?.parse().map_err(|_| invalid())?,
+            "--min-elo" => parsed.min_elo = Some(value()?.parse().map_err(|_| invalid())?),
+            "--winners-only" => parsed.winners_only = true,
+            _ if arg.starts_with("--") => return Err(format!("Unexpected argument: {arg}")),
+            _ if parsed.output.is_empty() => parsed.output = arg,
+            _ => parsed.pgn_files.push(arg),
+        }
+    }
+
+    if parsed.output.is_empty() {
+        return Err(String::from("Missing output file"));
+    }
+
+    if parsed.pgn_files.is_empty() {
+        return Err(String::from("Missing PGN files"));
+    }
+
+    Ok(parsed)
+}
+
+/// Both players are rated at least `min_elo`. Games without ratings fail.
+fn is_rated_at_least(game: &PgnGame, min_elo: u32) -> bool {
+    ["WhiteElo", "BlackElo"].iter().all(|tag| {
+        game.tag(tag)
+            .and_then(|elo| elo.parse::<u32>().ok())
+            .is_some_and(|elo| elo >= min_elo)
+    })
+}
+
+/// Reads every game of the PGN files and writes the moves played up to
+/// `--max-ply` as a Polyglot book, weighted by the points they scored.
+fn main() {
+    let args = parse_args().unwrap_or_else(|err| {
+        eprintln!("{err}\n{USAGE}");
+        process::exit(1);
+    });
+
+    let mut builder = BookBuilder::new(args.max_ply);
+    builder.min_count = args.min_count;
+    builder.winners_only = args.winners_only;
+
+    let mut games = 0;
+    let mut skipped = 0;
+
+    for path in &args.pgn_files {
+        let file = File::open(path).unwrap_or_else(|err| {
+            eprintln!("{path}: {err}");
+            process::exit(1);
+        });
+
+        for game in PgnReader::new(BufReader::new(file)) {
+            let game = match game {
+                Ok(game) => game,
+                Err(err) => {
+                    eprintln!("{path}: skipping game: {err}");
+                    skipped += 1;
+                    continue;
+                }
+            };
+
+            let result = match game.score() {
+                Some(result) => result,
+                None => {
+                    skipped += 1;
+                    continue;
+                }
+            };
+
+            if args
+                .min_elo
+                .is_some_and(|min_elo| !is_rated_at_least(&game, min_elo))
+            {
+                skipped += 1;
+                continue;
+            }
+
+            builder.add_game(&game.start, &game.mainline(), result);
+            games += 1;
+        }
+    }
+
+    let entries = builder.write(&args.output).unwrap_or_else(|err| {
+        eprintln!("{err}");
+        process::exit(1);
+    });
+
+    println!("Read {games} games, skipped {skipped}. Wrote {entries} book entries.");
+}
@@ -0,0 +1,137 @@
+use std::env;
+use std::process;
+
+use chess_engine_rust::chess::board::{Board, GameStatus};
+use chess_engine_rust::chess::options::EngineOptions;
+use chess_engine_rust::chess::search::skill::MAX_SKILL_LEVEL;
+use chess_engine_rust::chess::tournament::sprt::MatchScore;
+use chess_engine_rust::chess::tournament::{
+    load_openings, play_game, EnginePlayer, Player, TimeControl,
+};
+
+const USAGE: &str = "Usage: calibrate [--games N] [--openings FILE] [--movetime MS] \
+                     [--anchor ELO] [--levels FIRST-LAST]";
+
+struct Args {
+    games: u32,
+    openings: Option<String>,
+    movetime: u64,
+    anchor: f64,
+    levels: (usize, usize),
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut args = env::args().skip(1);
+
+    let mut parsed = Args {
+        games: 20,
+        openings: None,
+        movetime: 100,
+        anchor: 2600.0,
+        levels: (0, MAX_SKILL_LEVEL - 1),
+    };
+
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("Missing value for {arg}"));
+        let invalid = || format!("Invalid value for {arg}");
+
+        match arg.as_str() {
+            "--games" => parsed.games = value()?.parse().map_err(|_| invalid())?,
+            "--openings" => parsed.openings = Some(value()?),
+            "--movetime" => parsed.movetime = value()?.parse().map_err(|_| invalid())?,
+            "--anchor" => parsed.anchor = value()?.parse().map_err(|_| invalid())?,
+            "--levels" => {
+                let levels = value()?;
+                let (first, last) = levels.split_once('-').ok_or_else(invalid)?;
+
+                parsed.levels = (
+                    first.parse().map_err(|_| invalid())?,
+                    last.parse().map_err(|_| invalid())?,
+                );
+            }
+            _ => return Err(format!("Unexpected argument: {arg}")),
+        }
+    }
+
+    if parsed.levels.0 > parsed.levels.1 || parsed.levels.1 >= MAX_SKILL_LEVEL {
+        return Err(String::from("Invalid level range"));
+    }
+
+    Ok(parsed)
+}
+
+/// Plays each skill level against the full-strength engine and estimates its
+/// Elo relative to `--anchor`, the assumed rating of full strength. Prints the
+/// results as a `LEVEL_ELO` table for the skill module.
+fn main() {
+    let args = parse_args().unwrap_or_else(|err| {
+        eprintln!("{err}\n{USAGE}");
+        process::exit(1);
+    });
+
+    let openings = match &args.openings {
+        Some(path) => load_openings(path).unwrap_or_else(|err| {
+            eprintln!("{err}");
+            process::exit(1);
+        }),
+        None => vec![Board::from_fen("start").unwrap()],
+    };
+
+    if openings.is_empty() {
+        eprintln!("The opening suite is empty");
+        process::exit(1);
+    }
+
+    let time_control = TimeControl::MoveTime(args.movetime);
+    let mut full_strength = EnginePlayer::new("full strength", EngineOptions::default());
+    let mut level_elo = Vec::new();
+
+    for level in args.levels.0..=args.levels.1 {
+        let options = EngineOptions {
+            skill_level: level,
+            ..Default::default()
+        };
+
+        let mut limited = EnginePlayer::new(&format!("level {level}"), options);
+        let mut score = MatchScore::default();
+
+        for game_index in 0..args.games {
+            let opening = &openings[(game_index / 2) as usize % openings.len()];
+            let limited_is_white = game_index % 2 == 0;
+
+            let (white, black): (&mut dyn Player, &mut dyn Player) = if limited_is_white {
+                (&mut limited, &mut full_strength)
+            } else {
+                (&mut full_strength, &mut limited)
+            };
+
+            let record = match play_game(white, black, opening, time_control, 400) {
+                Ok(record) => record,
+                Err(err) => {
+                    eprintln!("Game aborted: {err}");
+                    continue;
+                }
+            };
+
+            match (record.result, limited_is_white) {
+                (GameStatus::WhiteWin, true) | (GameStatus::BlackWin, false) => score.wins += 1,
+                (GameStatus::WhiteWin, false) | (GameStatus::BlackWin, true) => score.losses += 1,
+                _ => score.draws += 1,
+            }
+        }
+
+        // A level that never scores, or never drops a point, has no finite Elo.
+        let difference = match score.elo() {
+            Some((elo, _)) => elo,
+            None if score.score() >= 1.0 => 800.0,
+            None => -800.0,
+        };
+        let elo = (args.anchor + difference).round() as i32;
+
+        println!("Level {level}: {score}, estimated Elo {elo}");
+        level_elo.push(elo);
+    }
+
+    let table: Vec<String> = level_elo.iter().map(|elo| elo.to_string()).collect();
+    println!("LEVEL_ELO = [{}]", table.join(", "));
+}
@@ -0,0 +1,156 @@
+use std::env;
+use std::process;
+use std::sync::mpsc;
+use std::thread;
+
+use chess_engine_rust::chess::board::Board;
+use chess_engine_rust::chess::datagen::{DataFormat, DataGenerator, DataWriter};
+use chess_engine_rust::chess::options::EngineOptions;
+use chess_engine_rust::chess::tournament::load_openings;
+
+const USAGE: &str = "Usage: datagen <output> [--games N] [--nodes N] [--random-plies N] \
+                     [--max-plies N] [--openings FILE] [--format binary|text] [--threads N] \
+                     [--seed N] [--keep-checks] [--keep-captures]";
+
+struct Args {
+    output: String,
+    games: u32,
+    nodes: u64,
+    random_plies: usize,
+    max_plies: usize,
+    openings: Option<String>,
+    format: DataFormat,
+    threads: u32,
+    seed: u64,
+    keep_checks: bool,
+    keep_captures: bool,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut args = env::args().skip(1);
+
+    let mut parsed = Args {
+        output: String::new(),
+        games: 100,
+        nodes: 5000,
+        random_plies: 8,
+        max_plies: 400,
+        openings: None,
+        format: DataFormat::Binary,
+        threads: 1,
+        seed: rand::random(),
+        keep_checks: false,
+        keep_captures: false,
+    };
+
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("Missing value for {arg}"));
+        let invalid = || format!("Invalid value for {arg}");
+
+        match arg.as_str() {
+            "--games" => parsed.games = value()?.parse().map_err(|_| invalid())?,
+            "--nodes" => parsed.nodes = value()?.parse().map_err(|_| invalid())?,
+            "--random-plies" => parsed.random_plies = value()?.parse().map_err(|_| invalid())?,
+            "--max-plies" => parsed.max_plies = value()?.parse().map_err(|_| invalid())?,
+            "--openings" => parsed.openings = Some(value()?),
+            "--format" => parsed.format = DataFormat::parse(&value()?).ok_or_else(invalid)?,
+            "--threads" => parsed.threads = value()?.parse().map_err(|_| invalid())?,
+            "--seed" => parsed.seed = value()?.parse().map_err(|_| invalid())?,
+            "--keep-checks" => parsed.keep_checks = true,
+            "--keep-captures" => parsed.keep_captures = true,
+            _ if parsed.output.is_empty() && !arg.starts_with("--") => parsed.output = arg,
+            _ => return Err(format!("Unexpected argument: {arg}")),
+        }
+    }
+
+    if parsed.output.is_empty() {
+        return Err(String::from("Missing output file"));
+    }
+
+    if parsed.threads == 0 {
+        return Err(String::from("Invalid thread count"));
+    }
+
+    Ok(parsed)
+}
+
+/// Plays self-play games on `--threads` threads, each with its own single
+/// threaded searcher, and writes the recorded positions as they arrive.
+fn main() {
+    let args = parse_args().unwrap_or_else(|err| {
+        eprintln!("{err}\n{USAGE}");
+        process::exit(1);
+    });
+
+    let openings = match &args.openings {
+        Some(path) => load_openings(path).unwrap_or_else(|err| {
+            eprintln!("{err}");
+            process::exit(1);
+        }),
+        None => vec![Board::from_fen("start").unwrap()],
+    };
+
+    if openings.is_empty() {
+        eprintln!("The opening suite is empty");
+        process::exit(1);
+    }
+
+    let mut writer = DataWriter::create(&args.output, args.format).unwrap_or_else(|err| {
+        eprintln!("{err}");
+        process::exit(1);
+    });
+
+    let (sender, receiver) = mpsc::channel();
+
+    thread::scope(|scope| {
+        for thread_id in 0..args.threads {
+            let sender = sender.clone();
+            let openings = &openings;
+            let args = &args;
+
+            scope.spawn(move || {
+                let mut generator = DataGenerator::new(
+                    EngineOptions::default(),
+                    args.seed.wrapping_add(thread_id as u64),
+                );
+                generator.nodes = args.nodes;
+                generator.random_plies = args.random_plies;
+                generator.max_plies = args.max_plies;
+                generator.filters.skip_in_check = !args.keep_checks;
+                generator.filters.skip_pending_captures = !args.keep_captures;
+
+                for game_index in (thread_id..args.games).step_by(args.threads as usize) {
+                    let opening = &openings[game_index as usize % openings.len()];
+
+                    if sender.send(generator.play_game(opening)).is_err() {
+                        return;
+                    }
+                }
+            });
+        }
+
+        drop(sender);
+
+        for (game_index, positions) in receiver.iter().enumerate() {
+            for position in &positions {
+                if let Err(err) = writer.write(position) {
+                    eprintln!("{err}");
+                    process::exit(1);
+                }
+            }
+
+            println!(
+                "Game {}/{}: {} positions, {} total",
+                game_index + 1,
+                args.games,
+                positions.len(),
+                writer.count
+            );
+        }
+    });
+
+    if let Err(err) = writer.flush() {
+        eprintln!("{err}");
+        process::exit(1);
+    }
+}
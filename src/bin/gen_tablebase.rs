@@ -0,0 +1,81 @@
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process;
+
+use chess_engine_rust::chess::tablebase::{Signature, Tablebase, MAX_PIECES};
+
+const USAGE: &str = "Usage: gen_tablebase <dir> [signature]... [--pieces N]";
+
+struct Args {
+    dir: String,
+    signatures: Vec<Signature>,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut args = env::args().skip(1);
+
+    let mut parsed = Args {
+        dir: String::new(),
+        signatures: Vec::new(),
+    };
+
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("Missing value for {arg}"));
+        let invalid = || format!("Invalid value for {arg}");
+
+        match arg.as_str() {
+            "--pieces" => {
+                let pieces: usize = value()?.parse().map_err(|_| invalid())?;
+
+                if !(3..=MAX_PIECES).contains(&pieces) {
+                    return Err(invalid());
+                }
+
+                parsed.signatures.extend(Signature::all(pieces));
+            }
+            _ if arg.starts_with("--") => return Err(format!("Unexpected argument: {arg}")),
+            _ if parsed.dir.is_empty() => parsed.dir = arg,
+            _ => parsed
+                .signatures
+                .push(Signature::parse(&arg).map_err(|err| err.to_string())?),
+        }
+    }
+
+    if parsed.dir.is_empty() {
+        return Err(String::from("Missing tablebase directory"));
+    }
+
+    if parsed.signatures.is_empty() {
+        return Err(String::from("Missing signatures"));
+    }
+
+    Ok(parsed)
+}
+
+/// Generates the tables for the given signatures, and the smaller ones they
+/// need, into the tablebase directory. Tables already there are kept.
+fn main() {
+    let args = parse_args().unwrap_or_else(|err| {
+        eprintln!("{err}\n{USAGE}");
+        process::exit(1);
+    });
+
+    let fail = |err: &dyn std::fmt::Display| -> ! {
+        eprintln!("{}: {err}", args.dir);
+        process::exit(1);
+    };
+
+    fs::create_dir_all(&args.dir).unwrap_or_else(|err| fail(&err));
+    let mut tablebase = Tablebase::load_dir(&args.dir).unwrap_or_else(|err| fail(&err));
+
+    for signature in &args.signatures {
+        for generated in tablebase.generate(signature) {
+            let table = tablebase.table(&generated).unwrap();
+            let path = Path::new(&args.dir).join(table.file_name());
+
+            table.save(&path).unwrap_or_else(|err| fail(&err));
+            println!("{generated}: {}", path.display());
+        }
+    }
+}
@@ -0,0 +1,225 @@
+use std::env;
+use std::process;
+
+use chess_engine_rust::chess::board::{Board, GameStatus};
+use chess_engine_rust::chess::options::EngineOptions;
+use chess_engine_rust::chess::tournament::sprt::{MatchScore, Sprt, SprtVerdict};
+use chess_engine_rust::chess::tournament::uci::UciEngine;
+use chess_engine_rust::chess::tournament::{
+    load_openings, play_game, EnginePlayer, MatchError, Player, TimeControl,
+};
+
+const USAGE: &str = "Usage: match <engine1> <engine2> [--games N] [--openings FILE] \
+                     [--depth N | --nodes N | --movetime MS | --tc BASE+INC] [--max-plies N] \
+                     [--elo0 E] [--elo1 E] [--alpha A] [--beta B]\n\
+                     Engines are `builtin` or `uci:COMMAND`, followed by `,Name=Value` options.";
+
+struct Args {
+    engines: Vec<String>,
+    games: u32,
+    openings: Option<String>,
+    time_control: TimeControl,
+    max_plies: usize,
+    sprt: Sprt,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut args = env::args().skip(1);
+
+    let mut parsed = Args {
+        engines: Vec::new(),
+        games: 1000,
+        openings: None,
+        time_control: TimeControl::Depth(4),
+        max_plies: 400,
+        sprt: Sprt::default(),
+    };
+
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("Missing value for {arg}"));
+        let invalid = || format!("Invalid value for {arg}");
+
+        match arg.as_str() {
+            "--games" => parsed.games = value()?.parse().map_err(|_| invalid())?,
+            "--openings" => parsed.openings = Some(value()?),
+            "--depth" => {
+                parsed.time_control = TimeControl::Depth(value()?.parse().map_err(|_| invalid())?)
+            }
+            "--nodes" => {
+                parsed.time_control = TimeControl::Nodes(value()?.parse().map_err(|_| invalid())?)
+            }
+            "--movetime" => {
+                parsed.time_control =
+                    TimeControl::MoveTime(value()?.parse().map_err(|_| invalid())?)
+            }
+            "--tc" => parsed.time_control = parse_clock(&value()?).ok_or_else(invalid)?,
+            "--max-plies" => parsed.max_plies = value()?.parse().map_err(|_| invalid())?,
+            "--elo0" => parsed.sprt.elo0 = value()?.parse().map_err(|_| invalid())?,
+            "--elo1" => parsed.sprt.elo1 = value()?.parse().map_err(|_| invalid())?,
+            "--alpha" => parsed.sprt.alpha = value()?.parse().map_err(|_| invalid())?,
+            "--beta" => parsed.sprt.beta = value()?.parse().map_err(|_| invalid())?,
+            _ if parsed.engines.len() < 2 && !arg.starts_with("--") => parsed.engines.push(arg),
+            _ => return Err(format!("Unexpected argument: {arg}")),
+        }
+    }
+
+    if parsed.engines.len() != 2 {
+        return Err(String::from("Expected two engines"));
+    }
+
+    Ok(parsed)
+}
+
+/// Parses a `BASE+INC` clock given in seconds, e.g. `10+0.1`.
+fn parse_clock(clock: &str) -> Option<TimeControl> {
+    let (base, increment) = clock.split_once('+').unwrap_or((clock, "0"));
+
+    let base = base.parse::<f64>().ok()?;
+    let increment = increment.parse::<f64>().ok()?;
+
+    Some(TimeControl::Clock {
+        base: (base * 1000.0) as u64,
+        increment: (increment * 1000.0) as u64,
+    })
+}
+
+/// Builds a player from `builtin,Name=Value,...` or `uci:COMMAND,Name=Value,...`.
+fn create_player(spec: &str, default_name: &str) -> Result<Box<dyn Player>, String> {
+    let mut parts = spec.split(',');
+    let kind = parts.next().unwrap_or_default();
+
+    let options = parts
+        .map(|option| {
+            option
+                .split_once('=')
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .ok_or(format!("Malformed option: {option}"))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if let Some(command) = kind.strip_prefix("uci:") {
+        let engine = UciEngine::spawn(command, &options).map_err(|err| err.to_string())?;
+        return Ok(Box::new(engine));
+    }
+
+    if kind != "builtin" {
+        return Err(format!("Unknown engine: {kind}"));
+    }
+
+    let mut engine_options = EngineOptions::default();
+
+    for (name, value) in &options {
+        engine_options
+            .set_option(name, value)
+            .map_err(|err| err.to_string())?;
+    }
+
+    let engine = EnginePlayer::new(default_name, engine_options);
+
+    for warning in engine.warnings() {
+        eprintln!("{default_name}: {warning}");
+    }
+
+    Ok(Box::new(engine))
+}
+
+fn main() {
+    let args = parse_args().unwrap_or_else(|err| {
+        eprintln!("{err}\n{USAGE}");
+        process::exit(1);
+    });
+
+    let openings = match &args.openings {
+        Some(path) => load_openings(path).unwrap_or_else(|err| {
+            eprintln!("{err}");
+            process::exit(1);
+        }),
+        None => vec![Board::from_fen("start").unwrap()],
+    };
+
+    if openings.is_empty() {
+        eprintln!("The opening suite is empty");
+        process::exit(1);
+    }
+
+    let mut players = [
+        create_player(&args.engines[0], "engine1"),
+        create_player(&args.engines[1], "engine2"),
+    ]
+    .map(|player| {
+        player.unwrap_or_else(|err| {
+            eprintln!("{err}");
+            process::exit(1);
+        })
+    });
+
+    let (lower, upper) = args.sprt.bounds();
+    let mut score = MatchScore::default();
+
+    println!(
+        "{} vs {}, SPRT elo0 {} elo1 {} alpha {} beta {}",
+        players[0].name(),
+        players[1].name(),
+        args.sprt.elo0,
+        args.sprt.elo1,
+        args.sprt.alpha,
+        args.sprt.beta
+    );
+
+    for game_index in 0..args.games {
+        // Each opening is played twice with colors swapped.
+        let opening = &openings[(game_index / 2) as usize % openings.len()];
+        let first_is_white = game_index % 2 == 0;
+
+        let [first, second] = &mut players;
+
+        let (white, black): (&mut dyn Player, &mut dyn Player) = if first_is_white {
+            (first.as_mut(), second.as_mut())
+        } else {
+            (second.as_mut(), first.as_mut())
+        };
+
+        let record = match play_game(white, black, opening, args.time_control, args.max_plies) {
+            Ok(record) => record,
+            Err(err @ MatchError::Io(_)) | Err(err @ MatchError::EngineExited(_)) => {
+                eprintln!("{err}");
+                process::exit(1);
+            }
+            Err(err) => {
+                eprintln!("Game {} aborted: {err}", game_index + 1);
+                continue;
+            }
+        };
+
+        let white_name = white.name().to_string();
+        let black_name = black.name().to_string();
+
+        let (result, first_points) = match record.result {
+            GameStatus::WhiteWin => ("1-0", if first_is_white { 2 } else { 0 }),
+            GameStatus::BlackWin => ("0-1", if first_is_white { 0 } else { 2 }),
+            _ => ("1/2-1/2", 1),
+        };
+
+        match first_points {
+            2 => score.wins += 1,
+            1 => score.draws += 1,
+            _ => score.losses += 1,
+        }
+
+        let verdict = args.sprt.verdict(&score);
+
+        println!(
+            "Game {}: {white_name} - {black_name} {result} ({}, {} plies) | {score} | LLR {:.2} [{lower:.2}, {upper:.2}] {verdict}",
+            game_index + 1,
+            record.termination,
+            record.moves.len(),
+            args.sprt.llr(&score),
+        );
+
+        if verdict != SprtVerdict::Continue {
+            break;
+        }
+    }
+
+    println!("Final: {score}, SPRT {}", args.sprt.verdict(&score));
+}
@@ -0,0 +1,106 @@
+use std::env;
+use std::process;
+
+use chess_engine_rust::chess::epd::load_epd_file;
+use chess_engine_rust::chess::epd::suite::run_suite;
+use chess_engine_rust::chess::options::EngineOptions;
+use chess_engine_rust::chess::search::{SearchLimits, Searcher};
+
+const USAGE: &str = "Usage: test_suite <epd>... [--movetime MS] [--depth N] [--nodes N] \
+                     [--threads N] [--hash MB] [--quiet]";
+
+struct Args {
+    epd_files: Vec<String>,
+    limits: SearchLimits,
+    threads: usize,
+    hash_size_mb: usize,
+    quiet: bool,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut args = env::args().skip(1);
+
+    let mut parsed = Args {
+        epd_files: Vec::new(),
+        limits: SearchLimits::default(),
+        threads: 1,
+        hash_size_mb: EngineOptions::default().hash_size_mb,
+        quiet: false,
+    };
+
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("Missing value for {arg}"));
+        let invalid = || format!("Invalid value for {arg}");
+
+        match arg.as_str() {
+            "--movetime" => parsed.limits.movetime = Some(value()?.parse().map_err(|_| invalid())?),
+            "--depth" => parsed.limits.depth = Some(value()?.parse().map_err(|_| invalid())?),
+            "--nodes" => parsed.limits.nodes = Some(value()?.parse().map_err(|_| invalid())?),
+            "--threads" => parsed.threads = value()?.parse().map_err(|_| invalid())?,
+            "--hash" => parsed.hash_size_mb = value()?.parse().map_err(|_| invalid())?,
+            "--quiet" => parsed.quiet = true,
+            _ if !arg.starts_with("--") => parsed.epd_files.push(arg),
+            _ => return Err(format!("Unexpected argument: {arg}")),
+        }
+    }
+
+    if parsed.epd_files.is_empty() {
+        return Err(String::from("Missing EPD file"));
+    }
+
+    if parsed.threads == 0 {
+        return Err(String::from("Invalid thread count"));
+    }
+
+    // Without a limit every position would be searched to the default depth,
+    // so give each one a second, as most suites expect.
+    if parsed.limits.movetime.is_none()
+        && parsed.limits.depth.is_none()
+        && parsed.limits.nodes.is_none()
+    {
+        parsed.limits.movetime = Some(1000);
+    }
+
+    Ok(parsed)
+}
+
+/// Runs each EPD suite and prints the result of every position, followed by
+/// the number solved and, for STS suites, the score.
+fn main() {
+    let args = parse_args().unwrap_or_else(|err| {
+        eprintln!("{err}\n{USAGE}");
+        process::exit(1);
+    });
+
+    let options = EngineOptions {
+        threads: args.threads,
+        hash_size_mb: args.hash_size_mb,
+        ..Default::default()
+    };
+
+    let mut searcher = Searcher::new(options);
+
+    for warning in searcher.warnings() {
+        eprintln!("{warning}");
+    }
+
+    for path in &args.epd_files {
+        let positions = load_epd_file(path).unwrap_or_else(|err| {
+            eprintln!("{path}: {err}");
+            process::exit(1);
+        });
+
+        let report = run_suite(
+            &mut searcher,
+            &positions,
+            &args.limits,
+            |result| match result {
+                Ok(result) if !args.quiet => println!("{result}"),
+                Ok(_) => (),
+                Err(err) => eprintln!("{path}: skipping position: {err}"),
+            },
+        );
+
+        println!("{path}: {report}");
+    }
+}
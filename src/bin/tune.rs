@@ -0,0 +1,109 @@
+use std::env;
+use std::process;
+
+use chess_engine_rust::chess::evaluation::params::{default_params, EvalParams};
+use chess_engine_rust::chess::evaluation::tuning::{load_positions, Tuner};
+
+const USAGE: &str = "Usage: tune <positions> [--epochs N] [--learning-rate R] \
+                     [--start PARAMS] [--params OUT] [--rust OUT]";
+
+struct Args {
+    positions: String,
+    epochs: usize,
+    learning_rate: f64,
+    start: Option<String>,
+    params_out: String,
+    rust_out: Option<String>,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut args = env::args().skip(1);
+
+    let mut parsed = Args {
+        positions: String::new(),
+        epochs: 1000,
+        learning_rate: 1.0,
+        start: None,
+        params_out: String::from("tuned_params.txt"),
+        rust_out: None,
+    };
+
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("Missing value for {arg}"));
+
+        match arg.as_str() {
+            "--epochs" => parsed.epochs = value()?.parse().map_err(|_| "Invalid epoch count")?,
+            "--learning-rate" => {
+                parsed.learning_rate = value()?.parse().map_err(|_| "Invalid learning rate")?
+            }
+            "--start" => parsed.start = Some(value()?),
+            "--params" => parsed.params_out = value()?,
+            "--rust" => parsed.rust_out = Some(value()?),
+            _ if parsed.positions.is_empty() && !arg.starts_with("--") => parsed.positions = arg,
+            _ => return Err(format!("Unexpected argument: {arg}")),
+        }
+    }
+
+    if parsed.positions.is_empty() {
+        return Err(String::from("Missing positions file"));
+    }
+
+    Ok(parsed)
+}
+
+fn main() {
+    let args = parse_args().unwrap_or_else(|err| {
+        eprintln!("{err}\n{USAGE}");
+        process::exit(1);
+    });
+
+    let start = match &args.start {
+        Some(path) => EvalParams::load(path).unwrap_or_else(|err| {
+            eprintln!("{err}");
+            process::exit(1);
+        }),
+        None => default_params().clone(),
+    };
+
+    let positions = load_positions(&args.positions).unwrap_or_else(|err| {
+        eprintln!("{err}");
+        process::exit(1);
+    });
+
+    println!("Loaded {} positions.", positions.len());
+
+    let mut tuner = Tuner::new(positions, &start);
+    tuner.learning_rate = args.learning_rate;
+    tuner.fit_scaling_constant();
+
+    println!("Scaling constant: {:.2}", tuner.scaling_constant);
+    println!("Initial error: {:.6}", tuner.error());
+
+    for epoch in 1..=args.epochs {
+        let error = tuner.step();
+
+        if epoch % 50 == 0 || epoch == args.epochs {
+            println!("Epoch {epoch}: error {error:.6}");
+
+            if let Err(err) = tuner.params().save(&args.params_out) {
+                eprintln!("Failed to write {}: {err}", args.params_out);
+            }
+        }
+    }
+
+    let params = tuner.params();
+
+    if let Err(err) = params.save(&args.params_out) {
+        eprintln!("Failed to write {}: {err}", args.params_out);
+        process::exit(1);
+    }
+
+    if let Some(rust_out) = &args.rust_out {
+        if let Err(err) = std::fs::write(rust_out, params.to_rust_source()) {
+            eprintln!("Failed to write {rust_out}: {err}");
+            process::exit(1);
+        }
+    }
+
+    println!("Final error: {:.6}", tuner.error());
+}
@@ -1,10 +1,15 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
 use super::{
     Position,
-    piece::{Piece, PieceKind},
+    piece::{Color, Piece, PieceKind},
     Board,
 };
 
-#[derive(Debug, Copy, Clone)]
+#[allow(clippy::enum_variant_names)]
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Move {
     CastleKingside,
     CastleQueenside,
@@ -13,10 +18,14 @@ pub enum Move {
         origin_square: Position,
         target_square: Position,
         origin_piece: Piece,
-        target_piece: Option<Piece>
+        target_piece: Option<Piece>,
+        promotion: Option<PieceKind>
     }
 }
 
+/// The pieces a pawn may promote to, the usual choice first.
+pub const PROMOTION_KINDS: [PieceKind; 4] = [PieceKind::Queen, PieceKind::Knight, PieceKind::Rook, PieceKind::Bishop];
+
 impl Move { 
     pub fn from_lan(board: &Board, lan: &str) -> Result<Self, MoveError> {
         match lan.to_uppercase().as_str() {
@@ -31,29 +40,113 @@ impl Move {
 
         let (origin_square, target_square) = lan.split_at(2);
         
-        let origin_rank = 8 - origin_square.chars().nth(1).unwrap() as usize - 48;
+        let origin_rank = 8 - (origin_square.chars().nth(1).unwrap() as usize - 48);
         let origin_file = origin_square.chars().next().unwrap() as usize - 97;
 
-        let target_rank = 8 - target_square.chars().nth(1).unwrap() as usize - 48;
+        let target_rank = 8 - (target_square.chars().nth(1).unwrap() as usize - 48);
         let target_file = target_square.chars().next().unwrap() as usize - 97;
 
+        let origin_square = (origin_rank, origin_file);
+        let target_square = (target_rank, target_file);
+
         let origin_piece = board.state[origin_rank][origin_file].unwrap();
-        let target_piece = board.state[target_rank][target_file];
+        let target_piece = board.state[target_rank][target_file]
+            .or_else(|| board.en_passant_victim(origin_piece, origin_square, target_square));
+
+        let promotion = if is_promotion_square(origin_piece, target_rank) {
+            Some(PieceKind::Queen)
+        } else {
+            None
+        };
+
+        Ok(Self::PieceMove {
+            origin_square,
+            target_square,
+            origin_piece,
+            target_piece,
+            promotion
+        })
+    }
+
+    /// Parses a move in UCI notation, e.g. `e2e4`, `e1g1` or `e7e8q`.
+    pub fn from_uci(board: &Board, uci: &str) -> Result<Self, MoveError> {
+        let malformed = || MoveError::MalformedLANString(uci.to_string());
+
+        if uci.len() != 4 && uci.len() != 5 {
+            return Err(malformed());
+        }
+
+        let origin_square = uci.get(0..2).and_then(parse_square).ok_or_else(malformed)?;
+        let target_square = uci.get(2..4).and_then(parse_square).ok_or_else(malformed)?;
+
+        let promotion = match uci.chars().nth(4) {
+            Some('q') | None => PieceKind::Queen,
+            Some('r') => PieceKind::Rook,
+            Some('b') => PieceKind::Bishop,
+            Some('n') => PieceKind::Knight,
+            Some(piece) => return Err(MoveError::UnsupportedPromotion(piece)),
+        };
+
+        let origin_piece = board.state[origin_square.0][origin_square.1].ok_or_else(malformed)?;
+        let target_piece = board.state[target_square.0][target_square.1]
+            .or_else(|| board.en_passant_victim(origin_piece, origin_square, target_square));
+
+        if origin_piece.piece_kind == PieceKind::King && origin_square.1 == 4 {
+            match target_square.1 {
+                6 if origin_square.0 == target_square.0 => return Ok(Self::CastleKingside),
+                2 if origin_square.0 == target_square.0 => return Ok(Self::CastleQueenside),
+                _ => (),
+            }
+        }
+
+        let promotion = if is_promotion_square(origin_piece, target_square.0) {
+            Some(promotion)
+        } else {
+            None
+        };
 
         Ok(Self::PieceMove {
-            origin_square: (origin_rank, origin_file),
-            target_square: (target_rank, target_file),
+            origin_square,
+            target_square,
             origin_piece,
-            target_piece
+            target_piece,
+            promotion,
         })
     }
 
+    /// Formats the move in UCI notation. Castling is written as the king's move,
+    /// which depends on the side castling.
+    pub fn to_uci(self, side: Color) -> String {
+        let back_rank = match side {
+            Color::White => 7,
+            Color::Black => 0,
+        };
+
+        match self {
+            Self::CastleKingside => {
+                square_to_string((back_rank, 4)) + &square_to_string((back_rank, 6))
+            },
+            Self::CastleQueenside => {
+                square_to_string((back_rank, 4)) + &square_to_string((back_rank, 2))
+            },
+
+            Self::PieceMove { origin_square, target_square, promotion, .. } => {
+                let mut uci = square_to_string(origin_square) + &square_to_string(target_square);
+
+                if let Some(promotion) = promotion {
+                    uci.push(Piece::new(promotion, Color::Black).to_char());
+                }
+                uci
+            }
+        }
+    }
+
     pub fn to_str(self) -> String {
         match self {
             Self::CastleKingside => String::from("O-O"),
             Self::CastleQueenside => String::from("O-O-O"),
 
-            Self::PieceMove { origin_square, target_square, origin_piece, target_piece } => {
+            Self::PieceMove { origin_square, target_square, origin_piece, target_piece, promotion } => {
                 let mut str = String::new();
                 let piece_char = origin_piece.to_char().to_ascii_uppercase();
 
@@ -74,15 +167,16 @@ impl Move {
                     if rank_offset == 1 && file_offset == 1 {
                         str.push(origin_file);
                         str.push('x');
-                        str.push(target_file);
-                        str.push(target_rank);
-
-                        return str;
                     }
 
                     str.push(target_file);
                     str.push(target_rank);
 
+                    if let Some(promotion) = promotion {
+                        str.push('=');
+                        str.push(Piece::new(promotion, Color::White).to_char());
+                    }
+
                     return str;
                 }
 
@@ -105,9 +199,45 @@ impl Move {
     }
 }
 
+/// Whether a move of `piece` to `target_rank` is a pawn reaching the last rank.
+pub fn is_promotion_square(piece: Piece, target_rank: usize) -> bool {
+    let promotion_rank = match piece.color {
+        Color::White => 0,
+        Color::Black => 7,
+    };
+
+    piece.piece_kind == PieceKind::Pawn && target_rank == promotion_rank
+}
+
+/// Formats a square as its algebraic name, e.g. `(7, 4)` as `e1`.
+pub fn square_to_string(square: Position) -> String {
+    let file = (square.1 as u8 + 97) as char;
+    let rank = (b'8' - square.0 as u8) as char;
+
+    format!("{file}{rank}")
+}
+
+/// Parses an algebraic square name such as `e4`.
+pub fn parse_square(square: &str) -> Option<Position> {
+    let mut chars = square.chars();
+
+    let file = chars.next()?;
+    let rank = chars.next()?;
+
+    if chars.next().is_some() || !('a'..='h').contains(&file) || !('1'..='8').contains(&rank) {
+        return None;
+    }
+
+    Some((8 - (rank as usize - 48), file as usize - 97))
+}
+
+#[allow(clippy::enum_variant_names)]
 #[derive(Debug)]
 pub enum MoveError {
     MalformedLANString(String),
+    MalformedSANString(String),
+    AmbiguousSANString(String),
+    UnsupportedPromotion(char),
     IllegalMoveError,
 }
 
@@ -118,6 +248,18 @@ impl std::fmt::Display for MoveError {
                 write!(f, "Invalid LAN String: '{lan}'")
             },
 
+            Self::MalformedSANString(san) => {
+                write!(f, "Invalid SAN String: '{san}'")
+            },
+
+            Self::AmbiguousSANString(san) => {
+                write!(f, "Ambiguous SAN String: '{san}'")
+            },
+
+            Self::UnsupportedPromotion(piece) => {
+                write!(f, "Unsupported promotion piece: '{piece}'")
+            },
+
             Self::IllegalMoveError => {
                 write!(f, "Illegal move.")
             } 
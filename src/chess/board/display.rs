@@ -0,0 +1,347 @@
+use std::fmt;
+
+use image::Rgba;
+
+use super::{
+    chess_move::Move, Board, Color, PieceKind, Position, BLACK_KING_ORIGIN, BOARD_BACKGROUND_COLOR,
+    BOARD_FOREGROUND_COLOR, BOARD_SIZE, WHITE_KING_ORIGIN,
+};
+
+const LIGHT_HIGHLIGHT_COLOR: Rgba<u8> = Rgba([205, 210, 106, u8::MAX]);
+const DARK_HIGHLIGHT_COLOR: Rgba<u8> = Rgba([170, 162, 58, u8::MAX]);
+
+const WHITE_PIECE_COLOR: Rgba<u8> = Rgba([255, 255, 255, u8::MAX]);
+const BLACK_PIECE_COLOR: Rgba<u8> = Rgba([0, 0, 0, u8::MAX]);
+
+const ANSI_RESET: &str = "\x1b[0m";
+
+/// How a board is drawn as text.
+#[derive(Debug, Clone)]
+pub struct DisplayOptions {
+    /// Chess figurines instead of FEN letters.
+    pub unicode: bool,
+    /// Rank numbers on the left and file letters below.
+    pub coordinates: bool,
+    /// Black at the bottom.
+    pub flipped: bool,
+    /// Square colours as ANSI escape codes, for terminals.
+    pub ansi_colors: bool,
+    /// Colours the squares of the last move, or puts them in brackets
+    /// without `ansi_colors`.
+    pub highlight_last_move: bool,
+}
+
+impl Default for DisplayOptions {
+    fn default() -> Self {
+        Self {
+            unicode: false,
+            coordinates: true,
+            flipped: false,
+            ansi_colors: false,
+            highlight_last_move: true,
+        }
+    }
+}
+
+/// A board drawn with `DisplayOptions`, from `Board::display`.
+pub struct BoardDisplay<'a> {
+    board: &'a Board,
+    options: DisplayOptions,
+}
+
+impl Board {
+    /// Draws the board as text, one rank per line:
+    ///
+    /// ```text
+    /// 8 r n b q k b n r
+    /// 7 p p p p p p p p
+    /// 6 . . . . . . . .
+    /// ```
+    ///
+    /// Formatting the board itself uses the default options, with figurines
+    /// for the alternate form `{:#}`.
+    pub fn display(&self, options: DisplayOptions) -> BoardDisplay<'_> {
+        BoardDisplay {
+            board: self,
+            options,
+        }
+    }
+
+    /// The origin and target squares of the last move, the king's for a
+    /// castle.
+    fn last_move_squares(&self) -> Option<[Position; 2]> {
+        let king_origin = match self.active_turn.opposite() {
+            Color::White => WHITE_KING_ORIGIN,
+            Color::Black => BLACK_KING_ORIGIN,
+        };
+
+        match *self.history.last()? {
+            Move::CastleKingside => Some([king_origin, (king_origin.0, 6)]),
+            Move::CastleQueenside => Some([king_origin, (king_origin.0, 2)]),
+
+            Move::PieceMove {
+                origin_square,
+                target_square,
+                ..
+            } => Some([origin_square, target_square]),
+        }
+    }
+}
+
+impl fmt::Display for Board {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let options = DisplayOptions {
+            unicode: f.alternate(),
+            ..DisplayOptions::default()
+        };
+
+        self.display(options).fmt(f)
+    }
+}
+
+impl fmt::Display for BoardDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let options = &self.options;
+
+        let highlighted = if options.highlight_last_move {
+            self.board.last_move_squares()
+        } else {
+            None
+        };
+
+        // Turning the board around reverses both the ranks and the files.
+        let order: Vec<usize> = if options.flipped {
+            (0..BOARD_SIZE).rev().collect()
+        } else {
+            (0..BOARD_SIZE).collect()
+        };
+
+        for &r_index in &order {
+            let is_highlighted =
+                |f_index| highlighted.is_some_and(|squares| squares.contains(&(r_index, f_index)));
+
+            if options.coordinates {
+                write!(f, "{}", BOARD_SIZE - r_index)?;
+
+                // Plain text writes the space as the separator of the first
+                // square instead.
+                if options.ansi_colors {
+                    write!(f, " ")?;
+                }
+            }
+
+            for (i, &f_index) in order.iter().enumerate() {
+                let piece = self.board.state[r_index][f_index];
+
+                // Coloured text tells the sides apart, so both get the solid
+                // figurines, which read better on coloured squares.
+                let symbol = match piece {
+                    Some(piece) if options.unicode && options.ansi_colors => {
+                        figurine(Color::Black, piece.piece_kind)
+                    }
+                    Some(piece) if options.unicode => figurine(piece.color, piece.piece_kind),
+                    Some(piece) => piece.to_char(),
+                    None => '.',
+                };
+
+                let highlight = is_highlighted(f_index);
+
+                // Without colours, the separators around a highlighted square
+                // turn into brackets.
+                if !options.ansi_colors {
+                    let after_highlight = i > 0 && is_highlighted(order[i - 1]);
+
+                    if i > 0 || options.coordinates || highlighted.is_some() {
+                        let separator = match (after_highlight, highlight) {
+                            (false, false) => ' ',
+                            (false, true) => '[',
+                            (true, false) => ']',
+                            (true, true) => '|',
+                        };
+
+                        write!(f, "{separator}")?;
+                    }
+
+                    write!(f, "{symbol}")?;
+
+                    if i == BOARD_SIZE - 1 && highlight {
+                        write!(f, "]")?;
+                    }
+                    continue;
+                }
+
+                let light = (r_index + f_index) % 2 == 0;
+
+                let background = match (light, highlight) {
+                    (true, false) => BOARD_BACKGROUND_COLOR,
+                    (false, false) => BOARD_FOREGROUND_COLOR,
+                    (true, true) => LIGHT_HIGHLIGHT_COLOR,
+                    (false, true) => DARK_HIGHLIGHT_COLOR,
+                };
+
+                let foreground = match piece.map(|piece| piece.color) {
+                    Some(Color::Black) => BLACK_PIECE_COLOR,
+                    _ => WHITE_PIECE_COLOR,
+                };
+
+                let symbol = if piece.is_some() { symbol } else { ' ' };
+
+                write!(
+                    f,
+                    "{}{} {symbol} ",
+                    ansi_color(48, background),
+                    ansi_color(38, foreground)
+                )?;
+            }
+
+            if options.ansi_colors {
+                write!(f, "{ANSI_RESET}")?;
+            }
+
+            writeln!(f)?;
+        }
+
+        if options.coordinates {
+            // Line the letters up with the squares, which start after the
+            // rank number and a space, plus a space of padding with colours.
+            if options.ansi_colors {
+                write!(f, "  ")?;
+            } else {
+                write!(f, " ")?;
+            }
+
+            for &f_index in &order {
+                let file = (b'a' + f_index as u8) as char;
+
+                if options.ansi_colors {
+                    write!(f, " {file} ")?;
+                } else {
+                    write!(f, " {file}")?;
+                }
+            }
+
+            writeln!(f)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn figurine(color: Color, kind: PieceKind) -> char {
+    match (color, kind) {
+        (Color::White, PieceKind::King) => '♔',
+        (Color::White, PieceKind::Queen) => '♕',
+        (Color::White, PieceKind::Rook) => '♖',
+        (Color::White, PieceKind::Bishop) => '♗',
+        (Color::White, PieceKind::Knight) => '♘',
+        (Color::White, PieceKind::Pawn) => '♙',
+        (Color::Black, PieceKind::King) => '♚',
+        (Color::Black, PieceKind::Queen) => '♛',
+        (Color::Black, PieceKind::Rook) => '♜',
+        (Color::Black, PieceKind::Bishop) => '♝',
+        (Color::Black, PieceKind::Knight) => '♞',
+        (Color::Black, PieceKind::Pawn) => '♟',
+    }
+}
+
+/// A 24-bit ANSI colour escape: layer 38 for the text, 48 for the background.
+fn ansi_color(layer: u8, color: Rgba<u8>) -> String {
+    let Rgba([red, green, blue, _]) = color;
+    format!("\x1b[{layer};2;{red};{green};{blue}m")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn after_move(lan: &str) -> Board {
+        let mut board = Board::from_fen("start").unwrap();
+        let m = Move::from_lan(&board, lan).unwrap();
+        board.move_piece(m).unwrap();
+        board
+    }
+
+    fn plain() -> DisplayOptions {
+        DisplayOptions::default()
+    }
+
+    fn strip_ansi(text: &str) -> String {
+        let mut stripped = String::new();
+        let mut chars = text.chars();
+
+        while let Some(c) = chars.next() {
+            if c == '\x1b' {
+                chars.by_ref().find(|&c| c == 'm');
+            } else {
+                stripped.push(c);
+            }
+        }
+        stripped
+    }
+
+    #[test]
+    fn draws_the_start_position() {
+        let board = Board::from_fen("start").unwrap();
+        let lines: Vec<String> = board.to_string().lines().map(String::from).collect();
+
+        assert_eq!(lines[0], "8 r n b q k b n r");
+        assert_eq!(lines[7], "1 R N B Q K B N R");
+        assert_eq!(lines[8], "  a b c d e f g h");
+    }
+
+    #[test]
+    fn brackets_the_last_move_without_colours() {
+        let board = after_move("e2e4");
+        let text = board.display(plain()).to_string();
+        let lines: Vec<&str> = text.lines().collect();
+
+        assert_eq!(lines[4], "4 . . . .[P]. . .");
+        assert_eq!(lines[6], "2 P P P P[.]P P P");
+        assert_eq!(lines[7], "1 R N B Q K B N R");
+
+        let flipped = board
+            .display(DisplayOptions {
+                flipped: true,
+                coordinates: false,
+                ..plain()
+            })
+            .to_string();
+
+        assert_eq!(flipped.lines().nth(3), Some(" . . .[P]. . . ."));
+        assert_eq!(flipped.lines().nth(7), Some(" r n b k q b n r"));
+    }
+
+    #[test]
+    fn brackets_adjacent_and_edge_squares() {
+        let board = after_move("g1h3");
+        let text = board.display(plain()).to_string();
+        let lines: Vec<&str> = text.lines().collect();
+
+        assert_eq!(lines[5], "3 . . . . . . .[N]");
+        assert_eq!(lines[7], "1 R N B Q K B[.]R");
+
+        let mut board = Board::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        let m = Move::from_lan(&board, "e1f1").unwrap();
+        board.move_piece(m).unwrap();
+
+        let text = board.display(plain()).to_string();
+
+        assert_eq!(text.lines().nth(7), Some("1 . . . .[.|K]. ."));
+    }
+
+    #[test]
+    fn file_letters_line_up_with_coloured_squares() {
+        let board = Board::from_fen("start").unwrap();
+        let text = board
+            .display(DisplayOptions {
+                ansi_colors: true,
+                ..plain()
+            })
+            .to_string();
+
+        let lines: Vec<String> = text.lines().map(strip_ansi).collect();
+
+        assert_eq!(lines[7].find('R'), lines[8].find('a'));
+        assert_eq!(lines[7].rfind('R'), lines[8].find('h'));
+    }
+}
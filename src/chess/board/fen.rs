@@ -1,12 +1,15 @@
 use std::fmt;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 use super::{
-    piece::{Piece, Color},
+    piece::{Piece, PieceKind, Color},
     Position
 };
 
 pub const START_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
 
 #[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct CastleFlags {
     pub white_kingside: bool,
     pub white_queenside: bool,
@@ -40,23 +43,42 @@ impl CastleFlags {
     }
 }
 
-pub fn parse_ranks(ranks: &str) -> [[Option<Piece>; 8]; 8] {
+pub fn parse_ranks(ranks: &str) -> Result<[[Option<Piece>; 8]; 8], ParseFenError> {
     let mut board_state = [[None; 8]; 8];
-    let ranks: Vec<&str> = ranks.split('/').collect();
+    let rank_fields: Vec<&str> = ranks.split('/').collect();
 
-    for (r_index, rank) in ranks.iter().enumerate() {
+    if rank_fields.len() != 8 {
+        return Err(ParseFenError::ParseRanks(ranks.to_string()));
+    }
+
+    for (r_index, rank) in rank_fields.iter().enumerate() {
         let mut f_index = 0;
 
         for c in rank.chars() {
-            if c.is_numeric() {
-                f_index += c.to_digit(10).unwrap() as usize;
-            } else {
-                board_state[r_index][f_index] = Some(Piece::from_char(c));
-                f_index += 1;
+            if let Some(empty_squares) = c.to_digit(10) {
+                f_index += empty_squares as usize;
+                continue;
+            }
+
+            if f_index >= 8 {
+                return Err(ParseFenError::ParseRanks(ranks.to_string()));
+            }
+
+            let piece = Piece::try_from_char(c).ok_or(ParseFenError::InvalidPiece(c))?;
+
+            if piece.piece_kind == PieceKind::Pawn && (r_index == 0 || r_index == 7) {
+                return Err(ParseFenError::PawnOnBackRank);
             }
+
+            board_state[r_index][f_index] = Some(piece);
+            f_index += 1;
+        }
+
+        if f_index != 8 {
+            return Err(ParseFenError::ParseRanks(ranks.to_string()));
         }
     }
-    board_state
+    Ok(board_state)
 }
 
 pub fn parse_en_passant_target_square(en_passant_target_square: &str) -> Result<Option<Position>, ParseFenError> {
@@ -65,7 +87,9 @@ pub fn parse_en_passant_target_square(en_passant_target_square: &str) -> Result<
         let file = en_passant_target_square.chars().next();
 
         let (rank, file) = match (rank, file) {
-            (Some(rank), Some(file)) => (rank as usize, file as usize),
+            (Some(rank @ '1'..='8'), Some(file @ 'a'..='h')) if en_passant_target_square.len() == 2 => {
+                (rank as usize, file as usize)
+            },
             _ => { return Err(ParseFenError::ParseEnPassantSquare(en_passant_target_square.to_string())); }
         };
 
@@ -89,6 +113,10 @@ pub fn parse_active_turn(active_turn: &str) -> Result<Color, ParseFenError> {
 
 #[derive(Debug)]
 pub enum ParseFenError {
+    MissingFields(usize),
+    ParseRanks(String),
+    InvalidPiece(char),
+    PawnOnBackRank,
     ParseActiveTurn(String),
     ParseEnPassantSquare(String),
     MissingKing,
@@ -97,6 +125,22 @@ pub enum ParseFenError {
 impl fmt::Display for ParseFenError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
+            Self::MissingFields(count) => {
+                write!(f, "FEN has {count} fields, expected 6")
+            },
+
+            Self::ParseRanks(ranks) => {
+                write!(f, "Failed to parse ranks: {ranks}")
+            },
+
+            Self::InvalidPiece(c) => {
+                write!(f, "Invalid piece character: {c}")
+            },
+
+            Self::PawnOnBackRank => {
+                write!(f, "Position is invalid because a pawn stands on the first or last rank")
+            },
+
             Self::ParseActiveTurn(active_turn) => {
                 write!(f, "Failed to parse active_turn: {active_turn}")
             },
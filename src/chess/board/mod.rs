@@ -1,15 +1,24 @@
 use image::{imageops, Rgba, RgbaImage};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 use std::env;
 
-use self::chess_move::Move;
+use self::chess_move::{is_promotion_square, Move, PROMOTION_KINDS};
 use self::chess_move::MoveError;
 
 pub use self::fen::*;
 pub use self::piece::*;
 
 pub mod chess_move;
+pub mod display;
 pub mod fen;
+pub mod packed;
 pub mod piece;
+pub mod san;
+pub mod see;
+#[cfg(feature = "serde")]
+pub mod serialization;
+pub mod zobrist;
 
 const BOARD_SIZE: usize = 8;
 const IMAGE_SIZE: u32 = 512;
@@ -27,7 +36,8 @@ const BOARD_BACKGROUND_COLOR: Rgba<u8> = Rgba([240, 217, 181, u8::MAX]);
 
 pub type Position = (usize, usize);
 
-#[derive(PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum GameStatus {
     WhiteWin,
     BlackWin,
@@ -36,6 +46,7 @@ pub enum GameStatus {
 }
 
 #[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Board {
     pub state: [[Option<Piece>; BOARD_SIZE]; BOARD_SIZE],
     pub active_turn: Color,
@@ -124,7 +135,7 @@ impl Board {
                 Color::White => {
                     let king_pos = self.white_king_position.unwrap();
 
-                    if opponent_attacks.iter().any(|&pos| pos == king_pos) {
+                    if opponent_attacks.contains(&king_pos) {
                         return GameStatus::BlackWin;
                     }
                     GameStatus::Draw
@@ -133,13 +144,11 @@ impl Board {
                 Color::Black => {
                     let king_pos = self.black_king_position.unwrap();
 
-                    if opponent_attacks.iter().any(|&pos| pos == king_pos) {
+                    if opponent_attacks.contains(&king_pos) {
                         return GameStatus::WhiteWin;
                     }
                     GameStatus::Draw
                 }
-
-                _ => GameStatus::Draw,
             }
         } else {
             GameStatus::Ongoing
@@ -154,7 +163,7 @@ impl Board {
                 if let Some(piece) = self.state[r_index][f_index] {
                     if piece.color == side {
                         let mut piece_attacks =
-                            piece.get_attack_positions((r_index, f_index), &self);
+                            piece.get_attack_positions((r_index, f_index), self);
 
                         positions.append(&mut piece_attacks);
                     }
@@ -172,11 +181,25 @@ impl Board {
             return Err(MoveError::IllegalMoveError);
         }
 
+        self.make_move_unchecked(m);
+
+        Ok(())
+    }
+
+    /// Plays `m` without validating it. Used by search, where moves come straight
+    /// from `get_legal_moves`.
+    pub(crate) fn make_move_unchecked(&mut self, m: Move) {
         if self.active_turn == Color::Black {
             self.fullmove_number += 1;
-            self.halfmove_clock += 1;
         }
 
+        self.halfmove_clock = self.halfmove_clock.saturating_add(1);
+
+        let is_en_passant = self.is_en_passant(m);
+
+        // Only the double push made by this very move allows an en passant capture.
+        self.en_passant_square = None;
+
         match m {
             Move::CastleKingside => self.castle_kingside(),
             Move::CastleQueenside => self.castle_queenside(),
@@ -185,11 +208,16 @@ impl Board {
                 origin_square,
                 target_square,
                 origin_piece,
+                promotion,
                 ..
             } => {
                 let (origin_rank, origin_file) = origin_square;
                 let (target_rank, target_file) = target_square;
 
+                if is_en_passant {
+                    self.state[origin_rank][target_file] = None;
+                }
+
                 if origin_piece.piece_kind == PieceKind::Pawn {
                     let is_double_push = target_rank.abs_diff(origin_rank) == 2;
 
@@ -201,24 +229,29 @@ impl Board {
                     }
 
                     self.halfmove_clock = 0;
-                } else {
-                    self.en_passant_square = None;
                 }
 
                 if self.state[target_rank][target_file].is_some() {
                     self.halfmove_clock = 0;
                 }
 
+                // A rook captured on its original square can no longer castle.
+                match target_square {
+                    WHITE_KINGSIDE_ROOK_ORIGIN => self.castle_flags.white_kingside = false,
+                    WHITE_QUEENSIDE_ROOK_ORIGIN => self.castle_flags.white_queenside = false,
+                    BLACK_KINGSIDE_ROOK_ORIGIN => self.castle_flags.black_kingside = false,
+                    BLACK_QUEENSIDE_ROOK_ORIGIN => self.castle_flags.black_queenside = false,
+                    _ => (),
+                }
+
                 let piece = self.state[origin_rank][origin_file].take();
 
                 self.state[target_rank][target_file] = piece;
 
-                if origin_piece.piece_kind == PieceKind::Pawn {
-                    match target_rank {
-                        7 => self.state[target_rank][target_file] = Some(Piece::from_char('q')),
-                        0 => self.state[target_rank][target_file] = Some(Piece::from_char('Q')),
-                        _ => (),
-                    }
+                if is_promotion_square(origin_piece, target_rank) {
+                    let promotion = promotion.unwrap_or(PieceKind::Queen);
+                    self.state[target_rank][target_file] =
+                        Some(Piece::new(promotion, origin_piece.color));
                 }
 
                 if origin_piece.piece_kind == PieceKind::King {
@@ -266,8 +299,28 @@ impl Board {
         }
 
         self.history.push(m);
+    }
 
-        Ok(())
+    /// Passes the turn to the opponent without moving a piece.
+    pub(crate) fn make_null_move(&mut self) {
+        self.en_passant_square = None;
+        self.active_turn = self.active_turn.opposite();
+    }
+
+    pub fn is_in_check(&self) -> bool {
+        let king_pos = match self.active_turn {
+            Color::White => self.white_king_position,
+            Color::Black => self.black_king_position,
+        };
+
+        match king_pos {
+            Some(king_pos) => self.is_square_attacked(king_pos, self.active_turn.opposite()),
+            None => false,
+        }
+    }
+
+    pub fn is_square_attacked(&self, square: Position, by: Color) -> bool {
+        self.get_attacks_for_side(by).contains(&square)
     }
 
     pub fn get_legal_moves(&self) -> Vec<Move> {
@@ -285,7 +338,7 @@ impl Board {
             for f_index in 0..8 {
                 if let Some(piece) = self.state[r_index][f_index] {
                     if piece.color == self.active_turn {
-                        let piece_attacks = piece.get_attack_positions((r_index, f_index), &self);
+                        let piece_attacks = piece.get_attack_positions((r_index, f_index), self);
 
                         for pos in piece_attacks {
                             if let Some(target_piece) = self.state[pos.0][pos.1] {
@@ -295,25 +348,33 @@ impl Board {
                                         target_square: pos,
                                         origin_piece: piece,
                                         target_piece: Some(target_piece),
+                                        promotion: None,
                                     };
 
-                                    if self.is_legal_move(attack_move) {
-                                        legal_moves.push(attack_move);
-                                    }
-                                }
-                            } else {
-                                if piece.piece_kind != PieceKind::Pawn {
-                                    let m = Move::PieceMove {
-                                        origin_square: (r_index, f_index),
-                                        target_square: pos,
-                                        origin_piece: piece,
-                                        target_piece: None,
-                                    };
-
-                                    if self.is_legal_move(m) {
-                                        legal_moves.push(m);
-                                    }
+                                    self.push_if_legal(&mut legal_moves, attack_move);
                                 }
+                            } else if piece.piece_kind != PieceKind::Pawn {
+                                let m = Move::PieceMove {
+                                    origin_square: (r_index, f_index),
+                                    target_square: pos,
+                                    origin_piece: piece,
+                                    target_piece: None,
+                                    promotion: None,
+                                };
+
+                                self.push_if_legal(&mut legal_moves, m);
+                            } else if let Some(victim) =
+                                self.en_passant_victim(piece, (r_index, f_index), pos)
+                            {
+                                let en_passant = Move::PieceMove {
+                                    origin_square: (r_index, f_index),
+                                    target_square: pos,
+                                    origin_piece: piece,
+                                    target_piece: Some(victim),
+                                    promotion: None,
+                                };
+
+                                self.push_if_legal(&mut legal_moves, en_passant);
                             }
                         }
 
@@ -331,11 +392,10 @@ impl Board {
                                                 target_square: (r_index - 2, f_index),
                                                 origin_piece: piece,
                                                 target_piece: None,
+                                                promotion: None,
                                             };
 
-                                            if self.is_legal_move(double_pawn_push) {
-                                                legal_moves.push(double_pawn_push);
-                                            }
+                                            self.push_if_legal(&mut legal_moves, double_pawn_push);
                                         }
                                     }
 
@@ -345,11 +405,10 @@ impl Board {
                                             target_square: (r_index - 1, f_index),
                                             origin_piece: piece,
                                             target_piece: None,
+                                            promotion: None,
                                         };
 
-                                        if self.is_legal_move(single_pawn_push) {
-                                            legal_moves.push(single_pawn_push);
-                                        }
+                                        self.push_if_legal(&mut legal_moves, single_pawn_push);
                                     }
                                 }
 
@@ -365,11 +424,10 @@ impl Board {
                                                 target_square: (r_index + 2, f_index),
                                                 origin_piece: piece,
                                                 target_piece: None,
+                                                promotion: None,
                                             };
 
-                                            if self.is_legal_move(double_pawn_push) {
-                                                legal_moves.push(double_pawn_push);
-                                            }
+                                            self.push_if_legal(&mut legal_moves, double_pawn_push);
                                         }
                                     }
 
@@ -379,11 +437,10 @@ impl Board {
                                             target_square: (r_index + 1, f_index),
                                             origin_piece: piece,
                                             target_piece: None,
+                                            promotion: None,
                                         };
 
-                                        if self.is_legal_move(single_pawn_push) {
-                                            legal_moves.push(single_pawn_push);
-                                        }
+                                        self.push_if_legal(&mut legal_moves, single_pawn_push);
                                     }
                                 }
                             }
@@ -395,11 +452,78 @@ impl Board {
         legal_moves
     }
 
+    /// Adds `m` to `moves` if it is legal. A pawn reaching the last rank is
+    /// added once for each piece it may promote to.
+    fn push_if_legal(&self, moves: &mut Vec<Move>, m: Move) {
+        if !self.is_legal_move(m) {
+            return;
+        }
+
+        match m {
+            Move::PieceMove {
+                origin_square,
+                target_square,
+                origin_piece,
+                target_piece,
+                ..
+            } if is_promotion_square(origin_piece, target_square.0) => {
+                for promotion in PROMOTION_KINDS {
+                    moves.push(Move::PieceMove {
+                        origin_square,
+                        target_square,
+                        origin_piece,
+                        target_piece,
+                        promotion: Some(promotion),
+                    });
+                }
+            }
+
+            _ => moves.push(m),
+        }
+    }
+
+    /// The pawn a move of `piece` from `origin_square` to `target_square` would
+    /// capture en passant, if it is such a capture.
+    pub fn en_passant_victim(
+        &self,
+        piece: Piece,
+        origin_square: Position,
+        target_square: Position,
+    ) -> Option<Piece> {
+        if piece.piece_kind != PieceKind::Pawn
+            || self.en_passant_square != Some(target_square)
+            || origin_square.1.abs_diff(target_square.1) != 1
+        {
+            return None;
+        }
+
+        self.state[origin_square.0][target_square.1]
+            .filter(|victim| victim.piece_kind == PieceKind::Pawn && victim.color != piece.color)
+    }
+
+    /// Whether `m` is a pawn capturing en passant. The captured pawn stands
+    /// next to the capturing one rather than on the target square.
+    pub fn is_en_passant(&self, m: Move) -> bool {
+        match m {
+            Move::PieceMove {
+                origin_square,
+                target_square,
+                origin_piece,
+                ..
+            } => {
+                self.state[target_square.0][target_square.1].is_none()
+                    && self
+                        .en_passant_victim(origin_piece, origin_square, target_square)
+                        .is_some()
+            }
+            _ => false,
+        }
+    }
+
     pub fn is_legal_move(&self, m: Move) -> bool {
         let has_moved_king = self.history.iter().any(|&m| match m {
             Move::PieceMove { origin_piece, .. } => {
-                (origin_piece.piece_kind == PieceKind::King
-                    && origin_piece.color == self.active_turn)
+                origin_piece.piece_kind == PieceKind::King && origin_piece.color == self.active_turn
             }
             _ => false,
         });
@@ -437,6 +561,10 @@ impl Board {
                         return false;
                     }
                 }
+
+                if !self.castle_path_is_safe(1) {
+                    return false;
+                }
             }
 
             Move::CastleQueenside => {
@@ -473,6 +601,10 @@ impl Board {
                         return false;
                     }
                 }
+
+                if !self.castle_path_is_safe(-1) {
+                    return false;
+                }
             }
 
             Move::PieceMove {
@@ -497,6 +629,8 @@ impl Board {
                     }
                 }
 
+                let is_en_passant = self.is_en_passant(m);
+
                 let opponent_color = match self.active_turn {
                     Color::White => Color::Black,
                     Color::Black => Color::White,
@@ -511,18 +645,22 @@ impl Board {
 
                         board_copy.state[target_rank][target_file] = piece;
 
+                        if is_en_passant {
+                            board_copy.state[origin_rank][target_file] = None;
+                        }
+
                         let opponent_attacks = board_copy.get_attacks_for_side(opponent_color);
 
                         if piece.unwrap().piece_kind == PieceKind::King {
                             let king_target_position_attacked =
-                                opponent_attacks.iter().any(|&pos| pos == target_square);
+                                opponent_attacks.contains(&target_square);
 
                             if king_target_position_attacked {
                                 return false;
                             }
                         } else {
                             let king_position_attacked =
-                                opponent_attacks.iter().any(|&pos| pos == king_pos);
+                                opponent_attacks.contains(&king_pos);
 
                             if king_position_attacked {
                                 return false;
@@ -538,18 +676,22 @@ impl Board {
 
                         board_copy.state[target_rank][target_file] = piece;
 
+                        if is_en_passant {
+                            board_copy.state[origin_rank][target_file] = None;
+                        }
+
                         let opponent_attacks = board_copy.get_attacks_for_side(opponent_color);
 
                         if piece.unwrap().piece_kind == PieceKind::King {
                             let king_target_position_attacked =
-                                opponent_attacks.iter().any(|&pos| pos == target_square);
+                                opponent_attacks.contains(&target_square);
 
                             if king_target_position_attacked {
                                 return false;
                             }
                         } else {
                             let king_position_attacked =
-                                opponent_attacks.iter().any(|&pos| pos == king_pos);
+                                opponent_attacks.contains(&king_pos);
 
                             if king_position_attacked {
                                 return false;
@@ -562,6 +704,21 @@ impl Board {
         true
     }
 
+    /// The king may not castle out of, through or into check.
+    fn castle_path_is_safe(&self, direction: isize) -> bool {
+        let king_origin = match self.active_turn {
+            Color::White => WHITE_KING_ORIGIN,
+            Color::Black => BLACK_KING_ORIGIN,
+        };
+
+        let opponent_attacks = self.get_attacks_for_side(self.active_turn.opposite());
+
+        (0..=2).all(|step| {
+            let square = (king_origin.0, king_origin.1.wrapping_add_signed(direction * step));
+            !opponent_attacks.contains(&square)
+        })
+    }
+
     pub fn castle_kingside(&mut self) {
         match self.active_turn {
             Color::White => {
@@ -570,6 +727,7 @@ impl Board {
                     self.state[WHITE_KINGSIDE_ROOK_ORIGIN.0][WHITE_KINGSIDE_ROOK_ORIGIN.1].take();
 
                 self.state[WHITE_KING_ORIGIN.0][WHITE_KING_ORIGIN.1 + 2] = king;
+                self.white_king_position = Some((WHITE_KING_ORIGIN.0, WHITE_KING_ORIGIN.1 + 2));
                 self.state[WHITE_KINGSIDE_ROOK_ORIGIN.0][WHITE_KINGSIDE_ROOK_ORIGIN.1 - 2] = rook;
 
                 self.castle_flags.white_kingside = false;
@@ -582,6 +740,7 @@ impl Board {
                     self.state[BLACK_KINGSIDE_ROOK_ORIGIN.0][BLACK_KINGSIDE_ROOK_ORIGIN.1].take();
 
                 self.state[BLACK_KING_ORIGIN.0][BLACK_KING_ORIGIN.1 + 2] = king;
+                self.black_king_position = Some((BLACK_KING_ORIGIN.0, BLACK_KING_ORIGIN.1 + 2));
                 self.state[BLACK_KINGSIDE_ROOK_ORIGIN.0][BLACK_KINGSIDE_ROOK_ORIGIN.1 - 2] = rook;
 
                 self.castle_flags.black_kingside = false;
@@ -599,6 +758,7 @@ impl Board {
                     self.state[WHITE_QUEENSIDE_ROOK_ORIGIN.0][WHITE_QUEENSIDE_ROOK_ORIGIN.1].take();
 
                 self.state[WHITE_KING_ORIGIN.0][WHITE_KING_ORIGIN.1 - 2] = king;
+                self.white_king_position = Some((WHITE_KING_ORIGIN.0, WHITE_KING_ORIGIN.1 - 2));
                 self.state[WHITE_QUEENSIDE_ROOK_ORIGIN.0][WHITE_QUEENSIDE_ROOK_ORIGIN.1 + 3] = rook;
 
                 self.castle_flags.white_kingside = false;
@@ -611,6 +771,7 @@ impl Board {
                     self.state[BLACK_QUEENSIDE_ROOK_ORIGIN.0][BLACK_QUEENSIDE_ROOK_ORIGIN.1].take();
 
                 self.state[BLACK_KING_ORIGIN.0][BLACK_KING_ORIGIN.1 - 2] = king;
+                self.black_king_position = Some((BLACK_KING_ORIGIN.0, BLACK_KING_ORIGIN.1 - 2));
                 self.state[BLACK_QUEENSIDE_ROOK_ORIGIN.0][BLACK_QUEENSIDE_ROOK_ORIGIN.1 + 3] = rook;
 
                 self.castle_flags.black_kingside = false;
@@ -633,7 +794,11 @@ impl Board {
             fen_fields.push(field);
         }
 
-        let ranks = parse_ranks(fen_fields[0]);
+        if fen_fields.len() < 6 {
+            return Err(ParseFenError::MissingFields(fen_fields.len()));
+        }
+
+        let ranks = parse_ranks(fen_fields[0])?;
         let active_turn = parse_active_turn(fen_fields[1])?;
         let mut castle_flags = CastleFlags::parse_castle_flags(fen_fields[2]);
         let en_passant_square = parse_en_passant_target_square(fen_fields[3])?;
@@ -718,7 +883,7 @@ impl Board {
         }
 
         if white_king_position.is_none() || black_king_position.is_none() {
-            panic!("{}", ParseFenError::MissingKing)
+            return Err(ParseFenError::MissingKing);
         }
 
         Ok(Self {
@@ -814,3 +979,196 @@ impl Board {
         fen
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn perft(board: &Board, depth: u32) -> u64 {
+        if depth == 0 {
+            return 1;
+        }
+
+        board
+            .get_legal_moves()
+            .into_iter()
+            .map(|m| {
+                let mut child = board.clone();
+                child.make_move_unchecked(m);
+                perft(&child, depth - 1)
+            })
+            .sum()
+    }
+
+    fn square(name: &str) -> Position {
+        let name = name.as_bytes();
+        ((b'8' - name[1]) as usize, (name[0] - b'a') as usize)
+    }
+
+    fn play(board: &mut Board, origin: &str, target: &str) {
+        let (origin, target) = (square(origin), square(target));
+
+        let m = board
+            .get_legal_moves()
+            .into_iter()
+            .find(|m| match *m {
+                Move::PieceMove {
+                    origin_square,
+                    target_square,
+                    ..
+                } => origin_square == origin && target_square == target,
+                _ => false,
+            })
+            .expect("move should be legal");
+
+        board.make_move_unchecked(m);
+    }
+
+    // Published perft counts.
+
+    #[test]
+    fn perft_start_position() {
+        let board = Board::from_fen(START_FEN).unwrap();
+
+        assert_eq!(perft(&board, 1), 20);
+        assert_eq!(perft(&board, 2), 400);
+        assert_eq!(perft(&board, 3), 8902);
+    }
+
+    #[test]
+    fn perft_castling() {
+        let board = Board::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+
+        assert_eq!(perft(&board, 1), 26);
+        assert_eq!(perft(&board, 2), 568);
+        assert_eq!(perft(&board, 3), 13744);
+    }
+
+    #[test]
+    fn perft_kiwipete() {
+        let board = Board::from_fen(
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        )
+        .unwrap();
+
+        assert_eq!(perft(&board, 1), 48);
+        assert_eq!(perft(&board, 2), 2039);
+        assert_eq!(perft(&board, 3), 97862);
+    }
+
+    // Includes en passant captures that would expose the king along its rank.
+    #[test]
+    fn perft_pins_and_checks() {
+        let board = Board::from_fen("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1").unwrap();
+
+        assert_eq!(perft(&board, 1), 14);
+        assert_eq!(perft(&board, 2), 191);
+        assert_eq!(perft(&board, 3), 2812);
+        assert_eq!(perft(&board, 4), 43238);
+    }
+
+    #[test]
+    fn perft_promotions() {
+        let board =
+            Board::from_fen("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1")
+                .unwrap();
+
+        assert_eq!(perft(&board, 1), 6);
+        assert_eq!(perft(&board, 2), 264);
+        assert_eq!(perft(&board, 3), 9467);
+    }
+
+    #[test]
+    fn perft_promotion_with_check() {
+        let board =
+            Board::from_fen("rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8").unwrap();
+
+        assert_eq!(perft(&board, 1), 44);
+        assert_eq!(perft(&board, 2), 1486);
+        assert_eq!(perft(&board, 3), 62379);
+    }
+
+    #[test]
+    fn from_fen_rejects_malformed_positions() {
+        let malformed = [
+            ("", "FEN has 0 fields, expected 6"),
+            ("4k3/8/8/8/8/8/8/4K3 w - -", "FEN has 4 fields, expected 6"),
+            ("4k3/8/8/8/8/8/8/4KX2 w - - 0 1", "Invalid piece character: X"),
+            ("4k3/8/8/8/8/8/4K3 w - - 0 1", "Failed to parse ranks"),
+            ("4k3/8/8/8/8/8/8/4K4 w - - 0 1", "Failed to parse ranks"),
+            ("4k3/8/8/8/8/8/8/4K2P w - - 0 1", "pawn stands on the first or last rank"),
+            ("4k3/8/8/8/8/8/8/8 w - - 0 1", "kings are missing"),
+            ("4k3/8/8/8/8/8/8/4K3 x - - 0 1", "Failed to parse active_turn"),
+            ("4k3/8/8/8/8/8/8/4K3 w - e0 0 1", "Failed to parse en_passant"),
+            ("4k3/8/8/8/8/8/8/4K3 w - !! 0 1", "Failed to parse en_passant"),
+        ];
+
+        for (fen, message) in malformed {
+            match Board::from_fen(fen) {
+                Ok(_) => panic!("{fen:?} was accepted"),
+                Err(err) => assert!(err.to_string().contains(message), "{fen:?}: {err}"),
+            }
+        }
+    }
+
+    #[test]
+    fn castling_through_check_is_illegal() {
+        let board = Board::from_fen("4kr2/8/8/8/8/8/8/R3K2R w KQ - 0 1").unwrap();
+
+        assert!(!board.is_legal_move(Move::CastleKingside));
+        assert!(board.is_legal_move(Move::CastleQueenside));
+    }
+
+    #[test]
+    fn castling_out_of_check_is_illegal() {
+        let board = Board::from_fen("4r1k1/8/8/8/8/8/8/R3K2R w KQ - 0 1").unwrap();
+
+        assert!(board.is_in_check());
+        assert!(!board.is_legal_move(Move::CastleKingside));
+        assert!(!board.is_legal_move(Move::CastleQueenside));
+    }
+
+    #[test]
+    fn castling_moves_the_king_position() {
+        let mut board = Board::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+
+        board.make_move_unchecked(Move::CastleKingside);
+        board.make_move_unchecked(Move::CastleQueenside);
+
+        assert_eq!(board.white_king_position, Some(square("g1")));
+        assert_eq!(board.black_king_position, Some(square("c8")));
+    }
+
+    #[test]
+    fn capturing_a_rook_on_its_origin_clears_its_castle_flag() {
+        let mut board = Board::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+
+        play(&mut board, "a1", "a8");
+
+        assert!(!board.castle_flags.black_queenside);
+        assert!(!board.is_legal_move(Move::CastleQueenside));
+        assert!(board.to_fen().contains(" b Kk "));
+    }
+
+    #[test]
+    fn en_passant_square_lasts_one_move() {
+        let mut board = Board::from_fen(START_FEN).unwrap();
+
+        play(&mut board, "e2", "e4");
+        assert_eq!(board.en_passant_square, Some(square("e3")));
+
+        play(&mut board, "g8", "f6");
+        assert_eq!(board.en_passant_square, None);
+    }
+
+    #[test]
+    fn null_move_passes_the_turn() {
+        let mut board = Board::from_fen(START_FEN).unwrap();
+
+        play(&mut board, "e2", "e4");
+        board.make_null_move();
+
+        assert_eq!(board.active_turn, Color::White);
+        assert_eq!(board.en_passant_square, None);
+    }
+}
@@ -0,0 +1,292 @@
+use std::fmt;
+
+use super::{Board, Color};
+
+// A packed board is 32 bytes, little endian:
+//
+//   0       format version, `PACKED_VERSION`
+//   1..30   the position fields below
+//   30..32  reserved, zero
+//
+// The position fields are shared with the training data records:
+//
+//   0..8    occupancy, bit `rank_index * 8 + file` for each occupied square
+//   8..24   a 4-bit piece code per occupied square in bit order, low nibble first
+//   24      side to move in bit 0, castling rights KQkq in bits 1 to 4
+//   25      en passant square as `rank_index * 8 + file`, or 0xff if there is none
+//   26      halfmove clock
+//   27..29  fullmove number
+
+pub const PACKED_SIZE: usize = 32;
+
+/// Bumped whenever the layout changes. Older versions stay readable.
+pub const PACKED_VERSION: u8 = 1;
+
+/// Positions with more pieces cannot be packed.
+pub const MAX_PIECES: usize = 32;
+
+pub(crate) const FIELDS_SIZE: usize = 29;
+
+const NO_EN_PASSANT: u8 = 0xff;
+
+/// Piece codes are indices into this string.
+const PIECE_CHARS: &[u8; 12] = b"PNBRQKpnbrqk";
+
+impl Board {
+    /// Packs the position into `PACKED_SIZE` bytes. The move history is not
+    /// kept. Fails for positions with more than `MAX_PIECES` pieces.
+    pub fn to_packed(&self) -> Result<[u8; PACKED_SIZE], PackError> {
+        let mut packed = [0; PACKED_SIZE];
+
+        packed[0] = PACKED_VERSION;
+        packed[1..1 + FIELDS_SIZE]
+            .copy_from_slice(&encode_fields(self).ok_or(PackError::TooManyPieces)?);
+
+        Ok(packed)
+    }
+
+    pub fn from_packed(packed: &[u8; PACKED_SIZE]) -> Result<Self, PackError> {
+        match packed[0] {
+            1 => {
+                if packed[1 + FIELDS_SIZE..] != [0, 0] {
+                    return Err(PackError::Malformed);
+                }
+
+                decode_fields(packed[1..1 + FIELDS_SIZE].try_into().unwrap())
+                    .ok_or(PackError::Malformed)
+            }
+            version => Err(PackError::UnsupportedVersion(version)),
+        }
+    }
+}
+
+/// Encodes the position fields, or `None` with more than `MAX_PIECES` pieces.
+pub(crate) fn encode_fields(board: &Board) -> Option<[u8; FIELDS_SIZE]> {
+    let mut fields = [0; FIELDS_SIZE];
+
+    let mut occupancy: u64 = 0;
+    let mut pieces = 0;
+
+    for (r_index, rank) in board.state.iter().enumerate() {
+        for (f_index, square) in rank.iter().enumerate() {
+            let piece = match square {
+                Some(piece) => piece,
+                None => continue,
+            };
+
+            if pieces == MAX_PIECES {
+                return None;
+            }
+
+            let code = PIECE_CHARS
+                .iter()
+                .position(|&c| c == piece.to_char() as u8)
+                .unwrap() as u8;
+
+            occupancy |= 1 << (r_index * 8 + f_index);
+            fields[8 + pieces / 2] |= code << (4 * (pieces % 2));
+            pieces += 1;
+        }
+    }
+
+    fields[0..8].copy_from_slice(&occupancy.to_le_bytes());
+
+    let castle_flags = &board.castle_flags;
+
+    fields[24] = (board.active_turn == Color::Black) as u8
+        | (castle_flags.white_kingside as u8) << 1
+        | (castle_flags.white_queenside as u8) << 2
+        | (castle_flags.black_kingside as u8) << 3
+        | (castle_flags.black_queenside as u8) << 4;
+
+    fields[25] = board
+        .en_passant_square
+        .map_or(NO_EN_PASSANT, |(rank, file)| (rank * 8 + file) as u8);
+    fields[26] = board.halfmove_clock;
+    fields[27..29].copy_from_slice(&board.fullmove_number.to_le_bytes());
+
+    Some(fields)
+}
+
+/// Decodes the position fields, or `None` when they do not describe a
+/// position `Board::from_fen` accepts.
+pub(crate) fn decode_fields(fields: &[u8; FIELDS_SIZE]) -> Option<Board> {
+    let occupancy = u64::from_le_bytes(fields[0..8].try_into().unwrap());
+
+    if occupancy.count_ones() as usize > MAX_PIECES {
+        return None;
+    }
+
+    let mut state = [[None; 8]; 8];
+
+    for (piece_index, square) in (0..64)
+        .filter(|square| occupancy >> square & 1 == 1)
+        .enumerate()
+    {
+        let code = fields[8 + piece_index / 2] >> (4 * (piece_index % 2)) & 0xf;
+        let c = *PIECE_CHARS.get(code as usize)?;
+
+        state[square / 8][square % 8] = Some(c as char);
+    }
+
+    let mut placement = String::new();
+
+    for (r_index, rank) in state.iter().enumerate() {
+        let mut empty_squares = 0;
+
+        for square in rank {
+            match square {
+                Some(c) => {
+                    if empty_squares > 0 {
+                        placement.push_str(&empty_squares.to_string());
+                        empty_squares = 0;
+                    }
+                    placement.push(*c);
+                }
+                None => empty_squares += 1,
+            }
+        }
+
+        if empty_squares > 0 {
+            placement.push_str(&empty_squares.to_string());
+        }
+
+        if r_index < 7 {
+            placement.push('/');
+        }
+    }
+
+    let flags = fields[24];
+
+    if flags >> 5 != 0 {
+        return None;
+    }
+
+    let castling: String = "KQkq"
+        .chars()
+        .enumerate()
+        .filter(|(index, _)| flags >> (index + 1) & 1 == 1)
+        .map(|(_, c)| c)
+        .collect();
+
+    let en_passant = match fields[25] {
+        NO_EN_PASSANT => String::from("-"),
+        square @ 0..=63 => format!("{}{}", (b'a' + square % 8) as char, 8 - square / 8),
+        _ => return None,
+    };
+
+    let fen = format!(
+        "{placement} {} {} {en_passant} {} {}",
+        if flags & 1 == 1 { 'b' } else { 'w' },
+        if castling.is_empty() { "-" } else { &castling },
+        fields[26],
+        u16::from_le_bytes([fields[27], fields[28]])
+    );
+
+    Board::from_fen(&fen).ok()
+}
+
+#[derive(Debug)]
+pub enum PackError {
+    TooManyPieces,
+    UnsupportedVersion(u8),
+    Malformed,
+}
+
+impl fmt::Display for PackError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::TooManyPieces => {
+                write!(f, "Position has more than {MAX_PIECES} pieces")
+            }
+
+            Self::UnsupportedVersion(version) => {
+                write!(f, "Unsupported packed board version {version}")
+            }
+
+            Self::Malformed => {
+                write!(f, "Malformed packed board")
+            }
+        }
+    }
+}
+
+impl std::error::Error for PackError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_through_fen() {
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "rnbqkbnr/ppp1pppp/8/3pP3/8/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 3",
+            "rnbqkbnr/pppp1ppp/8/8/3Pp3/8/PPP1PPPP/RNBQKBNR b Kq d3 0 3",
+            "r3k2r/8/8/8/8/8/8/R3K2R b Qk - 17 300",
+            "8/8/4k3/8/8/4K3/8/8 w - - 99 65535",
+        ] {
+            let board = Board::from_fen(fen).unwrap();
+            let packed = board.to_packed().unwrap();
+
+            assert_eq!(packed[0], PACKED_VERSION);
+            assert_eq!(Board::from_packed(&packed).unwrap().to_fen(), fen);
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_versions_and_reserved_bytes() {
+        let board = Board::from_fen("start").unwrap();
+        let packed = board.to_packed().unwrap();
+
+        for version in [0, PACKED_VERSION + 1] {
+            let mut other_version = packed;
+            other_version[0] = version;
+
+            assert!(matches!(
+                Board::from_packed(&other_version),
+                Err(PackError::UnsupportedVersion(v)) if v == version
+            ));
+        }
+
+        let mut reserved = packed;
+        reserved[PACKED_SIZE - 1] = 1;
+
+        assert!(matches!(
+            Board::from_packed(&reserved),
+            Err(PackError::Malformed)
+        ));
+    }
+
+    #[test]
+    fn rejects_malformed_positions() {
+        let too_many =
+            Board::from_fen("rnbqkbnr/pppppppp/8/8/3N4/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap();
+
+        assert!(matches!(
+            too_many.to_packed(),
+            Err(PackError::TooManyPieces)
+        ));
+
+        let mut packed = Board::from_fen("8/8/4k3/8/8/4K3/8/8 w - - 0 1")
+            .unwrap()
+            .to_packed()
+            .unwrap();
+
+        // Turn the black king into a white one.
+        packed[9] = (packed[9] & 0xf0) | 5;
+
+        assert!(matches!(
+            Board::from_packed(&packed),
+            Err(PackError::Malformed)
+        ));
+
+        let mut en_passant = Board::from_fen("start").unwrap().to_packed().unwrap();
+        en_passant[26] = 64;
+
+        assert!(matches!(
+            Board::from_packed(&en_passant),
+            Err(PackError::Malformed)
+        ));
+    }
+}
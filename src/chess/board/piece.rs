@@ -1,6 +1,6 @@
-#[allow(dead_code)]
-#[allow(unused_variables)]
 use super::{Board, Position};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 
 pub(super) struct AttackOffsets;
 impl AttackOffsets {
@@ -46,6 +46,7 @@ impl AttackOffsets {
 }
 
 #[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum PieceKind {
     Pawn,
     Knight,
@@ -56,12 +57,23 @@ pub enum PieceKind {
 }
 
 #[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Color {
     White,
     Black,
 }
 
-#[derive(Debug, Copy, Clone)]
+impl Color {
+    pub fn opposite(self) -> Self {
+        match self {
+            Color::White => Color::Black,
+            Color::Black => Color::White,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Piece {
     pub piece_kind: PieceKind,
     pub color: Color,
@@ -90,22 +102,30 @@ impl Piece {
     }
 
     pub fn from_char(c: char) -> Self {
+        Self::try_from_char(c).expect("Invalid Piece Character")
+    }
+
+    /// Like `from_char`, but returns `None` for a character that is not a FEN
+    /// piece letter.
+    pub fn try_from_char(c: char) -> Option<Self> {
         let color = if c.is_uppercase() {
             Color::White
         } else {
             Color::Black
         };
 
-        match c.to_ascii_uppercase() {
-            'P' => Piece::new(PieceKind::Pawn, color),
-            'N' => Piece::new(PieceKind::Knight, color),
-            'B' => Piece::new(PieceKind::Bishop, color),
-            'R' => Piece::new(PieceKind::Rook, color),
-            'Q' => Piece::new(PieceKind::Queen, color),
-            'K' => Piece::new(PieceKind::King, color),
+        let piece_kind = match c.to_ascii_uppercase() {
+            'P' => PieceKind::Pawn,
+            'N' => PieceKind::Knight,
+            'B' => PieceKind::Bishop,
+            'R' => PieceKind::Rook,
+            'Q' => PieceKind::Queen,
+            'K' => PieceKind::King,
 
-            _ => panic!("Invalid Piece Character"),
-        }
+            _ => return None,
+        };
+
+        Some(Piece::new(piece_kind, color))
     }
 
     pub fn get_attack_positions(self, origin: Position, board: &Board) -> Vec<Position> {
@@ -125,7 +145,7 @@ impl Piece {
 
             match self.piece_kind {
                 PieceKind::Bishop | PieceKind::Rook | PieceKind::Queen => {
-                    let mut path = self.get_path_from_direction(origin, offset, &board);
+                    let mut path = self.get_path_from_direction(origin, offset, board);
 
                     positions.append(&mut path);
                 }
@@ -147,7 +167,7 @@ impl Piece {
         let mut f_index = origin.1.wrapping_add_signed(direction.1);
 
         while r_index < 8 && f_index < 8 {
-            if let Some(piece) = board.state[r_index][f_index] {
+            if board.state[r_index][f_index].is_some() {
                 positions.push((r_index, f_index));
                 return positions;
             }
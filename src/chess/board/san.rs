@@ -0,0 +1,205 @@
+use super::{
+    chess_move::{parse_square, square_to_string, Move, MoveError},
+    Board, Color, Piece, PieceKind,
+};
+
+impl Board {
+    /// Formats `m` in standard algebraic notation, including disambiguation and
+    /// check or mate suffixes. `m` must be legal in this position.
+    pub fn move_to_san(&self, m: Move) -> String {
+        let mut san = match m {
+            Move::CastleKingside => String::from("O-O"),
+            Move::CastleQueenside => String::from("O-O-O"),
+
+            Move::PieceMove {
+                origin_square,
+                target_square,
+                origin_piece,
+                target_piece,
+                promotion,
+            } => {
+                let mut san = String::new();
+                let target = square_to_string(target_square);
+
+                if origin_piece.piece_kind == PieceKind::Pawn {
+                    if origin_square.1 != target_square.1 || target_piece.is_some() {
+                        san.push((origin_square.1 as u8 + 97) as char);
+                        san.push('x');
+                    }
+
+                    san.push_str(&target);
+
+                    if let Some(promotion) = promotion {
+                        san.push('=');
+                        san.push(Piece::new(promotion, Color::White).to_char());
+                    }
+                } else {
+                    san.push(origin_piece.to_char().to_ascii_uppercase());
+                    san.push_str(&self.disambiguation(m));
+
+                    if target_piece.is_some() {
+                        san.push('x');
+                    }
+
+                    san.push_str(&target);
+                }
+                san
+            }
+        };
+
+        let mut child = self.clone();
+        child.make_move_unchecked(m);
+
+        if child.is_in_check() {
+            if child.get_legal_moves().is_empty() {
+                san.push('#');
+            } else {
+                san.push('+');
+            }
+        }
+        san
+    }
+
+    /// Parses a move in standard algebraic notation. Check, mate and annotation
+    /// suffixes are ignored, the capture sign is optional and castling may be
+    /// written with zeros.
+    pub fn move_from_san(&self, san: &str) -> Result<Move, MoveError> {
+        let malformed = || MoveError::MalformedSANString(san.to_string());
+
+        let trimmed = san.trim_end_matches(['+', '#', '!', '?']);
+
+        let castle = match trimmed {
+            "O-O" | "0-0" => Some(Move::CastleKingside),
+            "O-O-O" | "0-0-0" => Some(Move::CastleQueenside),
+            _ => None,
+        };
+
+        if let Some(castle) = castle {
+            if !self.is_legal_move(castle) {
+                return Err(MoveError::IllegalMoveError);
+            }
+
+            return Ok(castle);
+        }
+
+        // Promotions are written `e8=Q`, or sometimes `e8Q`.
+        let (body, promotion) = match trimmed.char_indices().last() {
+            Some((index, piece @ ('N' | 'B' | 'R' | 'Q'))) if index >= 2 => {
+                (trimmed[..index].trim_end_matches('='), Some(piece))
+            }
+            _ => (trimmed, None),
+        };
+
+        // A promotion written without its piece is taken to be to a queen.
+        let promotion = match promotion {
+            Some('N') => PieceKind::Knight,
+            Some('B') => PieceKind::Bishop,
+            Some('R') => PieceKind::Rook,
+            _ => PieceKind::Queen,
+        };
+
+        let (piece_kind, rest) = match body.chars().next() {
+            Some('N') => (PieceKind::Knight, &body[1..]),
+            Some('B') => (PieceKind::Bishop, &body[1..]),
+            Some('R') => (PieceKind::Rook, &body[1..]),
+            Some('Q') => (PieceKind::Queen, &body[1..]),
+            Some('K') => (PieceKind::King, &body[1..]),
+            Some('a'..='h') => (PieceKind::Pawn, body),
+            _ => return Err(malformed()),
+        };
+
+        let rest = rest.replace(['x', ':', '-'], "");
+
+        if rest.len() < 2 || !rest.is_ascii() {
+            return Err(malformed());
+        }
+
+        let (disambiguation, target) = rest.split_at(rest.len() - 2);
+        let target_square = parse_square(target).ok_or_else(malformed)?;
+
+        let mut origin_file = None;
+        let mut origin_rank = None;
+
+        for c in disambiguation.chars() {
+            match c {
+                'a'..='h' if origin_file.is_none() => origin_file = Some(c as usize - 97),
+                '1'..='8' if origin_rank.is_none() => origin_rank = Some(8 - (c as usize - 48)),
+                _ => return Err(malformed()),
+            }
+        }
+
+        let candidates: Vec<Move> = self
+            .get_legal_moves()
+            .into_iter()
+            .filter(|&m| match m {
+                Move::PieceMove {
+                    origin_square,
+                    target_square: other_target,
+                    origin_piece,
+                    promotion: other_promotion,
+                    ..
+                } => {
+                    origin_piece.piece_kind == piece_kind
+                        && other_promotion.is_none_or(|other| other == promotion)
+                        && other_target == target_square
+                        && origin_file.is_none_or(|file| file == origin_square.1)
+                        && origin_rank.is_none_or(|rank| rank == origin_square.0)
+                }
+                _ => false,
+            })
+            .collect();
+
+        match candidates[..] {
+            [m] => Ok(m),
+            [] => Err(MoveError::IllegalMoveError),
+            _ => Err(MoveError::AmbiguousSANString(san.to_string())),
+        }
+    }
+
+    /// The origin file, rank or square needed to tell `m` apart from other
+    /// moves of the same kind of piece to the same square.
+    fn disambiguation(&self, m: Move) -> String {
+        let (origin_square, target_square, origin_piece) = match m {
+            Move::PieceMove {
+                origin_square,
+                target_square,
+                origin_piece,
+                ..
+            } => (origin_square, target_square, origin_piece),
+            _ => return String::new(),
+        };
+
+        let rivals: Vec<_> = self
+            .get_legal_moves()
+            .into_iter()
+            .filter_map(|other| match other {
+                Move::PieceMove {
+                    origin_square: other_origin,
+                    target_square: other_target,
+                    origin_piece: other_piece,
+                    ..
+                } if other_target == target_square
+                    && other_origin != origin_square
+                    && other_piece.piece_kind == origin_piece.piece_kind =>
+                {
+                    Some(other_origin)
+                }
+                _ => None,
+            })
+            .collect();
+
+        if rivals.is_empty() {
+            return String::new();
+        }
+
+        let origin = square_to_string(origin_square);
+
+        if rivals.iter().all(|rival| rival.1 != origin_square.1) {
+            origin[0..1].to_string()
+        } else if rivals.iter().all(|rival| rival.0 != origin_square.0) {
+            origin[1..2].to_string()
+        } else {
+            origin
+        }
+    }
+}
@@ -0,0 +1,329 @@
+use super::{chess_move::Move, piece::AttackOffsets, Board, Color, PieceKind, Position};
+use crate::chess::evaluation::piece_value;
+
+type Occupancy = u64;
+
+fn square_bit(square: Position) -> Occupancy {
+    1 << (square.0 * 8 + square.1)
+}
+
+fn offset_square(square: Position, offset: (isize, isize)) -> Option<Position> {
+    let target = (
+        square.0.wrapping_add_signed(offset.0),
+        square.1.wrapping_add_signed(offset.1),
+    );
+
+    if target.0 >= 8 || target.1 >= 8 {
+        return None;
+    }
+    Some(target)
+}
+
+impl Board {
+    /// Static exchange evaluation of `m` from the point of view of the side to move.
+    ///
+    /// Both sides recapture on the target square with their least valuable attacker
+    /// and may stop whenever continuing would lose material. Sliders hidden behind
+    /// other attackers join the sequence once the pieces in front of them are gone.
+    pub fn see(&self, m: Move) -> i32 {
+        self.see_offset(m, 0, false)
+    }
+
+    /// Returns true if the static exchange of `m` wins at least `threshold`.
+    pub fn see_ge(&self, m: Move, threshold: i32) -> bool {
+        // Every line of the exchange starts with the first capture, so lowering
+        // its value by `threshold` lowers the result by the same amount and
+        // only the sign is left to decide.
+        self.see_offset(m, threshold, true) >= 0
+    }
+
+    fn see_offset(&self, m: Move, threshold: i32, sign_only: bool) -> i32 {
+        let (origin_square, target_square, origin_piece, target_piece) = match m {
+            Move::PieceMove {
+                origin_square,
+                target_square,
+                origin_piece,
+                target_piece,
+                ..
+            } => (origin_square, target_square, origin_piece, target_piece),
+
+            Move::CastleKingside | Move::CastleQueenside => return -threshold,
+        };
+
+        let captured_value = target_piece.map_or(0, |piece| piece_value(piece.piece_kind));
+
+        self.swap(
+            target_square,
+            origin_square,
+            origin_piece.piece_kind,
+            origin_piece.color,
+            captured_value - threshold,
+            sign_only,
+        )
+    }
+
+    /// Pieces of `side` that the opponent can win material against by starting
+    /// an exchange on their square.
+    pub fn hanging_pieces(&self, side: Color) -> Vec<Position> {
+        let opponent = match side {
+            Color::White => Color::Black,
+            Color::Black => Color::White,
+        };
+
+        let occupancy = self.occupancy();
+        let mut hanging = Vec::new();
+
+        for r_index in 0..8 {
+            for f_index in 0..8 {
+                let piece = match self.state[r_index][f_index] {
+                    Some(piece) if piece.color == side && piece.piece_kind != PieceKind::King => {
+                        piece
+                    }
+                    _ => continue,
+                };
+
+                let square = (r_index, f_index);
+
+                let attacker = match self.least_valuable_attacker(square, opponent, occupancy) {
+                    Some(attacker) => attacker,
+                    None => continue,
+                };
+
+                let attacker_kind = self.state[attacker.0][attacker.1].unwrap().piece_kind;
+
+                let gain = self.swap(
+                    square,
+                    attacker,
+                    attacker_kind,
+                    opponent,
+                    piece_value(piece.piece_kind),
+                    true,
+                );
+
+                if gain > 0 {
+                    hanging.push(square);
+                }
+            }
+        }
+        hanging
+    }
+
+    /// Plays out the exchange on `target_square`. With `sign_only`, the sequence
+    /// stops as soon as its outcome can no longer change sign, which keeps the
+    /// sign of the result but not its exact value.
+    fn swap(
+        &self,
+        target_square: Position,
+        origin_square: Position,
+        origin_kind: PieceKind,
+        origin_color: Color,
+        captured_value: i32,
+        sign_only: bool,
+    ) -> i32 {
+        let mut gain = [0; 32];
+        let mut depth = 0;
+
+        let mut occupancy = self.occupancy() & !square_bit(origin_square);
+        let mut attacker_kind = origin_kind;
+        let mut side = origin_color;
+
+        gain[0] = captured_value;
+
+        loop {
+            depth += 1;
+            side = match side {
+                Color::White => Color::Black,
+                Color::Black => Color::White,
+            };
+
+            gain[depth] = piece_value(attacker_kind) - gain[depth - 1];
+
+            if sign_only && gain[depth].max(-gain[depth - 1]) < 0 {
+                break;
+            }
+
+            if depth == gain.len() - 1 {
+                break;
+            }
+
+            let attacker = match self.least_valuable_attacker(target_square, side, occupancy) {
+                Some(attacker) => attacker,
+                None => break,
+            };
+
+            occupancy &= !square_bit(attacker);
+            attacker_kind = self.state[attacker.0][attacker.1].unwrap().piece_kind;
+        }
+
+        while depth > 1 {
+            depth -= 1;
+            gain[depth - 1] = -(-gain[depth - 1]).max(gain[depth]);
+        }
+        gain[0]
+    }
+
+    fn occupancy(&self) -> Occupancy {
+        let mut occupancy = 0;
+
+        for r_index in 0..8 {
+            for f_index in 0..8 {
+                if self.state[r_index][f_index].is_some() {
+                    occupancy |= square_bit((r_index, f_index));
+                }
+            }
+        }
+        occupancy
+    }
+
+    /// Finds the cheapest piece of `side` attacking `square`, considering only
+    /// pieces still present in `occupancy`.
+    fn least_valuable_attacker(
+        &self,
+        square: Position,
+        side: Color,
+        occupancy: Occupancy,
+    ) -> Option<Position> {
+        let mut best: Option<(i32, Position)> = None;
+
+        let mut consider = |attacker: Position, piece_kind: PieceKind| {
+            let value = piece_value(piece_kind);
+
+            if best.is_none_or(|(best_value, _)| value < best_value) {
+                best = Some((value, attacker));
+            }
+        };
+
+        // A pawn attacks `square` from the squares a pawn of the other color
+        // would attack from `square`.
+        let pawn_offsets = match side {
+            Color::White => AttackOffsets::BLACK_PAWN,
+            Color::Black => AttackOffsets::WHITE_PAWN,
+        };
+
+        let leapers = [
+            (PieceKind::Pawn, pawn_offsets.to_vec()),
+            (PieceKind::Knight, AttackOffsets::KNIGHT.to_vec()),
+            (PieceKind::King, AttackOffsets::KING.to_vec()),
+        ];
+
+        for (piece_kind, offsets) in leapers {
+            for offset in offsets {
+                let attacker = match offset_square(square, offset) {
+                    Some(attacker) => attacker,
+                    None => continue,
+                };
+
+                if occupancy & square_bit(attacker) == 0 {
+                    continue;
+                }
+
+                if let Some(piece) = self.state[attacker.0][attacker.1] {
+                    if piece.color == side && piece.piece_kind == piece_kind {
+                        consider(attacker, piece_kind);
+                    }
+                }
+            }
+        }
+
+        let sliders = [
+            (AttackOffsets::BISHOP, PieceKind::Bishop),
+            (AttackOffsets::ROOK, PieceKind::Rook),
+        ];
+
+        for (directions, line_kind) in sliders {
+            for direction in directions {
+                let mut current = square;
+
+                while let Some(next) = offset_square(current, direction) {
+                    current = next;
+
+                    if occupancy & square_bit(current) == 0 {
+                        continue;
+                    }
+
+                    if let Some(piece) = self.state[current.0][current.1] {
+                        let moves_along_line =
+                            piece.piece_kind == line_kind || piece.piece_kind == PieceKind::Queen;
+
+                        if piece.color == side && moves_along_line {
+                            consider(current, piece.piece_kind);
+                        }
+                    }
+                    break;
+                }
+            }
+        }
+
+        best.map(|(_, attacker)| attacker)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn capture(board: &Board, lan: &str) -> Move {
+        Move::from_lan(board, lan).unwrap()
+    }
+
+    #[test]
+    fn undefended_capture_wins_the_piece() {
+        let board = Board::from_fen("4k3/8/8/3p4/8/8/8/3RK3 w - - 0 1").unwrap();
+
+        assert_eq!(board.see(capture(&board, "d1d5")), 100);
+    }
+
+    #[test]
+    fn defended_capture_loses_the_attacker() {
+        let board = Board::from_fen("4k3/8/4p3/3p4/8/8/8/3RK3 w - - 0 1").unwrap();
+
+        assert_eq!(board.see(capture(&board, "d1d5")), -400);
+    }
+
+    #[test]
+    fn x_ray_rook_recaptures() {
+        let board = Board::from_fen("4k3/8/1n6/3p4/8/8/3R4/3RK3 w - - 0 1").unwrap();
+        let m = capture(&board, "d2d5");
+
+        assert_eq!(board.see(m), -80);
+        assert!(board.see_ge(m, -80));
+        assert!(!board.see_ge(m, -79));
+    }
+
+    #[test]
+    fn queen_in_front_of_rook_battery() {
+        let board = Board::from_fen("4k3/8/1n6/3p4/8/8/3Q4/3RK3 w - - 0 1").unwrap();
+        let m = capture(&board, "d2d5");
+
+        assert_eq!(board.see(m), -480);
+        assert!(!board.see_ge(m, 0));
+    }
+
+    #[test]
+    fn rook_in_front_of_queen_battery() {
+        let board = Board::from_fen("4k3/8/4p3/3p4/8/8/3R4/3QK3 w - - 0 1").unwrap();
+        let m = capture(&board, "d2d5");
+
+        assert_eq!(board.see(m), -300);
+        assert!(board.see_ge(m, -300));
+        assert!(!board.see_ge(m, -299));
+    }
+
+    #[test]
+    fn both_sides_use_batteries() {
+        let board = Board::from_fen("3rk3/3q4/8/3p4/8/8/3R4/3RK3 w - - 0 1").unwrap();
+        let m = capture(&board, "d2d5");
+
+        assert_eq!(board.see(m), 0);
+        assert!(board.see_ge(m, 0));
+        assert!(!board.see_ge(m, 1));
+    }
+
+    #[test]
+    fn hanging_pieces_counts_x_rays() {
+        let board = Board::from_fen("4k3/8/1n6/3p4/8/8/3R4/3RK3 b - - 0 1").unwrap();
+
+        assert!(board.hanging_pieces(Color::Black).is_empty());
+        assert!(board.hanging_pieces(Color::White).is_empty());
+    }
+}
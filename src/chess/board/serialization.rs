@@ -0,0 +1,198 @@
+//! Compact serde representations: boards as FEN and moves as UCI strings.
+//!
+//! The derived implementations give the verbose structural form of every
+//! type. Fields that should use the compact form opt in with
+//! `#[serde(with = "fen")]` for boards, while moves, which need a position
+//! to be read, go through `UciMove` and `UciMoveSeed`.
+
+use std::fmt;
+
+use serde::de::{self, DeserializeSeed, Deserializer, Visitor};
+use serde::{Serialize, Serializer};
+
+use super::chess_move::Move;
+use super::{Board, Color};
+
+/// Boards as FEN strings. The move history is not kept.
+pub mod fen {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    use crate::chess::board::Board;
+
+    pub fn serialize<S: Serializer>(board: &Board, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&board.to_fen())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Board, D::Error> {
+        let fen = String::deserialize(deserializer)?;
+
+        Board::from_fen(&fen).map_err(serde::de::Error::custom)
+    }
+}
+
+/// A move written as its UCI string. Castling is written as the king's move,
+/// so the side making the move is needed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UciMove {
+    pub m: Move,
+    pub side: Color,
+}
+
+impl Serialize for UciMove {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.m.to_uci(self.side))
+    }
+}
+
+/// Reads a UCI string as a legal move in the given position.
+pub struct UciMoveSeed<'a>(pub &'a Board);
+
+impl<'de> DeserializeSeed<'de> for UciMoveSeed<'_> {
+    type Value = Move;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Move, D::Error> {
+        deserializer.deserialize_str(self)
+    }
+}
+
+impl Visitor<'_> for UciMoveSeed<'_> {
+    type Value = Move;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a move in UCI notation")
+    }
+
+    fn visit_str<E: de::Error>(self, uci: &str) -> Result<Move, E> {
+        let m = Move::from_uci(self.0, uci).map_err(E::custom)?;
+
+        if !self.0.get_legal_moves().contains(&m) {
+            return Err(E::custom(format!("Illegal move: '{uci}'")));
+        }
+
+        Ok(m)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::de::{value, DeserializeOwned, IntoDeserializer};
+
+    use super::*;
+    use crate::chess::board::piece::{Piece, PieceKind};
+    use crate::chess::board::{GameStatus, START_FEN};
+
+    fn deserialize_fen(fen: &str) -> Result<Board, value::Error> {
+        fen::deserialize(fen.into_deserializer())
+    }
+
+    fn round_trip<T: Serialize + DeserializeOwned>(value: &T) -> T {
+        serde_json::from_str(&serde_json::to_string(value).unwrap()).unwrap()
+    }
+
+    fn uci(fen: &str, uci: &str) -> Move {
+        Move::from_uci(&Board::from_fen(fen).unwrap(), uci).unwrap()
+    }
+
+    #[test]
+    fn moves_round_trip() {
+        let moves = [
+            uci(START_FEN, "g1f3"),
+            uci("4k3/8/8/3p4/4P3/8/8/4K3 w - - 0 1", "e4d5"),
+            uci("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", "e5d6"),
+            uci("1r2k3/P7/8/8/8/8/8/4K3 w - - 0 1", "a7b8n"),
+            Move::CastleKingside,
+            Move::CastleQueenside,
+        ];
+
+        for m in moves {
+            assert_eq!(round_trip(&m), m);
+        }
+    }
+
+    #[test]
+    fn pieces_round_trip() {
+        for kind in [
+            PieceKind::Pawn,
+            PieceKind::Knight,
+            PieceKind::Bishop,
+            PieceKind::Rook,
+            PieceKind::Queen,
+            PieceKind::King,
+        ] {
+            for color in [Color::White, Color::Black] {
+                let piece = Piece::new(kind, color);
+
+                assert_eq!(round_trip(&piece), piece);
+            }
+        }
+
+        assert_eq!(
+            serde_json::to_string(&Piece::new(PieceKind::Knight, Color::Black)).unwrap(),
+            r#"{"piece_kind":"Knight","color":"Black"}"#
+        );
+    }
+
+    #[test]
+    fn game_statuses_round_trip() {
+        for status in [
+            GameStatus::WhiteWin,
+            GameStatus::BlackWin,
+            GameStatus::Draw,
+            GameStatus::Ongoing,
+        ] {
+            assert_eq!(round_trip(&status), status);
+        }
+
+        assert_eq!(
+            serde_json::to_string(&GameStatus::Draw).unwrap(),
+            r#""Draw""#
+        );
+    }
+
+    #[test]
+    fn uci_moves_round_trip() {
+        let board = Board::from_fen("r3k2r/1P6/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+
+        for m in [
+            Move::CastleKingside,
+            Move::CastleQueenside,
+            Move::from_uci(&board, "b7a8r").unwrap(),
+        ] {
+            let json = serde_json::to_string(&UciMove {
+                m,
+                side: board.active_turn,
+            })
+            .unwrap();
+
+            let mut deserializer = serde_json::Deserializer::from_str(&json);
+            assert_eq!(
+                UciMoveSeed(&board).deserialize(&mut deserializer).unwrap(),
+                m
+            );
+        }
+
+        // Moves are read in the position, so illegal ones are refused.
+        let mut deserializer = serde_json::Deserializer::from_str(r#""e1e3""#);
+        assert!(UciMoveSeed(&board).deserialize(&mut deserializer).is_err());
+    }
+
+    #[test]
+    fn fen_round_trips() {
+        let fen = "r3k2r/1P4pp/8/3p4/4P3/8/6PP/R3K2R w KQkq - 0 1";
+
+        assert_eq!(deserialize_fen(fen).unwrap().to_fen(), fen);
+    }
+
+    #[test]
+    fn malformed_fen_is_an_error() {
+        for fen in [
+            "",
+            "4k3/8/8/8/8/8/8/4K3 w - -",
+            "4k3/8/8/8/8/8/8/4KX2 w - - 0 1",
+            "4k3/8/8/8/8/8/8/8 w - - 0 1",
+            "4k3/8/8/8/8/8/8/4K3 w - a0 0 1",
+        ] {
+            assert!(deserialize_fen(fen).is_err(), "{fen:?} was accepted");
+        }
+    }
+}
@@ -0,0 +1,124 @@
+use std::sync::OnceLock;
+
+use super::{Board, Color, Piece, PieceKind};
+
+const SEED: u64 = 0x9E37_79B9_7F4A_7C15;
+
+pub struct ZobristKeys {
+    pub pieces: [[u64; 64]; 12],
+    pub black_to_move: u64,
+    pub castling: [u64; 4],
+    pub en_passant_file: [u64; 8],
+}
+
+pub(crate) fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(SEED);
+
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+pub fn keys() -> &'static ZobristKeys {
+    static KEYS: OnceLock<ZobristKeys> = OnceLock::new();
+
+    KEYS.get_or_init(|| {
+        let mut state = 0;
+        let mut keys = ZobristKeys {
+            pieces: [[0; 64]; 12],
+            black_to_move: 0,
+            castling: [0; 4],
+            en_passant_file: [0; 8],
+        };
+
+        for piece_keys in keys.pieces.iter_mut() {
+            for key in piece_keys.iter_mut() {
+                *key = splitmix64(&mut state);
+            }
+        }
+
+        keys.black_to_move = splitmix64(&mut state);
+
+        for key in keys.castling.iter_mut() {
+            *key = splitmix64(&mut state);
+        }
+
+        for key in keys.en_passant_file.iter_mut() {
+            *key = splitmix64(&mut state);
+        }
+
+        keys
+    })
+}
+
+/// Index of `piece` into `ZobristKeys::pieces`.
+pub fn piece_index(piece: Piece) -> usize {
+    let kind_index = match piece.piece_kind {
+        PieceKind::Pawn => 0,
+        PieceKind::Knight => 1,
+        PieceKind::Bishop => 2,
+        PieceKind::Rook => 3,
+        PieceKind::Queen => 4,
+        PieceKind::King => 5,
+    };
+
+    match piece.color {
+        Color::White => kind_index,
+        Color::Black => kind_index + 6,
+    }
+}
+
+impl Board {
+    /// Zobrist hash of the position, computed from scratch.
+    pub fn hash(&self) -> u64 {
+        let keys = keys();
+        let mut hash = 0;
+
+        for (r_index, rank) in self.state.iter().enumerate() {
+            for (f_index, square) in rank.iter().enumerate() {
+                if let Some(piece) = square {
+                    hash ^= keys.pieces[piece_index(*piece)][r_index * 8 + f_index];
+                }
+            }
+        }
+
+        if self.active_turn == Color::Black {
+            hash ^= keys.black_to_move;
+        }
+
+        let castle_flags = [
+            self.castle_flags.white_kingside,
+            self.castle_flags.white_queenside,
+            self.castle_flags.black_kingside,
+            self.castle_flags.black_queenside,
+        ];
+
+        for (flag, key) in castle_flags.iter().zip(keys.castling) {
+            if *flag {
+                hash ^= key;
+            }
+        }
+
+        if let Some((_, file)) = self.en_passant_square {
+            hash ^= keys.en_passant_file[file];
+        }
+
+        hash
+    }
+    /// Zobrist hash of the pawns alone, the key of the pawn hash table.
+    pub fn pawn_hash(&self) -> u64 {
+        let keys = keys();
+        let mut hash = 0;
+
+        for (r_index, rank) in self.state.iter().enumerate() {
+            for (f_index, square) in rank.iter().enumerate() {
+                if let Some(piece) = square.filter(|piece| piece.piece_kind == PieceKind::Pawn) {
+                    hash ^= keys.pieces[piece_index(piece)][r_index * 8 + f_index];
+                }
+            }
+        }
+
+        hash
+    }
+}
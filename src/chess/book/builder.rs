@@ -0,0 +1,172 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use super::{encode_move, BookEntry, BookError};
+use crate::chess::board::chess_move::Move;
+use crate::chess::board::{Board, Color};
+
+/// Results of the games in which a move was played, from the point of view of
+/// the side that played it.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct MoveStats {
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+}
+
+impl MoveStats {
+    pub fn games(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+
+    /// Polyglot's weight: two points per win and one per draw.
+    pub fn points(&self) -> u32 {
+        2 * self.wins + self.draws
+    }
+}
+
+/// Collects move statistics per position from games and turns them into a
+/// Polyglot book.
+pub struct BookBuilder {
+    /// Moves after this many plies from the start of a game are not recorded.
+    pub max_ply: usize,
+    /// Moves played in fewer games are left out of the book.
+    pub min_count: u32,
+    /// Only record the moves of the side that won, so draws add nothing.
+    pub winners_only: bool,
+    stats: HashMap<(u64, u16), MoveStats>,
+}
+
+impl BookBuilder {
+    pub fn new(max_ply: usize) -> Self {
+        Self {
+            max_ply,
+            min_count: 1,
+            winners_only: false,
+            stats: HashMap::new(),
+        }
+    }
+
+    /// Replays `moves` from `start` and counts each move up to `max_ply` with
+    /// `result`, given from white's point of view.
+    pub fn add_game(&mut self, start: &Board, moves: &[Move], result: f64) {
+        let mut board = start.clone();
+
+        for &m in moves.iter().take(self.max_ply) {
+            let side = board.active_turn;
+
+            let score = match side {
+                Color::White => result,
+                Color::Black => 1.0 - result,
+            };
+
+            if !self.winners_only || score > 0.75 {
+                let stats = self
+                    .stats
+                    .entry((board.polyglot_key(), encode_move(m, side)))
+                    .or_default();
+
+                if score > 0.75 {
+                    stats.wins += 1;
+                } else if score < 0.25 {
+                    stats.losses += 1;
+                } else {
+                    stats.draws += 1;
+                }
+            }
+
+            board.make_move_unchecked(m);
+        }
+    }
+
+    /// Number of distinct position and move pairs seen so far.
+    pub fn move_count(&self) -> usize {
+        self.stats.len()
+    }
+
+    /// The book entries, sorted by key and then by weight, highest first.
+    /// Moves that never scored a point are left out, and weights are scaled
+    /// down per position when they do not fit in 16 bits.
+    pub fn build(&self) -> Vec<BookEntry> {
+        let mut by_key: HashMap<u64, Vec<(u16, u32)>> = HashMap::new();
+
+        for (&(key, raw_move), stats) in &self.stats {
+            if stats.games() >= self.min_count && stats.points() > 0 {
+                by_key
+                    .entry(key)
+                    .or_default()
+                    .push((raw_move, stats.points()));
+            }
+        }
+
+        let mut entries = Vec::new();
+
+        for (key, moves) in by_key {
+            let max_points = moves.iter().map(|&(_, points)| points).max().unwrap_or(0);
+            let scale = (max_points as f64 / u16::MAX as f64).max(1.0);
+
+            for (raw_move, points) in moves {
+                entries.push(BookEntry {
+                    key,
+                    raw_move,
+                    weight: ((points as f64 / scale).round() as u16).max(1),
+                    learn: 0,
+                });
+            }
+        }
+
+        entries.sort_by(|a, b| {
+            a.key
+                .cmp(&b.key)
+                .then(b.weight.cmp(&a.weight))
+                .then(a.raw_move.cmp(&b.raw_move))
+        });
+
+        entries
+    }
+
+    /// Writes the book to `path` and returns the number of entries.
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<usize, BookError> {
+        let entries = self.build();
+        let mut writer = BufWriter::new(File::create(path)?);
+
+        for entry in &entries {
+            writer.write_all(&entry.to_bytes())?;
+        }
+
+        writer.flush()?;
+        Ok(entries.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chess::book::PolyglotBook;
+    use crate::chess::pgn::parse_game;
+
+    #[test]
+    fn books_keep_en_passant_captures_and_underpromotions() {
+        let game =
+            parse_game("1. e4 Nf6 2. e5 d5 3. exd6 e6 4. dxc7 Qd7 5. cxb8=N Rxb8 1-0").unwrap();
+
+        let mut builder = BookBuilder::new(20);
+        builder.add_game(&game.start, &game.mainline(), 1.0);
+
+        let book = PolyglotBook::from_entries(builder.build());
+        let mut board = game.start.clone();
+
+        for m in game.mainline() {
+            if board.active_turn == Color::White {
+                let book_moves = book.book_moves(&board);
+
+                assert_eq!(book_moves.len(), 1);
+                assert_eq!(book_moves[0].m, m);
+            }
+
+            board.make_move_unchecked(m);
+        }
+    }
+}
@@ -0,0 +1,355 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use rand::Rng;
+
+use super::board::chess_move::{is_promotion_square, Move};
+use super::board::{Board, Color, PieceKind, Position};
+
+pub mod builder;
+pub mod keys;
+
+/// Size of one entry in a Polyglot book file.
+pub const ENTRY_SIZE: usize = 16;
+
+/// One entry of a Polyglot book. Entries are sorted by key, so all moves of a
+/// position are next to each other.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BookEntry {
+    pub key: u64,
+    pub raw_move: u16,
+    pub weight: u16,
+    pub learn: u32,
+}
+
+impl BookEntry {
+    pub fn from_bytes(bytes: &[u8; ENTRY_SIZE]) -> Self {
+        Self {
+            key: u64::from_be_bytes(bytes[0..8].try_into().unwrap()),
+            raw_move: u16::from_be_bytes([bytes[8], bytes[9]]),
+            weight: u16::from_be_bytes([bytes[10], bytes[11]]),
+            learn: u32::from_be_bytes(bytes[12..16].try_into().unwrap()),
+        }
+    }
+
+    pub fn to_bytes(&self) -> [u8; ENTRY_SIZE] {
+        let mut bytes = [0; ENTRY_SIZE];
+
+        bytes[0..8].copy_from_slice(&self.key.to_be_bytes());
+        bytes[8..10].copy_from_slice(&self.raw_move.to_be_bytes());
+        bytes[10..12].copy_from_slice(&self.weight.to_be_bytes());
+        bytes[12..16].copy_from_slice(&self.learn.to_be_bytes());
+
+        bytes
+    }
+}
+
+/// A legal move found in the book for a position.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BookMove {
+    pub m: Move,
+    pub weight: u16,
+}
+
+/// How a move is picked among the book moves of a position.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BookSelection {
+    /// The move with the highest weight.
+    BestMove,
+    /// A random move with probability proportional to its weight.
+    WeightedRandom,
+}
+
+/// A Polyglot opening book held in memory.
+pub struct PolyglotBook {
+    entries: Vec<BookEntry>,
+}
+
+impl PolyglotBook {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, BookError> {
+        let bytes = fs::read(path)?;
+
+        if bytes.len() % ENTRY_SIZE != 0 {
+            return Err(BookError::TruncatedFile);
+        }
+
+        let mut entries: Vec<BookEntry> = bytes
+            .chunks_exact(ENTRY_SIZE)
+            .map(|chunk| BookEntry::from_bytes(chunk.try_into().unwrap()))
+            .collect();
+
+        // Lookups rely on the order, so accept books that are not quite sorted.
+        entries.sort_by_key(|entry| entry.key);
+
+        Ok(Self { entries })
+    }
+
+    pub fn from_entries(mut entries: Vec<BookEntry>) -> Self {
+        entries.sort_by_key(|entry| entry.key);
+        Self { entries }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The entries stored under `key`.
+    pub fn entries(&self, key: u64) -> &[BookEntry] {
+        let start = self.entries.partition_point(|entry| entry.key < key);
+        let end = self.entries.partition_point(|entry| entry.key <= key);
+
+        &self.entries[start..end]
+    }
+
+    /// The legal book moves for `board`, highest weight first. Entries whose
+    /// move is not legal here, such as moves stored under a colliding key, are
+    /// skipped.
+    pub fn book_moves(&self, board: &Board) -> Vec<BookMove> {
+        let legal_moves = board.get_legal_moves();
+
+        let mut book_moves: Vec<BookMove> = self
+            .entries(board.polyglot_key())
+            .iter()
+            .filter_map(|entry| {
+                let m = decode_move(board, entry.raw_move)?;
+
+                legal_moves.contains(&m).then_some(BookMove {
+                    m,
+                    weight: entry.weight,
+                })
+            })
+            .collect();
+
+        book_moves.sort_by_key(|book_move| std::cmp::Reverse(book_move.weight));
+        book_moves
+    }
+
+    /// Picks a book move for `board`, or `None` when the position is not in the
+    /// book. With weighted selection, moves of weight 0 are never played.
+    pub fn choose_move(&self, board: &Board, selection: BookSelection) -> Option<Move> {
+        let book_moves = self.book_moves(board);
+
+        match selection {
+            BookSelection::BestMove => book_moves.first().map(|book_move| book_move.m),
+
+            BookSelection::WeightedRandom => {
+                let total: u32 = book_moves
+                    .iter()
+                    .map(|book_move| book_move.weight as u32)
+                    .sum();
+
+                if total == 0 {
+                    return None;
+                }
+
+                let mut pick = rand::thread_rng().gen_range(0..total);
+
+                book_moves.iter().find_map(|book_move| {
+                    if pick < book_move.weight as u32 {
+                        Some(book_move.m)
+                    } else {
+                        pick -= book_move.weight as u32;
+                        None
+                    }
+                })
+            }
+        }
+    }
+}
+
+/// Polyglot squares count from a1 = 0 to h8 = 63.
+fn square_index(square: Position) -> u16 {
+    ((7 - square.0) * 8 + square.1) as u16
+}
+
+fn square_from_index(index: u16) -> Position {
+    (7 - (index / 8) as usize, (index % 8) as usize)
+}
+
+/// Decodes a Polyglot move for `board`. Castling is stored as the king taking
+/// its own rook.
+pub fn decode_move(board: &Board, raw_move: u16) -> Option<Move> {
+    let target_square = square_from_index(raw_move & 0x3f);
+    let origin_square = square_from_index(raw_move >> 6 & 0x3f);
+
+    let promotion = match raw_move >> 12 & 0x7 {
+        0 => None,
+        1 => Some(PieceKind::Knight),
+        2 => Some(PieceKind::Bishop),
+        3 => Some(PieceKind::Rook),
+        4 => Some(PieceKind::Queen),
+        _ => return None,
+    };
+
+    let origin_piece = board.state[origin_square.0][origin_square.1]?;
+    let target_piece = board.state[target_square.0][target_square.1]
+        .or_else(|| board.en_passant_victim(origin_piece, origin_square, target_square));
+
+    if promotion.is_some() != is_promotion_square(origin_piece, target_square.0) {
+        return None;
+    }
+
+    if origin_piece.piece_kind == PieceKind::King
+        && target_piece.is_some_and(|piece| {
+            piece.piece_kind == PieceKind::Rook && piece.color == origin_piece.color
+        })
+    {
+        return match target_square.1 {
+            7 => Some(Move::CastleKingside),
+            0 => Some(Move::CastleQueenside),
+            _ => None,
+        };
+    }
+
+    Some(Move::PieceMove {
+        origin_square,
+        target_square,
+        origin_piece,
+        target_piece,
+        promotion,
+    })
+}
+
+/// Encodes `m`, played by `side`, as a Polyglot move.
+pub fn encode_move(m: Move, side: Color) -> u16 {
+    let back_rank = match side {
+        Color::White => 7,
+        Color::Black => 0,
+    };
+
+    let (origin_square, target_square) = match m {
+        Move::CastleKingside => ((back_rank, 4), (back_rank, 7)),
+        Move::CastleQueenside => ((back_rank, 4), (back_rank, 0)),
+        Move::PieceMove {
+            origin_square,
+            target_square,
+            ..
+        } => (origin_square, target_square),
+    };
+
+    let mut raw_move = square_index(origin_square) << 6 | square_index(target_square);
+
+    if let Move::PieceMove {
+        promotion: Some(promotion),
+        ..
+    } = m
+    {
+        raw_move |= match promotion {
+            PieceKind::Knight => 1,
+            PieceKind::Bishop => 2,
+            PieceKind::Rook => 3,
+            _ => 4,
+        } << 12;
+    }
+
+    raw_move
+}
+
+#[derive(Debug)]
+pub enum BookError {
+    Io(io::Error),
+    /// The file size is not a whole number of entries.
+    TruncatedFile,
+}
+
+impl From<io::Error> for BookError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl fmt::Display for BookError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Io(err) => {
+                write!(f, "I/O error: {err}")
+            }
+
+            Self::TruncatedFile => {
+                write!(
+                    f,
+                    "Book file is not a whole number of {ENTRY_SIZE} byte entries"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for BookError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chess::board::START_FEN;
+
+    // Six entries: 1. e4 (weight 10), 1. d4 (5) and the illegal 1. e2e5 (20)
+    // from the start, 1... e5 (8) and 1... c5 (3) after 1. e4, and castling
+    // written as e1h1 (7) after 1. e4 e5 2. Nf3 Nc6 3. Bc4 Nf6.
+    const BOOK: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/fixtures/polyglot/small.bin"
+    );
+
+    fn play(ucis: &[&str]) -> Board {
+        let mut board = Board::from_fen(START_FEN).unwrap();
+
+        for uci in ucis {
+            board.make_move_unchecked(Move::from_uci(&board, uci).unwrap());
+        }
+
+        board
+    }
+
+    fn book_moves(book: &PolyglotBook, board: &Board) -> Vec<(String, u16)> {
+        book.book_moves(board)
+            .iter()
+            .map(|book_move| (book_move.m.to_uci(board.active_turn), book_move.weight))
+            .collect()
+    }
+
+    #[test]
+    fn reads_book_moves_from_a_file() {
+        let book = PolyglotBook::load(BOOK).unwrap();
+        let start = play(&[]);
+
+        assert_eq!(book.len(), 6);
+        assert_eq!(book.entries(start.polyglot_key()).len(), 3);
+
+        // The illegal move is skipped despite its weight.
+        assert_eq!(
+            book_moves(&book, &start),
+            [("e2e4".to_string(), 10), ("d2d4".to_string(), 5)]
+        );
+        assert_eq!(
+            book_moves(&book, &play(&["e2e4"])),
+            [("e7e5".to_string(), 8), ("c7c5".to_string(), 3)]
+        );
+
+        let italian = play(&["e2e4", "e7e5", "g1f3", "b8c6", "f1c4", "g8f6"]);
+        assert_eq!(
+            book.book_moves(&italian),
+            [BookMove {
+                m: Move::CastleKingside,
+                weight: 7,
+            }]
+        );
+
+        assert_eq!(
+            book.choose_move(&start, BookSelection::BestMove),
+            Some(Move::from_uci(&start, "e2e4").unwrap())
+        );
+
+        let out_of_book = play(&["d2d4"]);
+        assert!(book.book_moves(&out_of_book).is_empty());
+        assert_eq!(
+            book.choose_move(&out_of_book, BookSelection::WeightedRandom),
+            None
+        );
+    }
+}
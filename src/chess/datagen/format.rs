@@ -0,0 +1,235 @@
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use super::DataPosition;
+use crate::chess::board::packed::{decode_fields, encode_fields, FIELDS_SIZE, MAX_PIECES};
+use crate::chess::board::Board;
+use crate::chess::evaluation::tuning::parse_result;
+
+// A binary record is 32 bytes, little endian:
+//
+//   0..29   the position fields of a packed board, see `board::packed`
+//   29..31  score from white's point of view
+//   31      result from white's point of view: 0 loss, 1 draw, 2 win
+
+pub const RECORD_SIZE: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DataFormat {
+    /// Fixed size records of `RECORD_SIZE` bytes.
+    Binary,
+    /// One `fen | score | result` line per position.
+    Text,
+}
+
+impl DataFormat {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "binary" | "bin" => Some(Self::Binary),
+            "text" | "txt" => Some(Self::Text),
+            _ => None,
+        }
+    }
+}
+
+pub fn encode(position: &DataPosition) -> Result<[u8; RECORD_SIZE], DataError> {
+    let mut record = [0; RECORD_SIZE];
+
+    let fields = encode_fields(&position.board).ok_or(DataError::TooManyPieces)?;
+    record[..FIELDS_SIZE].copy_from_slice(&fields);
+
+    let score = position.score.clamp(i16::MIN as i32, i16::MAX as i32) as i16;
+    record[29..31].copy_from_slice(&score.to_le_bytes());
+
+    record[31] = (position.result * 2.0).round() as u8;
+
+    Ok(record)
+}
+
+pub fn decode(record: &[u8; RECORD_SIZE]) -> Result<DataPosition, DataError> {
+    let board = decode_fields(record[..FIELDS_SIZE].try_into().unwrap())
+        .ok_or(DataError::MalformedRecord)?;
+
+    let result = match record[31] {
+        result @ 0..=2 => result as f64 / 2.0,
+        _ => return Err(DataError::MalformedRecord),
+    };
+
+    Ok(DataPosition {
+        board,
+        score: i16::from_le_bytes([record[29], record[30]]) as i32,
+        result,
+    })
+}
+
+fn result_string(result: f64) -> &'static str {
+    if result > 0.75 {
+        "1-0"
+    } else if result < 0.25 {
+        "0-1"
+    } else {
+        "1/2-1/2"
+    }
+}
+
+/// Formats a position as a `fen | score | result` line, without the newline.
+pub fn to_text(position: &DataPosition) -> String {
+    format!(
+        "{} | {} | {}",
+        position.board.to_fen(),
+        position.score,
+        result_string(position.result)
+    )
+}
+
+/// Parses a `fen | score | result` line.
+pub fn parse_text(line: &str) -> Result<DataPosition, DataError> {
+    let malformed = || DataError::MalformedLine(line.to_string());
+
+    let mut fields = line.split('|').map(str::trim);
+
+    let (fen, score, result) = match (fields.next(), fields.next(), fields.next(), fields.next()) {
+        (Some(fen), Some(score), Some(result), None) => (fen, score, result),
+        _ => return Err(malformed()),
+    };
+
+    let score = score.parse::<i32>().map_err(|_| malformed())?;
+    let result = parse_result(result).ok_or_else(malformed)?;
+    let board = Board::from_fen(fen).map_err(|_| malformed())?;
+
+    Ok(DataPosition {
+        board,
+        score,
+        result,
+    })
+}
+
+/// Appends labeled positions to a file in either format.
+pub struct DataWriter {
+    writer: BufWriter<File>,
+    format: DataFormat,
+    pub count: u64,
+}
+
+impl DataWriter {
+    pub fn create(path: impl AsRef<Path>, format: DataFormat) -> Result<Self, DataError> {
+        Ok(Self {
+            writer: BufWriter::new(File::create(path)?),
+            format,
+            count: 0,
+        })
+    }
+
+    pub fn write(&mut self, position: &DataPosition) -> Result<(), DataError> {
+        match self.format {
+            DataFormat::Binary => self.writer.write_all(&encode(position)?)?,
+            DataFormat::Text => writeln!(self.writer, "{}", to_text(position))?,
+        }
+
+        self.count += 1;
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> Result<(), DataError> {
+        Ok(self.writer.flush()?)
+    }
+}
+
+/// Reads labeled positions back from a file in either format.
+pub struct DataReader {
+    reader: BufReader<File>,
+    format: DataFormat,
+}
+
+impl DataReader {
+    pub fn open(path: impl AsRef<Path>, format: DataFormat) -> Result<Self, DataError> {
+        Ok(Self {
+            reader: BufReader::new(File::open(path)?),
+            format,
+        })
+    }
+
+    fn read_record(&mut self) -> Option<Result<DataPosition, DataError>> {
+        let mut record = [0; RECORD_SIZE];
+        let mut filled = 0;
+
+        while filled < RECORD_SIZE {
+            match self.reader.read(&mut record[filled..]) {
+                Ok(0) if filled == 0 => return None,
+                Ok(0) => return Some(Err(DataError::MalformedRecord)),
+                Ok(read) => filled += read,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => (),
+                Err(err) => return Some(Err(err.into())),
+            }
+        }
+
+        Some(decode(&record))
+    }
+
+    fn read_line(&mut self) -> Option<Result<DataPosition, DataError>> {
+        let mut line = String::new();
+
+        loop {
+            line.clear();
+
+            match self.reader.read_line(&mut line) {
+                Ok(0) => return None,
+                Ok(_) if line.trim().is_empty() => (),
+                Ok(_) => return Some(parse_text(line.trim())),
+                Err(err) => return Some(Err(err.into())),
+            }
+        }
+    }
+}
+
+impl Iterator for DataReader {
+    type Item = Result<DataPosition, DataError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.format {
+            DataFormat::Binary => self.read_record(),
+            DataFormat::Text => self.read_line(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum DataError {
+    Io(io::Error),
+    /// A position with more pieces than a binary record can hold.
+    TooManyPieces,
+    MalformedRecord,
+    MalformedLine(String),
+}
+
+impl From<io::Error> for DataError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl fmt::Display for DataError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Io(err) => {
+                write!(f, "I/O error: {err}")
+            }
+
+            Self::TooManyPieces => {
+                write!(f, "Position has more than {MAX_PIECES} pieces")
+            }
+
+            Self::MalformedRecord => {
+                write!(f, "Malformed binary record")
+            }
+
+            Self::MalformedLine(line) => {
+                write!(f, "Malformed position: '{line}'")
+            }
+        }
+    }
+}
+
+impl std::error::Error for DataError {}
@@ -0,0 +1,176 @@
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+
+use super::board::chess_move::Move;
+use super::board::{Board, Color, GameStatus};
+use super::options::EngineOptions;
+use super::search::{is_capture, is_promotion, SearchLimits, Searcher, MATE_BOUND};
+use super::tournament::{adjudicate, Game};
+
+pub mod format;
+
+pub use self::format::{DataError, DataFormat, DataReader, DataWriter};
+
+/// A recorded position labeled with the search score and the game result.
+#[derive(Clone)]
+pub struct DataPosition {
+    pub board: Board,
+    /// Search score in centipawns from white's point of view.
+    pub score: i32,
+    /// Game result from white's point of view: 1.0, 0.5 or 0.0.
+    pub result: f64,
+}
+
+/// Which positions are left out of the data because their score does not
+/// reflect the position at rest.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DataFilters {
+    pub skip_in_check: bool,
+    /// Positions where the side to move has a capture that wins material.
+    pub skip_pending_captures: bool,
+}
+
+impl Default for DataFilters {
+    fn default() -> Self {
+        Self {
+            skip_in_check: true,
+            skip_pending_captures: true,
+        }
+    }
+}
+
+impl DataFilters {
+    fn accepts(&self, board: &Board) -> bool {
+        if self.skip_in_check && board.is_in_check() {
+            return false;
+        }
+
+        if self.skip_pending_captures
+            && board
+                .get_legal_moves()
+                .into_iter()
+                .any(|m| is_capture(m) && board.see_ge(m, 1))
+        {
+            return false;
+        }
+
+        true
+    }
+}
+
+/// Plays self-play games from randomised openings at a fixed node count and
+/// records the quiet positions along the way.
+pub struct DataGenerator {
+    searcher: Searcher,
+    rng: StdRng,
+    pub nodes: u64,
+    /// Random moves played from the opening before the engine takes over.
+    pub random_plies: usize,
+    pub max_plies: usize,
+    pub filters: DataFilters,
+}
+
+impl DataGenerator {
+    pub fn new(options: EngineOptions, seed: u64) -> Self {
+        Self {
+            searcher: Searcher::new(options),
+            rng: StdRng::seed_from_u64(seed),
+            nodes: 5000,
+            random_plies: 8,
+            max_plies: 400,
+            filters: DataFilters::default(),
+        }
+    }
+
+    /// Plays `random_plies` random legal moves from `opening`, or returns `None`
+    /// if the game ends before then.
+    fn randomise(&mut self, opening: &Board) -> Option<Board> {
+        let mut board = opening.clone();
+
+        for _ in 0..self.random_plies {
+            let m = *board.get_legal_moves().choose(&mut self.rng)?;
+            board.make_move_unchecked(m);
+        }
+
+        (board.get_game_status() == GameStatus::Ongoing).then_some(board)
+    }
+
+    /// Plays one game from a randomised version of `opening` and returns its
+    /// quiet positions labeled with the result. Openings that end the game
+    /// during the random moves give no positions.
+    pub fn play_game(&mut self, opening: &Board) -> Vec<DataPosition> {
+        let start = match self.randomise(opening) {
+            Some(start) => start,
+            None => return Vec::new(),
+        };
+
+        self.searcher.clear_hash();
+
+        let mut game = Game::new(&start);
+        let mut positions = Vec::new();
+
+        let limits = SearchLimits {
+            nodes: Some(self.nodes),
+            ..Default::default()
+        };
+
+        let result = loop {
+            if let Some((result, _)) = adjudicate(&game, self.max_plies) {
+                break result;
+            }
+
+            self.searcher.set_game_history(game.previous_hashes());
+
+            let search = self.searcher.search(&game.board, &limits);
+
+            let m = match search.best_move {
+                Some(m) => m,
+                None => break GameStatus::Draw,
+            };
+
+            let side = game.board.active_turn;
+
+            // Once a mate is found the result is settled and the remaining
+            // positions say nothing the score does not.
+            if search.score.abs() >= MATE_BOUND {
+                break match (side, search.score > 0) {
+                    (Color::White, true) | (Color::Black, false) => GameStatus::WhiteWin,
+                    _ => GameStatus::BlackWin,
+                };
+            }
+
+            if is_quiet(m) && self.filters.accepts(&game.board) {
+                let score = match side {
+                    Color::White => search.score,
+                    Color::Black => -search.score,
+                };
+
+                positions.push(DataPosition {
+                    board: game.board.clone(),
+                    score,
+                    result: 0.5,
+                });
+            }
+
+            game.play(m);
+        };
+
+        let result = match result {
+            GameStatus::WhiteWin => 1.0,
+            GameStatus::BlackWin => 0.0,
+            _ => 0.5,
+        };
+
+        for position in &mut positions {
+            position.result = result;
+        }
+
+        positions
+    }
+}
+
+/// The best move neither captures nor promotes.
+fn is_quiet(m: Move) -> bool {
+    !is_capture(m) && !is_promotion(m)
+}
@@ -0,0 +1,302 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use super::board::chess_move::{Move, MoveError};
+use super::board::Board;
+
+pub mod suite;
+
+/// A position in Extended Position Description: the first four FEN fields
+/// followed by operations such as `bm Nf3; id "WAC.001";`.
+#[derive(Clone)]
+pub struct Epd {
+    pub board: Board,
+    /// Opcodes and their operands, in the order they were written.
+    pub operations: Vec<(String, Vec<String>)>,
+}
+
+impl Epd {
+    pub fn parse(line: &str) -> Result<Self, EpdError> {
+        let malformed = || EpdError::MalformedLine(line.to_string());
+
+        let mut fen_fields = Vec::new();
+        let mut rest = line.trim();
+
+        for _ in 0..4 {
+            let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+
+            if end == 0 {
+                return Err(malformed());
+            }
+
+            fen_fields.push(&rest[..end]);
+            rest = rest[end..].trim_start();
+        }
+
+        let operations = parse_operations(rest).ok_or_else(malformed)?;
+
+        let operand = |opcode: &str| {
+            operations
+                .iter()
+                .find(|(name, _)| name == opcode)
+                .and_then(|(_, operands)| operands.first())
+        };
+
+        let halfmove_clock = operand("hmvc").map_or("0", String::as_str);
+        let fullmove_number = operand("fmvn").map_or("1", String::as_str);
+
+        let fen = format!(
+            "{} {halfmove_clock} {fullmove_number}",
+            fen_fields.join(" ")
+        );
+        let board = Board::from_fen(&fen).map_err(|_| EpdError::InvalidFen(fen.clone()))?;
+
+        Ok(Self { board, operations })
+    }
+
+    /// The operands of `opcode`, or `None` when it is missing.
+    pub fn operation(&self, opcode: &str) -> Option<&[String]> {
+        self.operations
+            .iter()
+            .find(|(name, _)| name == opcode)
+            .map(|(_, operands)| operands.as_slice())
+    }
+
+    pub fn id(&self) -> Option<&str> {
+        self.operation("id")?.first().map(String::as_str)
+    }
+
+    /// Comment `c0` to `c9`.
+    pub fn comment(&self, index: usize) -> Option<&str> {
+        self.operation(&format!("c{index}"))?
+            .first()
+            .map(String::as_str)
+    }
+
+    /// The centipawn evaluation from the side to move's point of view.
+    pub fn centipawn_eval(&self) -> Option<i32> {
+        self.operation("ce")?.first()?.parse().ok()
+    }
+
+    /// The moves of `bm`, empty when it is missing.
+    pub fn best_moves(&self) -> Result<Vec<Move>, EpdError> {
+        self.san_moves("bm")
+    }
+
+    /// The moves of `am`, empty when it is missing.
+    pub fn avoid_moves(&self) -> Result<Vec<Move>, EpdError> {
+        self.san_moves("am")
+    }
+
+    /// The principal variation of `pv`, played out from the position.
+    pub fn pv(&self) -> Result<Vec<Move>, EpdError> {
+        let mut board = self.board.clone();
+        let mut moves = Vec::new();
+
+        for san in self.operation("pv").unwrap_or_default() {
+            let m = board
+                .move_from_san(san)
+                .map_err(|err| EpdError::IllegalMove(san.clone(), err))?;
+
+            board.make_move_unchecked(m);
+            moves.push(m);
+        }
+
+        Ok(moves)
+    }
+
+    /// The points awarded per move in STS suites, taken from `c0` such as
+    /// `"Nf3=10, Nd2=5"`, or from the moves in `c7` and points in `c8`.
+    /// Moves that are not legal in the position are skipped.
+    pub fn move_points(&self) -> Vec<(Move, u32)> {
+        let pairs: Vec<(String, String)> = match (self.comment(7), self.comment(8)) {
+            (Some(moves), Some(points)) => moves
+                .split_whitespace()
+                .zip(points.split_whitespace())
+                .map(|(san, points)| (san.to_string(), points.to_string()))
+                .collect(),
+            _ => self
+                .comment(0)
+                .unwrap_or_default()
+                .split(',')
+                .filter_map(|pair| pair.trim().split_once('='))
+                .map(|(san, points)| (san.to_string(), points.to_string()))
+                .collect(),
+        };
+
+        pairs
+            .iter()
+            .filter_map(|(san, points)| {
+                Some((self.board.move_from_san(san).ok()?, points.parse().ok()?))
+            })
+            .collect()
+    }
+
+    fn san_moves(&self, opcode: &str) -> Result<Vec<Move>, EpdError> {
+        self.operation(opcode)
+            .unwrap_or_default()
+            .iter()
+            .map(|san| {
+                self.board
+                    .move_from_san(san)
+                    .map_err(|err| EpdError::IllegalMove(san.clone(), err))
+            })
+            .collect()
+    }
+
+    /// The position in EPD, with the clocks left out of the FEN fields.
+    pub fn to_epd(&self) -> String {
+        let fen = self.board.to_fen();
+        let mut epd: String = fen.split(' ').take(4).collect::<Vec<_>>().join(" ");
+
+        for (opcode, operands) in &self.operations {
+            epd.push(' ');
+            epd.push_str(opcode);
+
+            // Identifiers and comments are strings, which are always quoted.
+            let is_string = opcode == "id" || matches!(opcode.as_bytes(), [b'c', b'0'..=b'9']);
+
+            for operand in operands {
+                let needs_quotes =
+                    operand.is_empty() || operand.contains(|c: char| c.is_whitespace() || c == ';');
+
+                if is_string || needs_quotes {
+                    epd.push_str(&format!(" \"{operand}\""));
+                } else {
+                    epd.push_str(&format!(" {operand}"));
+                }
+            }
+
+            epd.push(';');
+        }
+
+        epd
+    }
+}
+
+/// Splits `text` into `opcode operand...;` operations, where operands may be
+/// quoted strings containing spaces and semicolons.
+fn parse_operations(text: &str) -> Option<Vec<(String, Vec<String>)>> {
+    let mut operations = Vec::new();
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut chars = text.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                for c in chars.by_ref() {
+                    if c == '"' {
+                        break;
+                    }
+                    word.push(c);
+                }
+
+                words.push(std::mem::take(&mut word));
+            }
+
+            ';' => {
+                if !word.is_empty() {
+                    words.push(std::mem::take(&mut word));
+                }
+
+                if !words.is_empty() {
+                    let opcode = words.remove(0);
+                    operations.push((opcode, std::mem::take(&mut words)));
+                }
+            }
+
+            c if c.is_whitespace() => {
+                if !word.is_empty() {
+                    words.push(std::mem::take(&mut word));
+                }
+            }
+
+            c => word.push(c),
+        }
+    }
+
+    // Every operation must end with a semicolon.
+    if words.is_empty() && word.is_empty() {
+        Some(operations)
+    } else {
+        None
+    }
+}
+
+/// Reads every position of an EPD file, skipping blank lines and lines
+/// starting with `#`.
+pub fn load_epd_file(path: impl AsRef<Path>) -> Result<Vec<Epd>, EpdError> {
+    fs::read_to_string(path)?
+        .lines()
+        .filter(|line| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+        .map(Epd::parse)
+        .collect()
+}
+
+#[derive(Debug)]
+pub enum EpdError {
+    Io(io::Error),
+    MalformedLine(String),
+    InvalidFen(String),
+    IllegalMove(String, MoveError),
+}
+
+impl From<io::Error> for EpdError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl fmt::Display for EpdError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Io(err) => {
+                write!(f, "I/O error: {err}")
+            }
+
+            Self::MalformedLine(line) => {
+                write!(f, "Malformed EPD line: '{line}'")
+            }
+
+            Self::InvalidFen(fen) => {
+                write!(f, "Invalid FEN: '{fen}'")
+            }
+
+            Self::IllegalMove(san, err) => {
+                write!(f, "Cannot read move '{san}': {err}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for EpdError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_operations() {
+        let epd = Epd::parse("4k3/8/8/8/8/8/8/4K3 w - - bm Kd2; id \"test\";").unwrap();
+
+        assert_eq!(epd.board.to_fen(), "4k3/8/8/8/8/8/8/4K3 w - - 0 1");
+        assert_eq!(epd.operation("id"), Some(&[String::from("test")][..]));
+    }
+
+    #[test]
+    fn invalid_position_is_an_error() {
+        for line in [
+            "4k3/8/8/8/8/8/8/4KX2 w - - bm e2;",
+            "4k3/8/8/8/8/8/8/8 w - - bm e2;",
+            "4k3/8/8/8/8/8/8 w - - bm e2;",
+        ] {
+            assert!(
+                matches!(Epd::parse(line), Err(EpdError::InvalidFen(_))),
+                "{line:?} was not rejected as an invalid FEN"
+            );
+        }
+    }
+}
@@ -0,0 +1,156 @@
+use std::fmt;
+use std::time::Duration;
+
+use super::{Epd, EpdError};
+use crate::chess::board::chess_move::Move;
+use crate::chess::search::{SearchLimits, Searcher};
+
+/// How the engine did on one test position.
+#[derive(Debug, Clone)]
+pub struct PositionResult {
+    /// The `id` of the position, or its number in the suite.
+    pub id: String,
+    pub best_move: Option<Move>,
+    /// The engine's move in SAN, or `-` when it found none.
+    pub san: String,
+    pub solved: bool,
+    /// STS points for the engine's move, out of `max_points`.
+    pub points: u32,
+    pub max_points: u32,
+    pub depth: u8,
+    pub nodes: u64,
+    pub time: Duration,
+}
+
+impl fmt::Display for PositionResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:<16} {:<8} {:<8} depth {:>2} nodes {:>9} time {:>6} ms",
+            self.id,
+            self.san,
+            if self.solved { "solved" } else { "failed" },
+            self.depth,
+            self.nodes,
+            self.time.as_millis()
+        )?;
+
+        if self.max_points > 0 {
+            write!(f, " points {}/{}", self.points, self.max_points)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The results of a whole suite.
+#[derive(Debug, Clone, Default)]
+pub struct SuiteReport {
+    pub results: Vec<PositionResult>,
+}
+
+impl SuiteReport {
+    pub fn solved(&self) -> usize {
+        self.results.iter().filter(|result| result.solved).count()
+    }
+
+    /// The STS score and the maximum possible, over positions with points.
+    pub fn points(&self) -> (u32, u32) {
+        self.results
+            .iter()
+            .fold((0, 0), |(points, max_points), result| {
+                (points + result.points, max_points + result.max_points)
+            })
+    }
+}
+
+impl fmt::Display for SuiteReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Solved {}/{}", self.solved(), self.results.len())?;
+
+        let (points, max_points) = self.points();
+
+        if max_points > 0 {
+            write!(
+                f,
+                ", score {points}/{max_points} ({:.1}%)",
+                100.0 * points as f64 / max_points as f64
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Searches `epd` with `limits` and checks the move against its `bm` and `am`
+/// operations and its STS points. A position with neither `bm` nor `am` is
+/// solved when the move earns full points.
+pub fn run_position(
+    searcher: &mut Searcher,
+    epd: &Epd,
+    limits: &SearchLimits,
+    number: usize,
+) -> Result<PositionResult, EpdError> {
+    let best_moves = epd.best_moves()?;
+    let avoid_moves = epd.avoid_moves()?;
+    let move_points = epd.move_points();
+
+    let search = searcher.search(&epd.board, limits);
+
+    let max_points = move_points
+        .iter()
+        .map(|&(_, points)| points)
+        .max()
+        .unwrap_or(0);
+    let points = move_points
+        .iter()
+        .find(|&&(m, _)| Some(m) == search.best_move)
+        .map_or(0, |&(_, points)| points);
+
+    let solved = match search.best_move {
+        None => false,
+        Some(m) if !best_moves.is_empty() => best_moves.contains(&m),
+        Some(m) if !avoid_moves.is_empty() => !avoid_moves.contains(&m),
+        Some(_) => max_points > 0 && points == max_points,
+    };
+
+    Ok(PositionResult {
+        id: epd.id().map_or_else(|| number.to_string(), str::to_string),
+        best_move: search.best_move,
+        san: search
+            .best_move
+            .map_or_else(|| String::from("-"), |m| epd.board.move_to_san(m)),
+        solved,
+        points,
+        max_points,
+        depth: search.depth,
+        nodes: search.nodes,
+        time: search.time,
+    })
+}
+
+/// Runs every position of a suite, calling `on_result` as each one finishes.
+/// The hash table is cleared between positions so results do not depend on
+/// the order. Positions whose moves cannot be read are reported and skipped.
+pub fn run_suite(
+    searcher: &mut Searcher,
+    positions: &[Epd],
+    limits: &SearchLimits,
+    mut on_result: impl FnMut(Result<&PositionResult, EpdError>),
+) -> SuiteReport {
+    let mut report = SuiteReport::default();
+
+    for (index, epd) in positions.iter().enumerate() {
+        searcher.clear_hash();
+
+        match run_position(searcher, epd, limits, index + 1) {
+            Ok(result) => {
+                on_result(Ok(&result));
+                report.results.push(result);
+            }
+            Err(err) => on_result(Err(err)),
+        }
+    }
+
+    report
+}
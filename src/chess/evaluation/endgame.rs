@@ -0,0 +1,517 @@
+use super::params::piece_kind_index;
+use super::{kpk, piece_value, BISHOP_VALUE, PAWN_VALUE, ROOK_VALUE};
+use crate::chess::board::{Board, Color, PieceKind, Position};
+
+/// Score of an endgame known to be won, above anything the evaluation reaches
+/// otherwise but below mate scores.
+pub const KNOWN_WIN: i32 = 10000;
+
+/// Scale factors are out of this.
+pub const SCALE_NORMAL: i32 = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EndgameScore {
+    /// Replaces the evaluation, from white's point of view.
+    Exact(i32),
+    /// Scales the evaluation by this many 64ths.
+    Scale(i32),
+}
+
+/// A recognised endgame and what it does to the evaluation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Endgame {
+    pub name: &'static str,
+    pub score: EndgameScore,
+}
+
+impl Endgame {
+    /// Applies the endgame knowledge to `score`, from white's point of view.
+    pub fn apply(&self, score: i32) -> i32 {
+        match self.score {
+            EndgameScore::Exact(exact) => exact,
+            EndgameScore::Scale(factor) => score * factor / SCALE_NORMAL,
+        }
+    }
+}
+
+/// Applies any recognised endgame to `score`, from the side to move's point of view.
+pub fn adjust(board: &Board, score: i32) -> i32 {
+    let endgame = match probe(board) {
+        Some(endgame) => endgame,
+        None => return score,
+    };
+
+    match board.active_turn {
+        Color::White => endgame.apply(score),
+        Color::Black => -endgame.apply(-score),
+    }
+}
+
+/// Piece counts of both sides, indexed by color and piece kind.
+struct Material {
+    counts: [[u32; 6]; 2],
+}
+
+impl Material {
+    fn new(board: &Board) -> Self {
+        let mut counts = [[0; 6]; 2];
+
+        for piece in board.state.iter().flatten().flatten() {
+            counts[side_index(piece.color)][piece_kind_index(piece.piece_kind)] += 1;
+        }
+
+        Self { counts }
+    }
+
+    fn count(&self, side: Color, kind: PieceKind) -> u32 {
+        self.counts[side_index(side)][piece_kind_index(kind)]
+    }
+
+    /// Whether `side` has exactly `pieces` besides the king, e.g. `[Knight, Bishop]`.
+    fn is(&self, side: Color, pieces: &[PieceKind]) -> bool {
+        [
+            PieceKind::Pawn,
+            PieceKind::Knight,
+            PieceKind::Bishop,
+            PieceKind::Rook,
+            PieceKind::Queen,
+        ]
+        .iter()
+        .all(|&kind| {
+            self.count(side, kind) == pieces.iter().filter(|&&piece| piece == kind).count() as u32
+        })
+    }
+
+    fn is_bare_king(&self, side: Color) -> bool {
+        self.is(side, &[])
+    }
+
+    fn non_pawn_material(&self, side: Color) -> i32 {
+        [
+            PieceKind::Knight,
+            PieceKind::Bishop,
+            PieceKind::Rook,
+            PieceKind::Queen,
+        ]
+        .iter()
+        .map(|&kind| self.count(side, kind) as i32 * piece_value(kind))
+        .sum()
+    }
+}
+
+fn side_index(side: Color) -> usize {
+    match side {
+        Color::White => 0,
+        Color::Black => 1,
+    }
+}
+
+fn distance(a: Position, b: Position) -> i32 {
+    (a.0 as i32 - b.0 as i32)
+        .abs()
+        .max((a.1 as i32 - b.1 as i32).abs())
+}
+
+/// 1 for light squares, 0 for dark squares.
+fn square_color(square: Position) -> usize {
+    (square.0 + square.1 + 1) % 2
+}
+
+/// Bonus for driving a king towards the edge of the board.
+fn push_to_edge(square: Position) -> i32 {
+    let rank_distance = (square.0 as i32 * 2 - 7).abs() / 2;
+    let file_distance = (square.1 as i32 * 2 - 7).abs() / 2;
+
+    20 * (rank_distance + file_distance) + 20
+}
+
+/// Bonus for bringing two kings close together.
+fn push_close(a: Position, b: Position) -> i32 {
+    140 - 20 * distance(a, b)
+}
+
+fn king(board: &Board, side: Color) -> Option<Position> {
+    match side {
+        Color::White => board.white_king_position,
+        Color::Black => board.black_king_position,
+    }
+}
+
+fn find_pieces(board: &Board, side: Color, kind: PieceKind) -> Vec<Position> {
+    let mut squares = Vec::new();
+
+    for (r_index, rank) in board.state.iter().enumerate() {
+        for (f_index, square) in rank.iter().enumerate() {
+            if square.is_some_and(|piece| piece.color == side && piece.piece_kind == kind) {
+                squares.push((r_index, f_index));
+            }
+        }
+    }
+
+    squares
+}
+
+/// Converts a score for `strong` into white's point of view.
+fn for_side(strong: Color, score: i32) -> EndgameScore {
+    match strong {
+        Color::White => EndgameScore::Exact(score),
+        Color::Black => EndgameScore::Exact(-score),
+    }
+}
+
+/// Looks up the endgame knowledge that applies to `board`, if any.
+pub fn probe(board: &Board) -> Option<Endgame> {
+    let material = Material::new(board);
+
+    let no_pawns = material.count(Color::White, PieceKind::Pawn) == 0
+        && material.count(Color::Black, PieceKind::Pawn) == 0;
+
+    // A single minor piece or less on each side cannot mate.
+    if no_pawns
+        && material.non_pawn_material(Color::White) <= BISHOP_VALUE
+        && material.non_pawn_material(Color::Black) <= BISHOP_VALUE
+    {
+        return Some(Endgame {
+            name: "insufficient material",
+            score: EndgameScore::Exact(0),
+        });
+    }
+
+    for strong in [Color::White, Color::Black] {
+        let weak = strong.opposite();
+
+        let (strong_king, weak_king) = match (king(board, strong), king(board, weak)) {
+            (Some(strong_king), Some(weak_king)) => (strong_king, weak_king),
+            _ => return None,
+        };
+
+        let endgame = if !material.is_bare_king(weak) {
+            None
+        } else if material.is(strong, &[PieceKind::Knight, PieceKind::Bishop]) {
+            Some(kbnk(board, strong, strong_king, weak_king))
+        } else if material.count(strong, PieceKind::Queen) > 0
+            || material.count(strong, PieceKind::Rook) > 0
+        {
+            Some(kxk(&material, strong, strong_king, weak_king))
+        } else if material.is(strong, &[PieceKind::Pawn]) {
+            Some(kpk(board, strong, strong_king, weak_king))
+        } else if material.count(strong, PieceKind::Bishop) == 1
+            && material.non_pawn_material(strong) == BISHOP_VALUE
+        {
+            wrong_bishop(board, strong, weak_king)
+        } else {
+            None
+        };
+
+        if endgame.is_some() {
+            return endgame;
+        }
+
+        if material.is(strong, &[PieceKind::Rook]) && material.is(weak, &[PieceKind::Pawn]) {
+            return Some(krkp(board, strong, strong_king, weak_king));
+        }
+    }
+
+    opposite_bishops(board, &material)
+}
+
+/// Lone king against a queen or rook: drive it to the edge and bring the king up.
+fn kxk(material: &Material, strong: Color, strong_king: Position, weak_king: Position) -> Endgame {
+    let score = KNOWN_WIN
+        + material.non_pawn_material(strong)
+        + material.count(strong, PieceKind::Pawn) as i32 * PAWN_VALUE
+        + push_to_edge(weak_king)
+        + push_close(strong_king, weak_king);
+
+    Endgame {
+        name: "KXK",
+        score: for_side(strong, score),
+    }
+}
+
+/// Bishop and knight mate only in a corner of the bishop's color, so drive the
+/// lone king there.
+fn kbnk(board: &Board, strong: Color, strong_king: Position, weak_king: Position) -> Endgame {
+    let bishop = find_pieces(board, strong, PieceKind::Bishop)[0];
+
+    let corners: [Position; 2] = if square_color(bishop) == square_color((7, 0)) {
+        [(7, 0), (0, 7)]
+    } else {
+        [(0, 0), (7, 7)]
+    };
+
+    let corner_distance = corners
+        .iter()
+        .map(|&corner| distance(weak_king, corner))
+        .min()
+        .unwrap();
+
+    let score = KNOWN_WIN + piece_value(PieceKind::Knight) + BISHOP_VALUE - 40 * corner_distance
+        + push_close(strong_king, weak_king);
+
+    Endgame {
+        name: "KBNK",
+        score: for_side(strong, score),
+    }
+}
+
+/// Rank and file on a board seen from `side`, with its first rank as rank 0.
+fn relative(square: Position, side: Color) -> (i32, i32) {
+    let rank = match side {
+        Color::White => 7 - square.0,
+        Color::Black => square.0,
+    };
+
+    (square.1 as i32, rank as i32)
+}
+
+/// King and pawn against king, looked up in the bitbase.
+fn kpk(board: &Board, strong: Color, strong_king: Position, weak_king: Position) -> Endgame {
+    let pawn = find_pieces(board, strong, PieceKind::Pawn)[0];
+    let relative_pawn = relative(pawn, strong);
+
+    let wins = kpk::probe(
+        board.active_turn == strong,
+        relative(strong_king, strong),
+        relative(weak_king, strong),
+        relative_pawn,
+    );
+
+    let score = if wins {
+        KNOWN_WIN + PAWN_VALUE + relative_pawn.1 * 10
+    } else {
+        0
+    };
+
+    Endgame {
+        name: "KPK",
+        score: for_side(strong, score),
+    }
+}
+
+/// Bishop and rook pawns whose promotion square the bishop does not control
+/// cannot win once the lone king reaches the corner.
+fn wrong_bishop(board: &Board, strong: Color, weak_king: Position) -> Option<Endgame> {
+    let pawns = find_pieces(board, strong, PieceKind::Pawn);
+    let file = pawns.first()?.1;
+
+    if (file != 0 && file != 7) || pawns.iter().any(|pawn| pawn.1 != file) {
+        return None;
+    }
+
+    let promotion = match strong {
+        Color::White => (0, file),
+        Color::Black => (7, file),
+    };
+
+    let bishop = find_pieces(board, strong, PieceKind::Bishop)[0];
+
+    if square_color(bishop) == square_color(promotion) || distance(weak_king, promotion) > 1 {
+        return None;
+    }
+
+    Some(Endgame {
+        name: "KBPsK wrong bishop",
+        score: EndgameScore::Exact(0),
+    })
+}
+
+/// Rook against pawn, using the position of the kings relative to the pawn.
+fn krkp(board: &Board, strong: Color, strong_king: Position, weak_king: Position) -> Endgame {
+    let weak = strong.opposite();
+
+    let rook = find_pieces(board, strong, PieceKind::Rook)[0];
+    let pawn = find_pieces(board, weak, PieceKind::Pawn)[0];
+
+    // Seen from the weak side, whose pawn moves up to rank 7.
+    let strong_king = relative(strong_king, weak);
+    let weak_king = relative(weak_king, weak);
+    let rook = relative(rook, weak);
+    let pawn = relative(pawn, weak);
+
+    let queening = (pawn.0, 7);
+    let ahead = (pawn.0, pawn.1 + 1);
+
+    let distance = |a: (i32, i32), b: (i32, i32)| (a.0 - b.0).abs().max((a.1 - b.1).abs());
+    let weak_to_move = board.active_turn == weak;
+    let strong_to_move = !weak_to_move;
+
+    let score = if strong_king.0 == pawn.0 && strong_king.1 > pawn.1 {
+        // The strong king stands in front of the pawn.
+        ROOK_VALUE - distance(strong_king, pawn)
+    } else if distance(weak_king, pawn) >= 3 + weak_to_move as i32 && distance(weak_king, rook) >= 3
+    {
+        ROOK_VALUE - distance(strong_king, pawn)
+    } else if weak_king.1 >= 5
+        && distance(weak_king, pawn) == 1
+        && strong_king.1 <= 4
+        && distance(strong_king, pawn) > 2 + strong_to_move as i32
+    {
+        80 - 8 * distance(strong_king, pawn)
+    } else {
+        200 - 8
+            * (distance(strong_king, ahead) - distance(weak_king, ahead) - distance(pawn, queening))
+    };
+
+    Endgame {
+        name: "KRKP",
+        score: for_side(strong, score),
+    }
+}
+
+/// Bishops on opposite colors make the weaker side's drawing chances much
+/// better, especially without other pieces.
+fn opposite_bishops(board: &Board, material: &Material) -> Option<Endgame> {
+    if material.count(Color::White, PieceKind::Bishop) != 1
+        || material.count(Color::Black, PieceKind::Bishop) != 1
+    {
+        return None;
+    }
+
+    let white_bishop = find_pieces(board, Color::White, PieceKind::Bishop)[0];
+    let black_bishop = find_pieces(board, Color::Black, PieceKind::Bishop)[0];
+
+    if square_color(white_bishop) == square_color(black_bishop) {
+        return None;
+    }
+
+    let only_bishops = material.non_pawn_material(Color::White) == BISHOP_VALUE
+        && material.non_pawn_material(Color::Black) == BISHOP_VALUE;
+
+    Some(Endgame {
+        name: "opposite colored bishops",
+        score: EndgameScore::Scale(if only_bishops { 22 } else { 46 }),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chess::evaluation::evaluate;
+
+    fn board(fen: &str) -> Board {
+        Board::from_fen(fen).unwrap()
+    }
+
+    fn probe_fen(fen: &str) -> Option<Endgame> {
+        probe(&board(fen))
+    }
+
+    fn exact(fen: &str) -> i32 {
+        match probe_fen(fen).unwrap().score {
+            EndgameScore::Exact(score) => score,
+            score => panic!("{fen} scales by {score:?}"),
+        }
+    }
+
+    /// White's evaluation without any endgame knowledge applied.
+    fn unscaled(board: &Board) -> i32 {
+        let mut trace = board.eval_trace();
+        trace.endgame = None;
+        trace.total()
+    }
+
+    #[test]
+    fn kpk_wins_and_draws() {
+        // The king on the sixth in front of its pawn wins with either side to move.
+        for fen in [
+            "4k3/8/4K3/4P3/8/8/8/8 w - - 0 1",
+            "4k3/8/4K3/4P3/8/8/8/8 b - - 0 1",
+            "k7/8/1K6/1P6/8/8/8/8 w - - 0 1",
+        ] {
+            assert!(exact(fen) > KNOWN_WIN, "{fen}");
+        }
+
+        // With black to move it is stalemate, with white to move Kd6 wins.
+        assert_eq!(exact("4k3/4P3/4K3/8/8/8/8/8 b - - 0 1"), 0);
+        assert!(exact("4k3/4P3/4K3/8/8/8/8/8 w - - 0 1") > KNOWN_WIN);
+
+        // The pawn falls.
+        assert_eq!(exact("8/8/8/8/8/8/3kP3/7K b - - 0 1"), 0);
+
+        // The mirrored win belongs to black.
+        assert!(exact("8/8/8/8/4p3/4k3/8/4K3 w - - 0 1") < -KNOWN_WIN);
+    }
+
+    #[test]
+    fn kpk_rook_pawn_draws_with_the_king_in_the_corner() {
+        for fen in [
+            "k7/8/1K6/P7/8/8/8/8 w - - 0 1",
+            "k7/8/1K6/P7/8/8/8/8 b - - 0 1",
+            "1k6/8/K7/P7/8/8/8/8 w - - 0 1",
+        ] {
+            assert_eq!(exact(fen), 0, "{fen}");
+            assert_eq!(evaluate(&board(fen)), 0, "{fen}");
+        }
+    }
+
+    #[test]
+    fn kbnk_drives_the_king_to_the_bishops_corner() {
+        // a8 is a light corner and h8 a dark one, with the kings placed alike.
+        let light_bishop = [
+            "k7/8/2K5/8/8/3N4/8/3B4 b - - 0 1",
+            "7k/8/5K2/8/8/3N4/8/3B4 b - - 0 1",
+        ];
+        let dark_bishop = [
+            "k7/8/2K5/8/8/3N4/8/4B3 b - - 0 1",
+            "7k/8/5K2/8/8/3N4/8/4B3 b - - 0 1",
+        ];
+
+        assert!(exact(light_bishop[0]) > exact(light_bishop[1]));
+        assert!(exact(dark_bishop[0]) < exact(dark_bishop[1]));
+
+        for fen in light_bishop.iter().chain(&dark_bishop) {
+            assert_eq!(probe_fen(fen).unwrap().name, "KBNK");
+            assert!(exact(fen) > KNOWN_WIN, "{fen}");
+        }
+    }
+
+    #[test]
+    fn krkp_scores_drop_when_the_pawn_is_supported() {
+        // The white king blocks the pawn: an easy win for the rook.
+        let blocked = exact("k7/8/8/8/3p4/8/3K4/7R w - - 0 1");
+        assert_eq!(blocked, ROOK_VALUE - 2);
+
+        // The black king escorts the pawn to the second rank, the white king is far.
+        let supported = exact("R6K/8/8/8/8/8/3pk3/8 w - - 0 1");
+        assert_eq!(supported, 32);
+
+        assert_eq!(
+            probe_fen("R6K/8/8/8/8/8/3pk3/8 w - - 0 1").unwrap().name,
+            "KRKP"
+        );
+    }
+
+    #[test]
+    fn wrong_bishop_draws() {
+        // h8 is dark, the bishop on d1 light.
+        let fen = "7k/8/8/8/7P/8/8/3BK3 w - - 0 1";
+        assert_eq!(probe_fen(fen).unwrap().name, "KBPsK wrong bishop");
+        assert_eq!(evaluate(&board(fen)), 0);
+
+        // The right bishop, or the king too far from the corner.
+        assert_eq!(probe_fen("7k/8/8/8/7P/8/8/2B1K3 w - - 0 1"), None);
+        assert_eq!(probe_fen("8/8/8/8/7P/5k2/8/3BK3 w - - 0 1"), None);
+    }
+
+    #[test]
+    fn opposite_bishops_scale_the_evaluation() {
+        let only_bishops = board("4k3/5b2/8/3P4/4P3/8/8/2B1K3 w - - 0 1");
+        assert_eq!(probe(&only_bishops).unwrap().score, EndgameScore::Scale(22));
+        assert_eq!(
+            evaluate(&only_bishops),
+            unscaled(&only_bishops) * 22 / SCALE_NORMAL
+        );
+        assert_ne!(unscaled(&only_bishops), 0);
+
+        let with_rooks = board("r3k3/5b2/8/3P4/4P3/8/8/R1B1K3 b - - 0 1");
+        assert_eq!(probe(&with_rooks).unwrap().score, EndgameScore::Scale(46));
+        assert_eq!(
+            evaluate(&with_rooks),
+            -(unscaled(&with_rooks) * 46 / SCALE_NORMAL)
+        );
+
+        // Bishops of the same color are left alone.
+        assert_eq!(probe_fen("4k3/6b1/8/3P4/4P3/8/8/2B1K3 w - - 0 1"), None);
+    }
+}
@@ -0,0 +1,277 @@
+use super::params::{
+    piece_kind_index, KING_OPEN_FILE, KING_SEMI_OPEN_FILE, KING_ZONE_ATTACK, PAWN_SHIELD,
+    PAWN_STORM, SAFE_CHECK,
+};
+use crate::chess::board::{Board, Color, Piece, PieceKind, Position};
+
+/// Zone attacks only count once this many pieces take part in the attack.
+const MIN_ZONE_ATTACKERS: i32 = 2;
+
+const ATTACKING_KINDS: [PieceKind; 4] = [
+    PieceKind::Knight,
+    PieceKind::Bishop,
+    PieceKind::Rook,
+    PieceKind::Queen,
+];
+
+fn bit(square: Position) -> u64 {
+    1 << (square.0 * 8 + square.1)
+}
+
+/// Squares attacked by each piece, and by each side by kind of attacking piece.
+struct AttackMaps {
+    pieces: Vec<(Piece, u64)>,
+    by_kind: [[u64; 6]; 2],
+}
+
+impl AttackMaps {
+    fn new(board: &Board) -> Self {
+        let mut pieces = Vec::new();
+        let mut by_kind = [[0; 6]; 2];
+
+        for (r_index, rank) in board.state.iter().enumerate() {
+            for (f_index, square) in rank.iter().enumerate() {
+                if let Some(piece) = *square {
+                    let attacks = piece
+                        .get_attack_positions((r_index, f_index), board)
+                        .into_iter()
+                        .fold(0, |attacks, target| attacks | bit(target));
+
+                    by_kind[side_index(piece.color)][piece_kind_index(piece.piece_kind)] |= attacks;
+                    pieces.push((piece, attacks));
+                }
+            }
+        }
+
+        Self { pieces, by_kind }
+    }
+
+    fn all(&self, side: Color) -> u64 {
+        self.by_kind[side_index(side)]
+            .iter()
+            .fold(0, |all, attacks| all | attacks)
+    }
+}
+
+fn side_index(side: Color) -> usize {
+    match side {
+        Color::White => 0,
+        Color::Black => 1,
+    }
+}
+
+/// Calls `add` with the side, index and count of every king safety weight that
+/// applies to `board`. Each term belongs to the side whose king it concerns.
+pub fn for_each_king_safety_term(board: &Board, mut add: impl FnMut(Color, usize, i32)) {
+    let attack_maps = AttackMaps::new(board);
+
+    for side in [Color::White, Color::Black] {
+        let king = match side {
+            Color::White => board.white_king_position,
+            Color::Black => board.black_king_position,
+        };
+
+        if let Some(king) = king {
+            add_pawn_cover(board, side, king, &mut add);
+            add_king_attacks(board, &attack_maps, side, king, &mut add);
+        }
+    }
+}
+
+/// Pawn shield, pawn storm and open files on the king's file and its neighbours.
+fn add_pawn_cover(
+    board: &Board,
+    side: Color,
+    king: Position,
+    add: &mut impl FnMut(Color, usize, i32),
+) {
+    let forward: isize = match side {
+        Color::White => -1,
+        Color::Black => 1,
+    };
+
+    for file in king.1.saturating_sub(1)..=(king.1 + 1).min(7) {
+        let mut own_pawn = None;
+        let mut enemy_pawn = None;
+        let mut has_own_pawn = false;
+        let mut has_enemy_pawn = false;
+
+        for rank in 0..8 {
+            let piece = match board.state[rank][file] {
+                Some(piece) if piece.piece_kind == PieceKind::Pawn => piece,
+                _ => continue,
+            };
+
+            let distance = (rank as isize - king.0 as isize) * forward;
+
+            if piece.color == side {
+                has_own_pawn = true;
+
+                if distance > 0 && own_pawn.is_none_or(|nearest| distance < nearest) {
+                    own_pawn = Some(distance);
+                }
+            } else {
+                has_enemy_pawn = true;
+
+                if distance > 0 && enemy_pawn.is_none_or(|nearest| distance < nearest) {
+                    enemy_pawn = Some(distance);
+                }
+            }
+        }
+
+        if let Some(distance @ 1..=2) = own_pawn {
+            add(side, PAWN_SHIELD + distance as usize - 1, 1);
+        }
+
+        if let Some(distance @ 1..=4) = enemy_pawn {
+            add(side, PAWN_STORM + distance as usize - 1, 1);
+        }
+
+        match (has_own_pawn, has_enemy_pawn) {
+            (false, false) => add(side, KING_OPEN_FILE, 1),
+            (false, true) => add(side, KING_SEMI_OPEN_FILE, 1),
+            _ => (),
+        }
+    }
+}
+
+/// Enemy attacks on the squares around the king and safe checks available to
+/// the enemy, by kind of attacking piece.
+fn add_king_attacks(
+    board: &Board,
+    attack_maps: &AttackMaps,
+    side: Color,
+    king: Position,
+    add: &mut impl FnMut(Color, usize, i32),
+) {
+    let enemy = side.opposite();
+
+    let mut zone = bit(king);
+
+    for target in Piece::new(PieceKind::King, side).get_attack_positions(king, board) {
+        zone |= bit(target);
+    }
+
+    let mut attackers = 0;
+    let mut zone_attacks = [0; 4];
+
+    for &(piece, attacks) in &attack_maps.pieces {
+        if piece.color != enemy {
+            continue;
+        }
+
+        let kind_index = match ATTACKING_KINDS
+            .iter()
+            .position(|&kind| kind == piece.piece_kind)
+        {
+            Some(kind_index) => kind_index,
+            None => continue,
+        };
+
+        let attacked = (attacks & zone).count_ones() as i32;
+
+        if attacked > 0 {
+            attackers += 1;
+            zone_attacks[kind_index] += attacked;
+        }
+    }
+
+    if attackers >= MIN_ZONE_ATTACKERS {
+        for (kind_index, &attacked) in zone_attacks.iter().enumerate() {
+            if attacked > 0 {
+                add(side, KING_ZONE_ATTACK + kind_index, attacked);
+            }
+        }
+    }
+
+    let defended = attack_maps.all(side);
+    let enemy_occupied = occupancy(board, enemy);
+
+    for (kind_index, &kind) in ATTACKING_KINDS.iter().enumerate() {
+        // A piece checks from the squares it would attack from the king's square.
+        let mut checking_squares = 0;
+
+        for target in Piece::new(kind, side).get_attack_positions(king, board) {
+            checking_squares |= bit(target);
+        }
+
+        let reachable = attack_maps.by_kind[side_index(enemy)][piece_kind_index(kind)];
+        let safe_checks = checking_squares & reachable & !defended & !enemy_occupied;
+
+        if safe_checks != 0 {
+            add(
+                side,
+                SAFE_CHECK + kind_index,
+                safe_checks.count_ones() as i32,
+            );
+        }
+    }
+}
+
+fn occupancy(board: &Board, side: Color) -> u64 {
+    let mut occupied = 0;
+
+    for (r_index, rank) in board.state.iter().enumerate() {
+        for (f_index, square) in rank.iter().enumerate() {
+            if square.is_some_and(|piece| piece.color == side) {
+                occupied |= bit((r_index, f_index));
+            }
+        }
+    }
+
+    occupied
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chess::board::Board;
+    use crate::chess::evaluation::params::Weight;
+
+    /// The white and black scores of the named term.
+    fn term(fen: &str, name: &str) -> (Weight, Weight) {
+        let trace = Board::from_fen(fen).unwrap().eval_trace();
+        let term = trace.term(name).unwrap();
+
+        (term.white, term.black)
+    }
+
+    #[test]
+    fn pawn_shield_counts_pawns_one_and_two_ranks_ahead() {
+        let fen = "4k3/8/8/8/8/8/5PPP/6K1 w - - 0 1";
+        assert_eq!(term(fen, "king.pawn_shield"), ((36, 0), (0, 0)));
+
+        let fen = "4k3/8/8/8/8/7P/5PP1/6K1 w - - 0 1";
+        assert_eq!(term(fen, "king.pawn_shield"), ((30, 0), (0, 0)));
+    }
+
+    #[test]
+    fn open_files_and_pawn_storms_near_the_king() {
+        // Black's king has no pawns: d and e are open, f only has a white pawn.
+        let fen = "4k3/8/8/8/8/8/5PPP/6K1 w - - 0 1";
+        assert_eq!(term(fen, "king.open_file"), ((0, 0), (-50, 0)));
+        assert_eq!(term(fen, "king.semi_open_file"), ((0, 0), (-12, 0)));
+
+        let fen = "4k3/8/8/8/6p1/8/5P1P/6K1 w - - 0 1";
+        assert_eq!(term(fen, "king.pawn_storm").0, (-10, 0));
+        assert_eq!(term(fen, "king.semi_open_file").0, (-12, 0));
+    }
+
+    #[test]
+    fn zone_attacks_need_two_attackers() {
+        let fen = "6k1/8/8/8/7q/8/5PPP/6K1 w - - 0 1";
+        assert_eq!(term(fen, "king.zone_attack"), ((0, 0), (0, 0)));
+
+        // The knight hits f1 and g2, the queen f2 and h2.
+        let fen = "6k1/8/8/8/7q/4n3/5PPP/6K1 w - - 0 1";
+        assert_eq!(term(fen, "king.zone_attack"), ((-32, -12), (0, 0)));
+    }
+
+    #[test]
+    fn safe_checks_exclude_defended_squares() {
+        let fen = "r5k1/8/8/8/8/8/6PP/7K w - - 0 1";
+        assert_eq!(term(fen, "king.safe_check"), ((-30, -10), (0, 0)));
+
+        let fen = "r5k1/8/8/8/8/8/1B4PP/7K w - - 0 1";
+        assert_eq!(term(fen, "king.safe_check"), ((0, 0), (0, 0)));
+    }
+}
@@ -0,0 +1,215 @@
+use std::sync::OnceLock;
+
+// KPK positions are normalized so the strong side's pawn is on files a to d
+// and moves up the board. A square is `y * 8 + x`, where `x` is the file and
+// `y` the rank counted from the strong side's first rank.
+
+const PAWN_SQUARES: usize = 4 * 6;
+const POSITION_COUNT: usize = 2 * 64 * 64 * PAWN_SQUARES;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Outcome {
+    Invalid,
+    Unknown,
+    Draw,
+    Win,
+}
+
+/// Square coordinates on the normalized board.
+type Square = (i32, i32);
+
+fn index(strong_to_move: bool, strong_king: Square, weak_king: Square, pawn: Square) -> usize {
+    let square = |(x, y): Square| (y * 8 + x) as usize;
+    let pawn_index = ((pawn.1 - 1) * 4 + pawn.0) as usize;
+
+    (((strong_to_move as usize * 64 + square(strong_king)) * 64 + square(weak_king)) * PAWN_SQUARES)
+        + pawn_index
+}
+
+fn decode(index: usize) -> (bool, Square, Square, Square) {
+    let pawn_index = (index % PAWN_SQUARES) as i32;
+    let rest = index / PAWN_SQUARES;
+    let weak_king = (rest % 64) as i32;
+    let rest = rest / 64;
+    let strong_king = (rest % 64) as i32;
+    let strong_to_move = rest / 64 == 1;
+
+    (
+        strong_to_move,
+        (strong_king % 8, strong_king / 8),
+        (weak_king % 8, weak_king / 8),
+        (pawn_index % 4, pawn_index / 4 + 1),
+    )
+}
+
+fn distance(a: Square, b: Square) -> i32 {
+    (a.0 - b.0).abs().max((a.1 - b.1).abs())
+}
+
+fn on_board((x, y): Square) -> bool {
+    (0..8).contains(&x) && (0..8).contains(&y)
+}
+
+fn pawn_attacks(pawn: Square, square: Square) -> bool {
+    square.1 == pawn.1 + 1 && (square.0 - pawn.0).abs() == 1
+}
+
+fn king_moves(king: Square) -> impl Iterator<Item = Square> {
+    (-1..=1)
+        .flat_map(move |dy| (-1..=1).map(move |dx| (king.0 + dx, king.1 + dy)))
+        .filter(move |&square| square != king && on_board(square))
+}
+
+fn classify_initial(
+    strong_to_move: bool,
+    strong_king: Square,
+    weak_king: Square,
+    pawn: Square,
+) -> Outcome {
+    if distance(strong_king, weak_king) <= 1
+        || strong_king == pawn
+        || weak_king == pawn
+        || (strong_to_move && pawn_attacks(pawn, weak_king))
+    {
+        return Outcome::Invalid;
+    }
+
+    if strong_to_move {
+        // The pawn promotes safely.
+        let promotion = (pawn.0, 7);
+
+        if pawn.1 == 6
+            && strong_king != promotion
+            && weak_king != promotion
+            && (distance(weak_king, promotion) > 1 || distance(strong_king, promotion) == 1)
+        {
+            return Outcome::Win;
+        }
+    } else {
+        let mut escapes = king_moves(weak_king)
+            .filter(|&square| distance(square, strong_king) > 1 && !pawn_attacks(pawn, square));
+
+        if escapes.next().is_none() {
+            return Outcome::Draw;
+        }
+
+        // The weak king takes the undefended pawn.
+        if distance(weak_king, pawn) == 1 && distance(strong_king, pawn) > 1 {
+            return Outcome::Draw;
+        }
+    }
+
+    Outcome::Unknown
+}
+
+fn classify(outcomes: &[Outcome], position: usize) -> Outcome {
+    let (strong_to_move, strong_king, weak_king, pawn) = decode(position);
+
+    let mut successors = Vec::new();
+
+    if strong_to_move {
+        for square in king_moves(strong_king) {
+            if square != pawn && distance(square, weak_king) > 1 {
+                successors.push(index(false, square, weak_king, pawn));
+            }
+        }
+
+        let push = (pawn.0, pawn.1 + 1);
+
+        // Promotions are already classified as immediate wins.
+        if pawn.1 < 6 && push != strong_king && push != weak_king {
+            successors.push(index(false, strong_king, weak_king, push));
+
+            let double_push = (pawn.0, pawn.1 + 2);
+
+            if pawn.1 == 1 && double_push != strong_king && double_push != weak_king {
+                successors.push(index(false, strong_king, weak_king, double_push));
+            }
+        }
+    } else {
+        for square in king_moves(weak_king) {
+            if distance(square, strong_king) > 1 && !pawn_attacks(pawn, square) && square != pawn {
+                successors.push(index(true, strong_king, square, pawn));
+            }
+        }
+    }
+
+    let outcomes: Vec<Outcome> = successors
+        .into_iter()
+        .map(|successor| outcomes[successor])
+        .filter(|&outcome| outcome != Outcome::Invalid)
+        .collect();
+
+    // The side to move picks its best successor.
+    let (good, bad) = if strong_to_move {
+        (Outcome::Win, Outcome::Draw)
+    } else {
+        (Outcome::Draw, Outcome::Win)
+    };
+
+    if outcomes.contains(&good) {
+        good
+    } else if !outcomes.is_empty() && outcomes.iter().all(|&outcome| outcome == bad) {
+        bad
+    } else {
+        Outcome::Unknown
+    }
+}
+
+fn bitbase() -> &'static Vec<bool> {
+    static BITBASE: OnceLock<Vec<bool>> = OnceLock::new();
+
+    BITBASE.get_or_init(|| {
+        let mut outcomes: Vec<Outcome> = (0..POSITION_COUNT)
+            .map(|position| {
+                let (strong_to_move, strong_king, weak_king, pawn) = decode(position);
+                classify_initial(strong_to_move, strong_king, weak_king, pawn)
+            })
+            .collect();
+
+        let mut changed = true;
+
+        while changed {
+            changed = false;
+
+            for position in 0..POSITION_COUNT {
+                if outcomes[position] != Outcome::Unknown {
+                    continue;
+                }
+
+                let outcome = classify(&outcomes, position);
+
+                if outcome != Outcome::Unknown {
+                    outcomes[position] = outcome;
+                    changed = true;
+                }
+            }
+        }
+
+        // Positions nobody can force anything from are draws.
+        outcomes
+            .into_iter()
+            .map(|outcome| outcome == Outcome::Win)
+            .collect()
+    })
+}
+
+/// Whether the side with the pawn wins. Squares are `(file, rank)` counted from
+/// the strong side's first rank, so the pawn moves towards rank 7.
+pub fn probe(strong_to_move: bool, strong_king: Square, weak_king: Square, pawn: Square) -> bool {
+    // Mirror pawns on files e to h onto files a to d.
+    let mirror = |square: Square| {
+        if pawn.0 >= 4 {
+            (7 - square.0, square.1)
+        } else {
+            square
+        }
+    };
+
+    bitbase()[index(
+        strong_to_move,
+        mirror(strong_king),
+        mirror(weak_king),
+        mirror(pawn),
+    )]
+}
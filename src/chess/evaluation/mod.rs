@@ -0,0 +1,176 @@
+use std::error::Error;
+use std::sync::Arc;
+
+use self::nnue::{Accumulator, Network};
+use self::params::{default_params, piece_kind_index, EvalParams, MATERIAL, PIECE_SQUARE};
+use super::board::{Board, Color, PieceKind};
+use super::options::EngineOptions;
+
+pub mod endgame;
+pub mod king_safety;
+pub mod kpk;
+pub mod nnue;
+pub mod params;
+pub mod pawns;
+pub mod pst;
+pub mod trace;
+pub mod tuning;
+
+pub const PAWN_VALUE: i32 = 100;
+pub const KNIGHT_VALUE: i32 = 320;
+pub const BISHOP_VALUE: i32 = 330;
+pub const ROOK_VALUE: i32 = 500;
+pub const QUEEN_VALUE: i32 = 900;
+pub const KING_VALUE: i32 = 20000;
+
+pub const MAX_PHASE: i32 = 24;
+
+pub fn piece_value(piece_kind: PieceKind) -> i32 {
+    match piece_kind {
+        PieceKind::Pawn => PAWN_VALUE,
+        PieceKind::Knight => KNIGHT_VALUE,
+        PieceKind::Bishop => BISHOP_VALUE,
+        PieceKind::Rook => ROOK_VALUE,
+        PieceKind::Queen => QUEEN_VALUE,
+        PieceKind::King => KING_VALUE,
+    }
+}
+
+/// Game phase contributed by each piece, a full board adds up to `MAX_PHASE`.
+pub fn phase_weight(piece_kind: PieceKind) -> i32 {
+    match piece_kind {
+        PieceKind::Knight | PieceKind::Bishop => 1,
+        PieceKind::Rook => 2,
+        PieceKind::Queen => 4,
+        PieceKind::Pawn | PieceKind::King => 0,
+    }
+}
+
+/// Chooses between the handcrafted evaluation and a loaded NNUE network.
+#[derive(Clone)]
+pub enum Evaluator {
+    Handcrafted(Arc<EvalParams>),
+    Nnue(Arc<Network>),
+}
+
+impl Default for Evaluator {
+    fn default() -> Self {
+        Self::Handcrafted(Arc::new(default_params().clone()))
+    }
+}
+
+impl Evaluator {
+    pub fn from_options(options: &EngineOptions) -> Result<Self, Box<dyn Error>> {
+        if let (true, Some(eval_file)) = (options.use_nnue, &options.eval_file) {
+            return Ok(Self::Nnue(Arc::new(Network::load(eval_file)?)));
+        }
+
+        match &options.eval_params_file {
+            Some(eval_params_file) => Ok(Self::Handcrafted(Arc::new(EvalParams::load(
+                eval_params_file,
+            )?))),
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// Evaluates `board`, using `accumulator` as the network's first layer when
+    /// the NNUE evaluator is active.
+    pub fn evaluate(&self, board: &Board, accumulator: Option<&Accumulator>) -> i32 {
+        match (self, accumulator) {
+            (Self::Nnue(network), Some(accumulator)) => {
+                endgame::adjust(board, network.evaluate(accumulator, board.active_turn))
+            }
+            (Self::Nnue(network), None) => endgame::adjust(
+                board,
+                network.evaluate(&network.refresh(board), board.active_turn),
+            ),
+            (Self::Handcrafted(params), _) => evaluate_with(board, params),
+        }
+    }
+}
+
+/// Static evaluation of `board` in centipawns from the side to move's point of view.
+pub fn evaluate(board: &Board) -> i32 {
+    evaluate_with(board, default_params())
+}
+
+pub fn evaluate_with(board: &Board, params: &EvalParams) -> i32 {
+    let mut middlegame = 0;
+    let mut endgame = 0;
+
+    let phase = for_each_term(board, |index, count| {
+        let (mg_weight, eg_weight) = params.weights[index];
+
+        middlegame += mg_weight * count;
+        endgame += eg_weight * count;
+    });
+
+    let score = (middlegame * phase + endgame * (MAX_PHASE - phase)) / MAX_PHASE;
+
+    let score = match endgame::probe(board) {
+        Some(endgame) => endgame.apply(score),
+        None => score,
+    };
+
+    match board.active_turn {
+        Color::White => score,
+        Color::Black => -score,
+    }
+}
+
+/// Calls `add` with the index of every weight that applies to `board` and how
+/// many times it applies, counting white positively and black negatively.
+/// Returns the game phase the weights are blended with.
+pub fn for_each_term(board: &Board, mut add: impl FnMut(usize, i32)) -> i32 {
+    for_each_side_term(board, |side, index, count| match side {
+        Color::White => add(index, count),
+        Color::Black => add(index, -count),
+    })
+}
+
+/// Like `for_each_term`, but reports which side each count belongs to.
+pub fn for_each_side_term(board: &Board, mut add: impl FnMut(Color, usize, i32)) -> i32 {
+    let mut phase = 0;
+
+    for (r_index, rank) in board.state.iter().enumerate() {
+        for (f_index, square) in rank.iter().enumerate() {
+            let piece = match square {
+                Some(piece) => piece,
+                None => continue,
+            };
+
+            let kind_index = piece_kind_index(piece.piece_kind);
+            let square_index = pst::table_index((r_index, f_index), piece.color);
+
+            add(piece.color, MATERIAL + kind_index, 1);
+            add(
+                piece.color,
+                PIECE_SQUARE + kind_index * 64 + square_index,
+                1,
+            );
+
+            phase += phase_weight(piece.piece_kind);
+        }
+    }
+
+    pawns::for_each_pawn_term(board, &mut add);
+    king_safety::for_each_king_safety_term(board, &mut add);
+
+    phase.min(MAX_PHASE)
+}
+
+/// Material of `side` excluding pawns and the king.
+pub fn non_pawn_material(board: &Board, side: Color) -> i32 {
+    board
+        .state
+        .iter()
+        .flatten()
+        .flatten()
+        .filter(|piece| {
+            piece.color == side
+                && piece.piece_kind != PieceKind::Pawn
+                && piece.piece_kind != PieceKind::King
+        })
+        .map(|piece| piece_value(piece.piece_kind))
+        .sum()
+}
@@ -0,0 +1,509 @@
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use crate::chess::board::{chess_move::Move, Board, Color, Piece, PieceKind, Position};
+
+const MAGIC: &[u8; 8] = b"CENNUE01";
+
+/// King square times ten non-king piece types times piece square.
+pub const HALF_KP_INPUTS: usize = 64 * 10 * 64;
+
+/// Activations are clipped to `[0, ACTIVATION_RANGE]` before the output layer.
+const ACTIVATION_RANGE: i16 = 127;
+const WEIGHT_SCALE: i32 = 64;
+const OUTPUT_SCALE: i32 = 400;
+
+/// A HalfKP network with one hidden layer per perspective.
+///
+/// The feature transformer uses 16-bit weights and the output layer 8-bit
+/// weights, both quantized by the trainer.
+pub struct Network {
+    pub hidden_size: usize,
+    pub feature_weights: Vec<i16>,
+    pub feature_biases: Vec<i16>,
+    pub output_weights: Vec<i8>,
+    pub output_bias: i32,
+}
+
+/// First layer outputs for both perspectives, indexed by `Color as usize`.
+#[derive(Clone, PartialEq)]
+pub struct Accumulator {
+    pub values: [Vec<i16>; 2],
+}
+
+impl Network {
+    /// Loads a network file: the magic bytes, the input and hidden sizes as
+    /// little-endian u32, then feature weights, feature biases, output weights
+    /// (side to move half first) and the output bias.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, NnueError> {
+        let mut reader = BufReader::new(File::open(path)?);
+
+        let mut magic = [0; 8];
+        reader.read_exact(&mut magic)?;
+
+        if &magic != MAGIC {
+            return Err(NnueError::InvalidMagic);
+        }
+
+        let input_size = read_u32(&mut reader)? as usize;
+        let hidden_size = read_u32(&mut reader)? as usize;
+
+        if input_size != HALF_KP_INPUTS {
+            return Err(NnueError::UnsupportedArchitecture(input_size));
+        }
+
+        if hidden_size == 0 {
+            return Err(NnueError::UnsupportedArchitecture(hidden_size));
+        }
+
+        let feature_weights = read_i16s(&mut reader, input_size * hidden_size)?;
+        let feature_biases = read_i16s(&mut reader, hidden_size)?;
+
+        let mut output_weights = vec![0; 2 * hidden_size];
+        reader.read_exact(&mut output_weights)?;
+
+        let output_weights = output_weights.into_iter().map(|byte| byte as i8).collect();
+
+        let output_bias = read_u32(&mut reader)? as i32;
+
+        Ok(Self {
+            hidden_size,
+            feature_weights,
+            feature_biases,
+            output_weights,
+            output_bias,
+        })
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+
+        writer.write_all(MAGIC)?;
+        writer.write_all(&(HALF_KP_INPUTS as u32).to_le_bytes())?;
+        writer.write_all(&(self.hidden_size as u32).to_le_bytes())?;
+
+        for weight in self.feature_weights.iter().chain(&self.feature_biases) {
+            writer.write_all(&weight.to_le_bytes())?;
+        }
+
+        for weight in &self.output_weights {
+            writer.write_all(&weight.to_le_bytes())?;
+        }
+
+        writer.write_all(&self.output_bias.to_le_bytes())?;
+        writer.flush()
+    }
+
+    /// Builds the accumulator for `board` from scratch.
+    pub fn refresh(&self, board: &Board) -> Accumulator {
+        let mut accumulator = Accumulator {
+            values: [self.feature_biases.clone(), self.feature_biases.clone()],
+        };
+
+        for perspective in [Color::White, Color::Black] {
+            self.refresh_perspective(board, perspective, &mut accumulator);
+        }
+        accumulator
+    }
+
+    fn refresh_perspective(
+        &self,
+        board: &Board,
+        perspective: Color,
+        accumulator: &mut Accumulator,
+    ) {
+        let values = &mut accumulator.values[perspective as usize];
+        values.copy_from_slice(&self.feature_biases);
+
+        let king_square = match king_position(board, perspective) {
+            Some(king_square) => king_square,
+            None => return,
+        };
+
+        for (r_index, rank) in board.state.iter().enumerate() {
+            for (f_index, square) in rank.iter().enumerate() {
+                if let Some(piece) = square {
+                    if let Some(feature) =
+                        feature_index(perspective, king_square, *piece, (r_index, f_index))
+                    {
+                        add_feature(values, self.feature_weights(feature));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Updates `accumulator`, which must match `before`, to the position `after`
+    /// reached by playing `m`.
+    ///
+    /// Only the squares `m` touches are looked at. A perspective whose king
+    /// moved is rebuilt, since every one of its features depends on the king.
+    pub fn update(&self, accumulator: &mut Accumulator, before: &Board, m: Move, after: &Board) {
+        let squares = touched_squares(m, before.active_turn);
+
+        for perspective in [Color::White, Color::Black] {
+            let king_square = king_position(after, perspective);
+
+            if king_square != king_position(before, perspective) {
+                self.refresh_perspective(after, perspective, accumulator);
+                continue;
+            }
+
+            let king_square = match king_square {
+                Some(king_square) => king_square,
+                None => continue,
+            };
+
+            let values = &mut accumulator.values[perspective as usize];
+
+            for &square in &squares {
+                let old = before.state[square.0][square.1];
+                let new = after.state[square.0][square.1];
+
+                if old == new {
+                    continue;
+                }
+
+                if let Some(feature) =
+                    old.and_then(|piece| feature_index(perspective, king_square, piece, square))
+                {
+                    sub_feature(values, self.feature_weights(feature));
+                }
+
+                if let Some(feature) =
+                    new.and_then(|piece| feature_index(perspective, king_square, piece, square))
+                {
+                    add_feature(values, self.feature_weights(feature));
+                }
+            }
+        }
+    }
+
+    /// Evaluates the position behind `accumulator` in centipawns from the point
+    /// of view of `side_to_move`.
+    pub fn evaluate(&self, accumulator: &Accumulator, side_to_move: Color) -> i32 {
+        let us = &accumulator.values[side_to_move as usize];
+        let them = &accumulator.values[side_to_move.opposite() as usize];
+
+        let (our_weights, their_weights) = self.output_weights.split_at(self.hidden_size);
+
+        let sum =
+            dot_clipped(us, our_weights) + dot_clipped(them, their_weights) + self.output_bias;
+
+        sum * OUTPUT_SCALE / (ACTIVATION_RANGE as i32 * WEIGHT_SCALE)
+    }
+
+    fn feature_weights(&self, feature: usize) -> &[i16] {
+        &self.feature_weights[feature * self.hidden_size..(feature + 1) * self.hidden_size]
+    }
+}
+
+fn king_position(board: &Board, color: Color) -> Option<Position> {
+    match color {
+        Color::White => board.white_king_position,
+        Color::Black => board.black_king_position,
+    }
+}
+
+/// The squares whose contents `m`, played by `side`, may change.
+fn touched_squares(m: Move, side: Color) -> Vec<Position> {
+    let rank = match side {
+        Color::White => 7,
+        Color::Black => 0,
+    };
+
+    match m {
+        Move::CastleKingside => vec![(rank, 4), (rank, 5), (rank, 6), (rank, 7)],
+        Move::CastleQueenside => vec![(rank, 0), (rank, 2), (rank, 3), (rank, 4)],
+
+        // A pawn changing file may be capturing en passant, which empties the
+        // square next to its origin.
+        Move::PieceMove {
+            origin_square,
+            target_square,
+            origin_piece,
+            ..
+        } if origin_piece.piece_kind == PieceKind::Pawn && origin_square.1 != target_square.1 => {
+            vec![
+                origin_square,
+                target_square,
+                (origin_square.0, target_square.1),
+            ]
+        }
+
+        Move::PieceMove {
+            origin_square,
+            target_square,
+            ..
+        } => vec![origin_square, target_square],
+    }
+}
+
+/// Squares are seen from the perspective's own side of the board, so both
+/// perspectives share one set of weights.
+fn oriented_square(square: Position, perspective: Color) -> usize {
+    match perspective {
+        Color::White => square.0 * 8 + square.1,
+        Color::Black => (7 - square.0) * 8 + square.1,
+    }
+}
+
+fn feature_index(
+    perspective: Color,
+    king_square: Position,
+    piece: Piece,
+    square: Position,
+) -> Option<usize> {
+    let kind_index = match piece.piece_kind {
+        PieceKind::Pawn => 0,
+        PieceKind::Knight => 1,
+        PieceKind::Bishop => 2,
+        PieceKind::Rook => 3,
+        PieceKind::Queen => 4,
+        PieceKind::King => return None,
+    };
+
+    let piece_index = kind_index * 2 + usize::from(piece.color != perspective);
+
+    Some(
+        oriented_square(king_square, perspective) * 640
+            + piece_index * 64
+            + oriented_square(square, perspective),
+    )
+}
+
+fn add_feature(values: &mut [i16], weights: &[i16]) {
+    #[cfg(target_arch = "x86_64")]
+    {
+        if is_x86_feature_detected!("avx2") {
+            // SAFETY: AVX2 support was just checked.
+            unsafe { simd::add_i16(values, weights) };
+            return;
+        }
+    }
+
+    for (value, weight) in values.iter_mut().zip(weights) {
+        *value = value.wrapping_add(*weight);
+    }
+}
+
+fn sub_feature(values: &mut [i16], weights: &[i16]) {
+    #[cfg(target_arch = "x86_64")]
+    {
+        if is_x86_feature_detected!("avx2") {
+            // SAFETY: AVX2 support was just checked.
+            unsafe { simd::sub_i16(values, weights) };
+            return;
+        }
+    }
+
+    for (value, weight) in values.iter_mut().zip(weights) {
+        *value = value.wrapping_sub(*weight);
+    }
+}
+
+fn dot_clipped(values: &[i16], weights: &[i8]) -> i32 {
+    #[cfg(target_arch = "x86_64")]
+    {
+        if is_x86_feature_detected!("avx2") {
+            // SAFETY: AVX2 support was just checked.
+            return unsafe { simd::dot_clipped(values, weights) };
+        }
+    }
+
+    values
+        .iter()
+        .zip(weights)
+        .map(|(&value, &weight)| value.clamp(0, ACTIVATION_RANGE) as i32 * weight as i32)
+        .sum()
+}
+
+#[cfg(target_arch = "x86_64")]
+mod simd {
+    use std::arch::x86_64::*;
+
+    use super::ACTIVATION_RANGE;
+
+    const LANES: usize = 16;
+
+    #[target_feature(enable = "avx2")]
+    pub unsafe fn add_i16(values: &mut [i16], weights: &[i16]) {
+        let chunks = values.len() / LANES * LANES;
+
+        for i in (0..chunks).step_by(LANES) {
+            let value = _mm256_loadu_si256(values.as_ptr().add(i) as *const __m256i);
+            let weight = _mm256_loadu_si256(weights.as_ptr().add(i) as *const __m256i);
+
+            _mm256_storeu_si256(
+                values.as_mut_ptr().add(i) as *mut __m256i,
+                _mm256_add_epi16(value, weight),
+            );
+        }
+
+        for i in chunks..values.len() {
+            values[i] = values[i].wrapping_add(weights[i]);
+        }
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub unsafe fn sub_i16(values: &mut [i16], weights: &[i16]) {
+        let chunks = values.len() / LANES * LANES;
+
+        for i in (0..chunks).step_by(LANES) {
+            let value = _mm256_loadu_si256(values.as_ptr().add(i) as *const __m256i);
+            let weight = _mm256_loadu_si256(weights.as_ptr().add(i) as *const __m256i);
+
+            _mm256_storeu_si256(
+                values.as_mut_ptr().add(i) as *mut __m256i,
+                _mm256_sub_epi16(value, weight),
+            );
+        }
+
+        for i in chunks..values.len() {
+            values[i] = values[i].wrapping_sub(weights[i]);
+        }
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub unsafe fn dot_clipped(values: &[i16], weights: &[i8]) -> i32 {
+        let chunks = values.len() / LANES * LANES;
+
+        let zero = _mm256_setzero_si256();
+        let range = _mm256_set1_epi16(ACTIVATION_RANGE);
+        let mut sum = _mm256_setzero_si256();
+
+        for i in (0..chunks).step_by(LANES) {
+            let value = _mm256_loadu_si256(values.as_ptr().add(i) as *const __m256i);
+            let clipped = _mm256_min_epi16(_mm256_max_epi16(value, zero), range);
+
+            let weight = _mm_loadu_si128(weights.as_ptr().add(i) as *const __m128i);
+            let weight = _mm256_cvtepi8_epi16(weight);
+
+            sum = _mm256_add_epi32(sum, _mm256_madd_epi16(clipped, weight));
+        }
+
+        let mut lanes = [0i32; 8];
+        _mm256_storeu_si256(lanes.as_mut_ptr() as *mut __m256i, sum);
+
+        let mut total: i32 = lanes.iter().sum();
+
+        for i in chunks..values.len() {
+            total += values[i].clamp(0, ACTIVATION_RANGE) as i32 * weights[i] as i32;
+        }
+        total
+    }
+}
+
+fn read_u32(reader: &mut impl Read) -> io::Result<u32> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_i16s(reader: &mut impl Read, count: usize) -> io::Result<Vec<i16>> {
+    let mut bytes = vec![0; count * 2];
+    reader.read_exact(&mut bytes)?;
+
+    Ok(bytes
+        .chunks_exact(2)
+        .map(|pair| i16::from_le_bytes([pair[0], pair[1]]))
+        .collect())
+}
+
+#[derive(Debug)]
+pub enum NnueError {
+    Io(io::Error),
+    InvalidMagic,
+    UnsupportedArchitecture(usize),
+}
+
+impl From<io::Error> for NnueError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl fmt::Display for NnueError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Io(err) => {
+                write!(f, "Failed to read network file: {err}")
+            }
+
+            Self::InvalidMagic => {
+                write!(
+                    f,
+                    "Network file does not start with the expected magic bytes"
+                )
+            }
+
+            Self::UnsupportedArchitecture(size) => {
+                write!(f, "Unsupported network layer size: {size}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for NnueError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chess::board::zobrist::splitmix64;
+
+    /// A network with small pseudo-random weights. The hidden size is not a
+    /// multiple of the SIMD width, so both code paths run.
+    fn network() -> Network {
+        let hidden_size = 24;
+        let mut state = 1;
+
+        let mut weights = |count: usize| -> Vec<i16> {
+            (0..count)
+                .map(|_| (splitmix64(&mut state) % 65) as i16 - 32)
+                .collect()
+        };
+
+        Network {
+            hidden_size,
+            feature_weights: weights(HALF_KP_INPUTS * hidden_size),
+            feature_biases: weights(hidden_size),
+            output_weights: vec![1; 2 * hidden_size],
+            output_bias: 0,
+        }
+    }
+
+    #[test]
+    fn incremental_updates_match_refresh() {
+        let network = network();
+
+        let mut board = Board::from_fen("r3k2r/1P4pp/8/3p4/4P3/8/6PP/R3K2R w KQkq - 0 1").unwrap();
+        let mut accumulator = network.refresh(&board);
+
+        // A capture, both castles, a capturing promotion, a double push that
+        // sets the en passant square, a recapture, king moves, an
+        // underpromotion and an en passant capture.
+        let moves = [
+            "e4d5", "e8g8", "b7a8q", "g7g5", "e1c1", "f8a8", "d5d6", "a8a2", "c1b1", "g8g7",
+            "d6d7", "a2a1", "b1a1", "h7h5", "d7d8n", "h5h4", "g2g4", "h4g3", "h2g3",
+        ];
+
+        for uci in moves {
+            let m = Move::from_uci(&board, uci).unwrap();
+            assert!(board.get_legal_moves().contains(&m), "{uci} is not legal");
+
+            let mut child = board.clone();
+            child.make_move_unchecked(m);
+
+            network.update(&mut accumulator, &board, m, &child);
+            assert!(
+                accumulator == network.refresh(&child),
+                "accumulator diverged after {uci}"
+            );
+
+            board = child;
+        }
+    }
+}
@@ -0,0 +1,326 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use super::pst;
+use crate::chess::board::PieceKind;
+
+// Every weight of the handcrafted evaluation lives in one flat table so the
+// tuner can treat the evaluation as a linear function of it. These offsets
+// give each term's place in the table.
+
+pub const MATERIAL: usize = 0;
+pub const PIECE_SQUARE: usize = MATERIAL + 6;
+pub const DOUBLED_PAWN: usize = PIECE_SQUARE + 6 * 64;
+pub const ISOLATED_PAWN: usize = DOUBLED_PAWN + 1;
+pub const BACKWARD_PAWN: usize = ISOLATED_PAWN + 1;
+/// Indexed by relative rank, as are the passed pawn terms.
+pub const CONNECTED_PAWN: usize = BACKWARD_PAWN + 1;
+pub const PASSED_PAWN: usize = CONNECTED_PAWN + 8;
+pub const BLOCKED_PASSED_PAWN: usize = PASSED_PAWN + 8;
+pub const UNSTOPPABLE_PASSED_PAWN: usize = BLOCKED_PASSED_PAWN + 8;
+/// Per square of king distance to a passed pawn's stop square, scaled by rank.
+pub const PASSED_PAWN_FRIENDLY_KING: usize = UNSTOPPABLE_PASSED_PAWN + 1;
+pub const PASSED_PAWN_ENEMY_KING: usize = PASSED_PAWN_FRIENDLY_KING + 1;
+/// Indexed by how many ranks in front of the king the pawn stands.
+pub const PAWN_SHIELD: usize = PASSED_PAWN_ENEMY_KING + 1;
+pub const PAWN_STORM: usize = PAWN_SHIELD + 2;
+pub const KING_OPEN_FILE: usize = PAWN_STORM + 4;
+pub const KING_SEMI_OPEN_FILE: usize = KING_OPEN_FILE + 1;
+/// Indexed by attacking piece, from knight to queen, as are safe checks.
+pub const KING_ZONE_ATTACK: usize = KING_SEMI_OPEN_FILE + 1;
+pub const SAFE_CHECK: usize = KING_ZONE_ATTACK + 4;
+pub const PARAM_COUNT: usize = SAFE_CHECK + 4;
+
+/// The evaluation terms by name and first weight, in table order.
+pub const TERMS: [(&str, usize); 17] = [
+    ("material", MATERIAL),
+    ("psqt", PIECE_SQUARE),
+    ("pawn.doubled", DOUBLED_PAWN),
+    ("pawn.isolated", ISOLATED_PAWN),
+    ("pawn.backward", BACKWARD_PAWN),
+    ("pawn.connected", CONNECTED_PAWN),
+    ("pawn.passed", PASSED_PAWN),
+    ("pawn.passed_blocked", BLOCKED_PASSED_PAWN),
+    ("pawn.passed_unstoppable", UNSTOPPABLE_PASSED_PAWN),
+    ("pawn.passed_friendly_king", PASSED_PAWN_FRIENDLY_KING),
+    ("pawn.passed_enemy_king", PASSED_PAWN_ENEMY_KING),
+    ("king.pawn_shield", PAWN_SHIELD),
+    ("king.pawn_storm", PAWN_STORM),
+    ("king.open_file", KING_OPEN_FILE),
+    ("king.semi_open_file", KING_SEMI_OPEN_FILE),
+    ("king.zone_attack", KING_ZONE_ATTACK),
+    ("king.safe_check", SAFE_CHECK),
+];
+
+const CONNECTED_PAWN_WEIGHTS: [Weight; 8] = [
+    (0, 0),
+    (3, 3),
+    (5, 5),
+    (8, 8),
+    (12, 15),
+    (20, 30),
+    (35, 50),
+    (0, 0),
+];
+
+const PASSED_PAWN_WEIGHTS: [Weight; 8] = [
+    (0, 0),
+    (5, 10),
+    (5, 15),
+    (10, 25),
+    (20, 45),
+    (35, 75),
+    (60, 120),
+    (0, 0),
+];
+
+const BLOCKED_PASSED_PAWN_WEIGHTS: [Weight; 8] = [
+    (0, 0),
+    (0, -2),
+    (-2, -4),
+    (-3, -8),
+    (-5, -15),
+    (-10, -25),
+    (-15, -40),
+    (0, 0),
+];
+
+const PIECE_NAMES: [&str; 6] = ["pawn", "knight", "bishop", "rook", "queen", "king"];
+
+/// A middlegame and an endgame weight, blended by game phase.
+pub type Weight = (i32, i32);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EvalParams {
+    pub weights: Vec<Weight>,
+}
+
+pub fn piece_kind_index(piece_kind: PieceKind) -> usize {
+    match piece_kind {
+        PieceKind::Pawn => 0,
+        PieceKind::Knight => 1,
+        PieceKind::Bishop => 2,
+        PieceKind::Rook => 3,
+        PieceKind::Queen => 4,
+        PieceKind::King => 5,
+    }
+}
+
+/// The parameters used when no tuned set is loaded.
+pub fn default_params() -> &'static EvalParams {
+    static DEFAULT: OnceLock<EvalParams> = OnceLock::new();
+    DEFAULT.get_or_init(EvalParams::default)
+}
+
+impl Default for EvalParams {
+    fn default() -> Self {
+        let mut weights = vec![(0, 0); PARAM_COUNT];
+
+        let material = [
+            super::PAWN_VALUE,
+            super::KNIGHT_VALUE,
+            super::BISHOP_VALUE,
+            super::ROOK_VALUE,
+            super::QUEEN_VALUE,
+            0,
+        ];
+
+        for (index, value) in material.into_iter().enumerate() {
+            weights[MATERIAL + index] = (value, value);
+        }
+
+        let tables = [
+            (pst::PAWN, pst::PAWN),
+            (pst::KNIGHT, pst::KNIGHT),
+            (pst::BISHOP, pst::BISHOP),
+            (pst::ROOK, pst::ROOK),
+            (pst::QUEEN, pst::QUEEN),
+            (pst::KING_MIDDLEGAME, pst::KING_ENDGAME),
+        ];
+
+        for (kind_index, (middlegame, endgame)) in tables.into_iter().enumerate() {
+            for square in 0..64 {
+                weights[PIECE_SQUARE + kind_index * 64 + square] =
+                    (middlegame[square], endgame[square]);
+            }
+        }
+
+        weights[DOUBLED_PAWN] = (-10, -20);
+        weights[ISOLATED_PAWN] = (-10, -15);
+        weights[BACKWARD_PAWN] = (-8, -10);
+        weights[UNSTOPPABLE_PASSED_PAWN] = (0, 200);
+        weights[PASSED_PAWN_FRIENDLY_KING] = (0, -2);
+        weights[PASSED_PAWN_ENEMY_KING] = (0, 5);
+
+        weights[PAWN_SHIELD..PAWN_SHIELD + 2].copy_from_slice(&[(12, 0), (6, 0)]);
+        weights[PAWN_STORM..PAWN_STORM + 4].copy_from_slice(&[
+            (-5, 0),
+            (-15, 0),
+            (-10, 0),
+            (-5, 0),
+        ]);
+        weights[KING_OPEN_FILE] = (-25, 0);
+        weights[KING_SEMI_OPEN_FILE] = (-12, 0);
+        weights[KING_ZONE_ATTACK..KING_ZONE_ATTACK + 4].copy_from_slice(&[
+            (-6, -2),
+            (-5, -2),
+            (-7, -3),
+            (-10, -4),
+        ]);
+        weights[SAFE_CHECK..SAFE_CHECK + 4].copy_from_slice(&[
+            (-25, -5),
+            (-15, -5),
+            (-30, -10),
+            (-25, -10),
+        ]);
+
+        weights[CONNECTED_PAWN..CONNECTED_PAWN + 8].copy_from_slice(&CONNECTED_PAWN_WEIGHTS);
+        weights[PASSED_PAWN..PASSED_PAWN + 8].copy_from_slice(&PASSED_PAWN_WEIGHTS);
+        weights[BLOCKED_PASSED_PAWN..BLOCKED_PASSED_PAWN + 8]
+            .copy_from_slice(&BLOCKED_PASSED_PAWN_WEIGHTS);
+
+        Self { weights }
+    }
+}
+
+/// Name of the term the weight at `index` belongs to.
+pub fn term_name(index: usize) -> &'static str {
+    TERMS
+        .iter()
+        .rev()
+        .find(|(_, offset)| *offset <= index)
+        .map_or("unknown", |(name, _)| name)
+}
+
+impl EvalParams {
+    /// Human readable name of the weight at `index`, as used in parameter files.
+    pub fn name(index: usize) -> String {
+        let term = term_name(index);
+
+        let offset = TERMS
+            .iter()
+            .find(|(name, _)| *name == term)
+            .map_or(0, |(_, offset)| index - offset);
+
+        match term {
+            "material" => format!("material.{}", PIECE_NAMES[offset]),
+
+            "psqt" => {
+                let (kind_index, square) = (offset / 64, offset % 64);
+
+                let file = (b'a' + (square % 8) as u8) as char;
+                let rank = 8 - square / 8;
+
+                format!("psqt.{}.{file}{rank}", PIECE_NAMES[kind_index])
+            }
+
+            "pawn.connected" | "pawn.passed" | "pawn.passed_blocked" => {
+                format!("{term}.r{}", offset + 1)
+            }
+
+            "king.pawn_shield" | "king.pawn_storm" => format!("{term}.d{}", offset + 1),
+
+            "king.zone_attack" | "king.safe_check" => {
+                format!("{term}.{}", PIECE_NAMES[offset + 1])
+            }
+
+            _ => term.to_string(),
+        }
+    }
+
+    /// Reads a parameter file of `name middlegame endgame` lines. Weights not
+    /// mentioned in the file keep their default value.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ParamsError> {
+        let contents = fs::read_to_string(path)?;
+        let mut params = Self::default();
+
+        for (line_index, line) in contents.lines().enumerate() {
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let malformed = || ParamsError::MalformedLine(line_index + 1, line.to_string());
+
+            let fields: Vec<&str> = line.split_whitespace().collect();
+
+            let (name, middlegame, endgame) = match fields.as_slice() {
+                [name, middlegame, endgame] => (*name, *middlegame, *endgame),
+                _ => return Err(malformed()),
+            };
+
+            let index = (0..PARAM_COUNT)
+                .find(|&index| Self::name(index) == name)
+                .ok_or_else(|| ParamsError::UnknownParameter(name.to_string()))?;
+
+            params.weights[index] = (
+                middlegame.parse().map_err(|_| malformed())?,
+                endgame.parse().map_err(|_| malformed())?,
+            );
+        }
+
+        Ok(params)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut contents = String::new();
+
+        for (index, (middlegame, endgame)) in self.weights.iter().enumerate() {
+            contents.push_str(&format!("{} {middlegame} {endgame}\n", Self::name(index)));
+        }
+
+        fs::write(path, contents)
+    }
+
+    /// Formats the weights as a Rust constant that can replace the defaults.
+    pub fn to_rust_source(&self) -> String {
+        let mut source = format!("pub const TUNED_WEIGHTS: [(i32, i32); {PARAM_COUNT}] = [\n");
+
+        for (index, (middlegame, endgame)) in self.weights.iter().enumerate() {
+            source.push_str(&format!(
+                "    ({middlegame}, {endgame}), // {}\n",
+                Self::name(index)
+            ));
+        }
+
+        source.push_str("];\n");
+        source
+    }
+}
+
+#[derive(Debug)]
+pub enum ParamsError {
+    Io(io::Error),
+    MalformedLine(usize, String),
+    UnknownParameter(String),
+}
+
+impl From<io::Error> for ParamsError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl fmt::Display for ParamsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Io(err) => {
+                write!(f, "Failed to read parameter file: {err}")
+            }
+
+            Self::MalformedLine(line, contents) => {
+                write!(f, "Malformed parameter on line {line}: '{contents}'")
+            }
+
+            Self::UnknownParameter(name) => {
+                write!(f, "Unknown parameter: {name}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParamsError {}
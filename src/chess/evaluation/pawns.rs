@@ -0,0 +1,398 @@
+use std::cell::RefCell;
+
+use super::non_pawn_material;
+use super::params::{
+    BACKWARD_PAWN, BLOCKED_PASSED_PAWN, CONNECTED_PAWN, DOUBLED_PAWN, ISOLATED_PAWN, PASSED_PAWN,
+    PASSED_PAWN_ENEMY_KING, PASSED_PAWN_FRIENDLY_KING, UNSTOPPABLE_PASSED_PAWN,
+};
+use crate::chess::board::{Board, Color, PieceKind, Position};
+
+const PAWN_TABLE_SIZE: usize = 1 << 14;
+
+/// King distances beyond this no longer matter to a passed pawn.
+const MAX_KING_DISTANCE: i32 = 5;
+
+/// The pawn structure terms of one pawn configuration, which depend on the
+/// pawns alone and so can be shared by every position with the same pawns.
+#[derive(Debug, Clone)]
+struct PawnEntry {
+    key: u64,
+    terms: Vec<(Color, usize, i32)>,
+    passed_pawns: Vec<(Color, Position)>,
+}
+
+/// Always-replace cache of pawn structure terms, indexed by the pawn key.
+pub struct PawnHashTable {
+    entries: Vec<Option<PawnEntry>>,
+}
+
+impl PawnHashTable {
+    pub fn new(size: usize) -> Self {
+        Self {
+            entries: vec![None; size.next_power_of_two()],
+        }
+    }
+
+    fn probe(&mut self, board: &Board) -> &PawnEntry {
+        let key = board.pawn_hash();
+        let index = key as usize & (self.entries.len() - 1);
+
+        let entry = &mut self.entries[index];
+
+        if entry.as_ref().is_none_or(|entry| entry.key != key) {
+            *entry = Some(analyze_pawns(board, key));
+        }
+
+        entry.as_ref().unwrap()
+    }
+}
+
+thread_local! {
+    // Every search thread evaluates with its own pawn table.
+    static PAWN_TABLE: RefCell<PawnHashTable> = RefCell::new(PawnHashTable::new(PAWN_TABLE_SIZE));
+}
+
+/// Calls `add` with the side, index and count of every pawn structure weight
+/// that applies to `board`.
+pub fn for_each_pawn_term(board: &Board, mut add: impl FnMut(Color, usize, i32)) {
+    let passed_pawns = PAWN_TABLE.with(|table| {
+        let mut table = table.borrow_mut();
+        let entry = table.probe(board);
+
+        for &(side, index, count) in &entry.terms {
+            add(side, index, count);
+        }
+
+        entry.passed_pawns.clone()
+    });
+
+    for (side, square) in passed_pawns {
+        evaluate_passed_pawn(board, side, square, &mut add);
+    }
+}
+
+/// Rank counted from `side`'s first rank, 0 to 7.
+fn relative_rank(square: Position, side: Color) -> usize {
+    match side {
+        Color::White => 7 - square.0,
+        Color::Black => square.0,
+    }
+}
+
+/// The square in front of `square` from `side`'s point of view.
+fn square_ahead(square: Position, side: Color) -> Option<Position> {
+    match side {
+        Color::White => square.0.checked_sub(1).map(|rank| (rank, square.1)),
+        Color::Black => (square.0 < 7).then_some((square.0 + 1, square.1)),
+    }
+}
+
+fn king_distance(a: Position, b: Position) -> i32 {
+    (a.0 as i32 - b.0 as i32)
+        .abs()
+        .max((a.1 as i32 - b.1 as i32).abs())
+}
+
+fn pawns_of(board: &Board, side: Color) -> Vec<Position> {
+    let mut pawns = Vec::new();
+
+    for (r_index, rank) in board.state.iter().enumerate() {
+        for (f_index, square) in rank.iter().enumerate() {
+            if let Some(piece) = square {
+                if piece.color == side && piece.piece_kind == PieceKind::Pawn {
+                    pawns.push((r_index, f_index));
+                }
+            }
+        }
+    }
+
+    pawns
+}
+
+fn is_adjacent_file(a: usize, b: usize) -> bool {
+    a.abs_diff(b) == 1
+}
+
+fn analyze_pawns(board: &Board, key: u64) -> PawnEntry {
+    let mut terms = Vec::new();
+    let mut passed_pawns = Vec::new();
+
+    for side in [Color::White, Color::Black] {
+        let own = pawns_of(board, side);
+        let enemy = pawns_of(board, side.opposite());
+
+        for &square in &own {
+            let rank = relative_rank(square, side);
+
+            let is_ahead = |other: Position| relative_rank(other, side) > rank;
+
+            let doubled = own
+                .iter()
+                .any(|&other| other.1 == square.1 && is_ahead(other));
+
+            let isolated = !own.iter().any(|&other| is_adjacent_file(other.1, square.1));
+
+            // Side by side, or defending from one rank behind.
+            let connected = own.iter().any(|&other| {
+                is_adjacent_file(other.1, square.1)
+                    && (relative_rank(other, side) == rank
+                        || relative_rank(other, side) + 1 == rank)
+            });
+
+            let passed = !doubled
+                && !enemy
+                    .iter()
+                    .any(|&other| other.1.abs_diff(square.1) <= 1 && is_ahead(other));
+
+            // No pawn can come up to defend it, and an enemy pawn guards the
+            // square in front of it.
+            let backward = !isolated
+                && !connected
+                && !own.iter().any(|&other| {
+                    is_adjacent_file(other.1, square.1) && relative_rank(other, side) <= rank
+                })
+                && square_ahead(square, side)
+                    .and_then(|stop| square_ahead(stop, side))
+                    .is_some_and(|front| {
+                        enemy
+                            .iter()
+                            .any(|&other| other.0 == front.0 && is_adjacent_file(other.1, square.1))
+                    });
+
+            if doubled {
+                terms.push((side, DOUBLED_PAWN, 1));
+            }
+
+            if isolated {
+                terms.push((side, ISOLATED_PAWN, 1));
+            }
+
+            if backward {
+                terms.push((side, BACKWARD_PAWN, 1));
+            }
+
+            if connected {
+                terms.push((side, CONNECTED_PAWN + rank, 1));
+            }
+
+            if passed {
+                terms.push((side, PASSED_PAWN + rank, 1));
+                passed_pawns.push((side, square));
+            }
+        }
+    }
+
+    PawnEntry {
+        key,
+        terms,
+        passed_pawns,
+    }
+}
+
+/// The passed pawn terms that also depend on pieces and kings.
+fn evaluate_passed_pawn(
+    board: &Board,
+    side: Color,
+    square: Position,
+    add: &mut impl FnMut(Color, usize, i32),
+) {
+    let rank = relative_rank(square, side);
+
+    let stop = match square_ahead(square, side) {
+        Some(stop) => stop,
+        None => return,
+    };
+
+    if board.state[stop.0][stop.1].is_some() {
+        add(side, BLOCKED_PASSED_PAWN + rank, 1);
+    }
+
+    let (own_king, enemy_king) = match side {
+        Color::White => (board.white_king_position, board.black_king_position),
+        Color::Black => (board.black_king_position, board.white_king_position),
+    };
+
+    let (own_king, enemy_king) = match (own_king, enemy_king) {
+        (Some(own_king), Some(enemy_king)) => (own_king, enemy_king),
+        _ => return,
+    };
+
+    // King proximity matters more the further the pawn has advanced.
+    let rank_factor = rank.saturating_sub(2) as i32;
+
+    add(
+        side,
+        PASSED_PAWN_FRIENDLY_KING,
+        king_distance(own_king, stop).min(MAX_KING_DISTANCE) * rank_factor,
+    );
+    add(
+        side,
+        PASSED_PAWN_ENEMY_KING,
+        king_distance(enemy_king, stop).min(MAX_KING_DISTANCE) * rank_factor,
+    );
+
+    if non_pawn_material(board, side.opposite()) == 0 && is_unstoppable(board, side, square) {
+        add(side, UNSTOPPABLE_PASSED_PAWN, 1);
+    }
+}
+
+/// Rule of the square: the enemy king cannot catch the pawn before it promotes,
+/// and nothing stands in its way.
+fn is_unstoppable(board: &Board, side: Color, square: Position) -> bool {
+    let promotion_square = match side {
+        Color::White => (0, square.1),
+        Color::Black => (7, square.1),
+    };
+
+    let enemy_king = match side {
+        Color::White => board.black_king_position,
+        Color::Black => board.white_king_position,
+    };
+
+    let enemy_king = match enemy_king {
+        Some(enemy_king) => enemy_king,
+        None => return false,
+    };
+
+    let mut path = square;
+
+    while let Some(next) = square_ahead(path, side) {
+        if board.state[next.0][next.1].is_some() {
+            return false;
+        }
+        path = next;
+    }
+
+    // A pawn on its first move goes two squares at once.
+    let pawn_distance = (7 - relative_rank(square, side)).min(5) as i32;
+    let tempo = if board.active_turn == side { 0 } else { 1 };
+
+    pawn_distance < king_distance(enemy_king, promotion_square) - tempo
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chess::evaluation::evaluate;
+
+    fn board(fen: &str) -> Board {
+        Board::from_fen(fen).unwrap()
+    }
+
+    fn pawn_terms(board: &Board) -> Vec<(Color, usize, i32)> {
+        let mut terms = Vec::new();
+        for_each_pawn_term(board, |side, index, count| terms.push((side, index, count)));
+        terms
+    }
+
+    #[test]
+    fn traces_doubled_and_isolated_pawns() {
+        let trace = board("4k3/8/8/8/8/2P5/2P5/4K3 w - - 0 1").eval_trace();
+
+        let doubled = trace.term("pawn.doubled").unwrap();
+        assert_eq!((doubled.white, doubled.black), ((-10, -20), (0, 0)));
+
+        let isolated = trace.term("pawn.isolated").unwrap();
+        assert_eq!((isolated.white, isolated.black), ((-20, -30), (0, 0)));
+
+        // Only the front pawn of a doubled pair can be passed.
+        assert_eq!(trace.term("pawn.passed").unwrap().white, (5, 15));
+        assert_eq!(trace.term("pawn.connected").unwrap().white, (0, 0));
+    }
+
+    #[test]
+    fn traces_passed_pawns() {
+        let trace = board("4k3/4p3/8/3P4/8/8/8/4K3 w - - 0 1").eval_trace();
+        let passed = trace.term("pawn.passed").unwrap();
+        assert_eq!((passed.white, passed.black), ((0, 0), (0, 0)));
+
+        let trace = board("4k3/8/8/3P4/4p3/8/8/4K3 w - - 0 1").eval_trace();
+        let passed = trace.term("pawn.passed").unwrap();
+        assert_eq!((passed.white, passed.black), ((20, 45), (20, 45)));
+        assert_eq!(passed.difference(), (0, 0));
+
+        let trace = board("8/8/3k4/3P4/8/8/8/4K3 w - - 0 1").eval_trace();
+        assert_eq!(trace.term("pawn.passed_blocked").unwrap().white, (-5, -15));
+    }
+
+    #[test]
+    fn traces_connected_and_backward_pawns() {
+        let trace = board("4k3/8/8/8/8/3PP3/8/4K3 w - - 0 1").eval_trace();
+        let connected = trace.term("pawn.connected").unwrap();
+        assert_eq!(connected.white, (10, 10));
+        assert_eq!(trace.term("pawn.isolated").unwrap().white, (0, 0));
+
+        // d2 cannot advance past the black pawn guarding d4 and has no support.
+        let trace = board("4k3/8/8/8/2p5/4P3/3P4/4K3 w - - 0 1").eval_trace();
+        assert_eq!(trace.term("pawn.backward").unwrap().white, (-8, -10));
+    }
+
+    #[test]
+    fn trace_total_matches_the_evaluation() {
+        for fen in [
+            "4k3/8/8/8/8/2P5/2P5/4K3 w - - 0 1",
+            "4k3/8/8/3P4/4p3/8/8/4K3 b - - 0 1",
+            "r1bqkbnr/pp1ppppp/2n5/2p5/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3",
+        ] {
+            let board = board(fen);
+            let total = board.eval_trace().total();
+
+            let expected = match board.active_turn {
+                Color::White => total,
+                Color::Black => -total,
+            };
+
+            assert_eq!(evaluate(&board), expected, "{fen}");
+        }
+    }
+
+    #[test]
+    fn pawn_table_hits_on_the_same_pawns() {
+        let mut table = PawnHashTable::new(16);
+
+        let first = board("4k3/8/8/8/8/2P5/2P5/4K3 w - - 0 1");
+        let second = board("3qk3/8/8/8/8/2P5/2P5/R3K3 b - - 0 1");
+        assert_eq!(first.pawn_hash(), second.pawn_hash());
+
+        let terms = table.probe(&first).terms.clone();
+
+        // A hit returns the stored entry instead of analyzing the pawns again.
+        let index = first.pawn_hash() as usize & 15;
+        table.entries[index].as_mut().unwrap().terms.clear();
+        assert!(table.probe(&second).terms.is_empty());
+
+        assert_eq!(analyze_pawns(&second, second.pawn_hash()).terms, terms);
+    }
+
+    #[test]
+    fn pawn_table_replaces_colliding_entries() {
+        let mut table = PawnHashTable::new(1);
+
+        let doubled = board("4k3/8/8/8/8/2P5/2P5/4K3 w - - 0 1");
+        let connected = board("4k3/8/8/8/8/3PP3/8/4K3 w - - 0 1");
+
+        for board in [&doubled, &connected, &doubled] {
+            let entry = table.probe(board);
+
+            assert_eq!(entry.key, board.pawn_hash());
+            assert_eq!(entry.terms, analyze_pawns(board, board.pawn_hash()).terms);
+        }
+    }
+
+    #[test]
+    fn every_thread_gets_the_same_pawn_terms() {
+        let fen = "4k3/8/8/3P4/4p3/8/8/4K3 w - - 0 1";
+        let expected = pawn_terms(&board(fen));
+
+        // Repeated lookups hit the thread's table, other threads start empty.
+        assert_eq!(pawn_terms(&board(fen)), expected);
+
+        let handles: Vec<_> = (0..4)
+            .map(|_| std::thread::spawn(move || pawn_terms(&board(fen))))
+            .collect();
+
+        for handle in handles {
+            assert_eq!(handle.join().unwrap(), expected);
+        }
+    }
+}
@@ -0,0 +1,139 @@
+use std::fmt;
+
+use super::endgame::{self, Endgame};
+use super::params::{default_params, term_name, EvalParams, Weight, TERMS};
+use super::{for_each_side_term, MAX_PHASE};
+use crate::chess::board::{Board, Color};
+
+/// One evaluation term summed over all its weights, for each side.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceTerm {
+    pub name: &'static str,
+    pub white: Weight,
+    pub black: Weight,
+}
+
+impl TraceTerm {
+    /// White's score minus black's, before blending by phase.
+    pub fn difference(&self) -> Weight {
+        (self.white.0 - self.black.0, self.white.1 - self.black.1)
+    }
+}
+
+/// The handcrafted evaluation broken down by term, from white's point of view.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EvalTrace {
+    pub terms: Vec<TraceTerm>,
+    pub phase: i32,
+    /// Endgame knowledge that overrides or scales the sum of the terms.
+    pub endgame: Option<Endgame>,
+}
+
+impl EvalTrace {
+    pub fn term(&self, name: &str) -> Option<&TraceTerm> {
+        self.terms.iter().find(|term| term.name == name)
+    }
+
+    fn blend(&self, weight: Weight) -> i32 {
+        (weight.0 * self.phase + weight.1 * (MAX_PHASE - self.phase)) / MAX_PHASE
+    }
+
+    /// The final score from white's point of view, equal to the evaluation.
+    pub fn total(&self) -> i32 {
+        let (middlegame, endgame) = self
+            .terms
+            .iter()
+            .map(TraceTerm::difference)
+            .fold((0, 0), |total, weight| {
+                (total.0 + weight.0, total.1 + weight.1)
+            });
+
+        let score = self.blend((middlegame, endgame));
+
+        match self.endgame {
+            Some(endgame) => endgame.apply(score),
+            None => score,
+        }
+    }
+}
+
+/// Breaks the evaluation of `board` with `params` down by term.
+pub fn trace_with(board: &Board, params: &EvalParams) -> EvalTrace {
+    let mut terms: Vec<TraceTerm> = TERMS
+        .iter()
+        .map(|&(name, _)| TraceTerm {
+            name,
+            white: (0, 0),
+            black: (0, 0),
+        })
+        .collect();
+
+    let phase = for_each_side_term(board, |side, index, count| {
+        let (mg_weight, eg_weight) = params.weights[index];
+        let name = term_name(index);
+
+        let term = terms.iter_mut().find(|term| term.name == name).unwrap();
+
+        let score = match side {
+            Color::White => &mut term.white,
+            Color::Black => &mut term.black,
+        };
+
+        score.0 += mg_weight * count;
+        score.1 += eg_weight * count;
+    });
+
+    EvalTrace {
+        terms,
+        phase,
+        endgame: endgame::probe(board),
+    }
+}
+
+impl Board {
+    /// Breakdown of the handcrafted evaluation with the default parameters.
+    pub fn eval_trace(&self) -> EvalTrace {
+        trace_with(self, default_params())
+    }
+}
+
+impl fmt::Display for EvalTrace {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "{:<26} | {:>13} | {:>13} | {:>13}",
+            "Term", "White", "Black", "Total"
+        )?;
+        writeln!(
+            f,
+            "{:<26} | {:>6} {:>6} | {:>6} {:>6} | {:>6} {:>6}",
+            "", "MG", "EG", "MG", "EG", "MG", "EG"
+        )?;
+        writeln!(f, "{}", "-".repeat(76))?;
+
+        for term in &self.terms {
+            let difference = term.difference();
+
+            writeln!(
+                f,
+                "{:<26} | {:>6} {:>6} | {:>6} {:>6} | {:>6} {:>6}",
+                term.name,
+                term.white.0,
+                term.white.1,
+                term.black.0,
+                term.black.1,
+                difference.0,
+                difference.1
+            )?;
+        }
+
+        writeln!(f, "{}", "-".repeat(76))?;
+        writeln!(f, "Phase: {}/{MAX_PHASE}", self.phase)?;
+
+        if let Some(endgame) = self.endgame {
+            writeln!(f, "Endgame: {} ({:?})", endgame.name, endgame.score)?;
+        }
+
+        writeln!(f, "Total: {} (white side)", self.total())
+    }
+}
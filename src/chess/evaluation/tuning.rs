@@ -0,0 +1,304 @@
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+
+use super::params::{EvalParams, PARAM_COUNT};
+use super::{for_each_term, MAX_PHASE};
+use crate::chess::board::Board;
+
+const ADAM_BETA1: f64 = 0.9;
+const ADAM_BETA2: f64 = 0.999;
+const ADAM_EPSILON: f64 = 1e-8;
+
+/// A labeled position reduced to the evaluation terms it activates.
+pub struct TuningPosition {
+    pub phase: i32,
+    pub terms: Vec<(usize, i32)>,
+    /// Game result from white's point of view: 1.0, 0.5 or 0.0.
+    pub result: f64,
+}
+
+impl TuningPosition {
+    pub fn new(board: &Board, result: f64) -> Self {
+        let mut terms: Vec<(usize, i32)> = Vec::new();
+
+        let phase = for_each_term(board, |index, count| {
+            match terms.iter_mut().find(|(term, _)| *term == index) {
+                Some((_, total)) => *total += count,
+                None => terms.push((index, count)),
+            }
+        });
+
+        terms.retain(|&(_, count)| count != 0);
+
+        Self {
+            phase,
+            terms,
+            result,
+        }
+    }
+
+    /// Evaluation from white's point of view, without integer rounding.
+    fn evaluate(&self, weights: &[(f64, f64)]) -> f64 {
+        let mg_factor = self.phase as f64 / MAX_PHASE as f64;
+
+        self.terms
+            .iter()
+            .map(|&(index, count)| {
+                let (middlegame, endgame) = weights[index];
+                count as f64 * (middlegame * mg_factor + endgame * (1.0 - mg_factor))
+            })
+            .sum()
+    }
+}
+
+/// Parses a result as written after a FEN: `1-0`, `0-1`, `1/2-1/2` or a number.
+pub fn parse_result(result: &str) -> Option<f64> {
+    let result = result
+        .trim()
+        .trim_matches(|c| c == '[' || c == ']' || c == '"');
+
+    match result {
+        "1-0" => Some(1.0),
+        "0-1" => Some(0.0),
+        "1/2-1/2" => Some(0.5),
+        _ => result
+            .parse::<f64>()
+            .ok()
+            .filter(|result| (0.0..=1.0).contains(result)),
+    }
+}
+
+/// Parses a `fen | result` line, also accepting `fen [result]` and the
+/// `fen | score | result` lines written by the data generator.
+pub fn parse_labeled_position(line: &str) -> Result<(Board, f64), TuningError> {
+    let malformed = || TuningError::MalformedPosition(line.to_string());
+
+    let (fen, result) = match line.split_once('|') {
+        Some((fen, rest)) => (fen.trim(), rest.rsplit('|').next().unwrap()),
+        None => {
+            let index = line.rfind('[').ok_or_else(malformed)?;
+            (line[..index].trim(), &line[index..])
+        }
+    };
+
+    let result = parse_result(result).ok_or_else(malformed)?;
+    let board = Board::from_fen(fen).map_err(|_| malformed())?;
+
+    Ok((board, result))
+}
+
+pub fn load_positions(path: impl AsRef<Path>) -> Result<Vec<TuningPosition>, TuningError> {
+    let reader = BufReader::new(File::open(path)?);
+    let mut positions = Vec::new();
+
+    for line in reader.lines() {
+        let line = line?;
+
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let (board, result) = parse_labeled_position(&line)?;
+        positions.push(TuningPosition::new(&board, result));
+    }
+
+    Ok(positions)
+}
+
+/// Minimizes the squared error between game results and the evaluation passed
+/// through a logistic curve, using full-batch Adam over every weight.
+pub struct Tuner {
+    positions: Vec<TuningPosition>,
+    weights: Vec<(f64, f64)>,
+    first_moments: Vec<(f64, f64)>,
+    second_moments: Vec<(f64, f64)>,
+    steps: i32,
+    pub scaling_constant: f64,
+    pub learning_rate: f64,
+}
+
+impl Tuner {
+    pub fn new(positions: Vec<TuningPosition>, params: &EvalParams) -> Self {
+        Self {
+            positions,
+            weights: params
+                .weights
+                .iter()
+                .map(|&(middlegame, endgame)| (middlegame as f64, endgame as f64))
+                .collect(),
+            first_moments: vec![(0.0, 0.0); PARAM_COUNT],
+            second_moments: vec![(0.0, 0.0); PARAM_COUNT],
+            steps: 0,
+            scaling_constant: 1.0,
+            learning_rate: 1.0,
+        }
+    }
+
+    fn sigmoid(&self, score: f64) -> f64 {
+        1.0 / (1.0 + 10f64.powf(-self.scaling_constant * score / 400.0))
+    }
+
+    /// Mean squared error of the current weights over every position.
+    pub fn error(&self) -> f64 {
+        let total: f64 = self
+            .positions
+            .iter()
+            .map(|position| {
+                let prediction = self.sigmoid(position.evaluate(&self.weights));
+                (position.result - prediction).powi(2)
+            })
+            .sum();
+
+        total / self.positions.len().max(1) as f64
+    }
+
+    /// Picks the logistic scaling constant that best fits the untuned weights,
+    /// so the tuner moves the weights instead of the curve.
+    pub fn fit_scaling_constant(&mut self) {
+        let mut best = (self.scaling_constant, self.error());
+
+        for step in 1..=60 {
+            self.scaling_constant = step as f64 * 0.05;
+
+            let error = self.error();
+
+            if error < best.1 {
+                best = (self.scaling_constant, error);
+            }
+        }
+
+        self.scaling_constant = best.0;
+    }
+
+    /// Runs one full-batch Adam step and returns the error before the step.
+    pub fn step(&mut self) -> f64 {
+        let mut gradients = vec![(0.0, 0.0); PARAM_COUNT];
+        let mut total_error = 0.0;
+
+        let slope = self.scaling_constant * 10f64.ln() / 400.0;
+
+        for position in &self.positions {
+            let prediction = self.sigmoid(position.evaluate(&self.weights));
+            let difference = position.result - prediction;
+
+            total_error += difference.powi(2);
+
+            let delta = -2.0 * difference * prediction * (1.0 - prediction) * slope;
+            let mg_factor = position.phase as f64 / MAX_PHASE as f64;
+
+            for &(index, count) in &position.terms {
+                gradients[index].0 += delta * count as f64 * mg_factor;
+                gradients[index].1 += delta * count as f64 * (1.0 - mg_factor);
+            }
+        }
+
+        let count = self.positions.len().max(1) as f64;
+
+        self.steps += 1;
+
+        let first_correction = 1.0 - ADAM_BETA1.powi(self.steps);
+        let second_correction = 1.0 - ADAM_BETA2.powi(self.steps);
+
+        for (index, gradient) in gradients.iter().enumerate() {
+            let gradient = (gradient.0 / count, gradient.1 / count);
+
+            let first = &mut self.first_moments[index];
+            first.0 = ADAM_BETA1 * first.0 + (1.0 - ADAM_BETA1) * gradient.0;
+            first.1 = ADAM_BETA1 * first.1 + (1.0 - ADAM_BETA1) * gradient.1;
+
+            let second = &mut self.second_moments[index];
+            second.0 = ADAM_BETA2 * second.0 + (1.0 - ADAM_BETA2) * gradient.0.powi(2);
+            second.1 = ADAM_BETA2 * second.1 + (1.0 - ADAM_BETA2) * gradient.1.powi(2);
+
+            let (first, second) = (self.first_moments[index], self.second_moments[index]);
+
+            self.weights[index].0 -= self.learning_rate * (first.0 / first_correction)
+                / ((second.0 / second_correction).sqrt() + ADAM_EPSILON);
+            self.weights[index].1 -= self.learning_rate * (first.1 / first_correction)
+                / ((second.1 / second_correction).sqrt() + ADAM_EPSILON);
+        }
+
+        total_error / count
+    }
+
+    /// The current weights rounded to whole centipawns.
+    pub fn params(&self) -> EvalParams {
+        EvalParams {
+            weights: self
+                .weights
+                .iter()
+                .map(|&(middlegame, endgame)| (middlegame.round() as i32, endgame.round() as i32))
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum TuningError {
+    Io(io::Error),
+    MalformedPosition(String),
+}
+
+impl From<io::Error> for TuningError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl fmt::Display for TuningError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Io(err) => {
+                write!(f, "Failed to read positions: {err}")
+            }
+
+            Self::MalformedPosition(line) => {
+                write!(f, "Malformed labeled position: '{line}'")
+            }
+        }
+    }
+}
+
+impl std::error::Error for TuningError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_every_line_format() {
+        let fen = "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1";
+
+        for (line, expected) in [
+            (format!("{fen} | 1-0"), 1.0),
+            (format!("{fen} [0.5]"), 0.5),
+            (format!("{fen} | 35 | 0-1"), 0.0),
+        ] {
+            let (board, result) = parse_labeled_position(&line).unwrap();
+
+            assert_eq!(board.to_fen(), fen);
+            assert_eq!(result, expected);
+        }
+    }
+
+    #[test]
+    fn malformed_line_is_an_error() {
+        for line in [
+            "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1",
+            "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1 | 2-0",
+            "4k3/8/8/8/8/8/4P3/4KX2 w - - 0 1 | 1-0",
+            "4k3/8/8/8/8/8/4P3/8 w - - 0 1 | 1-0",
+            "4k3/8/8/8/8/8/4P3/4K3 w - - | 1-0",
+        ] {
+            assert!(
+                matches!(
+                    parse_labeled_position(line),
+                    Err(TuningError::MalformedPosition(_))
+                ),
+                "{line:?} was accepted"
+            );
+        }
+    }
+}
@@ -0,0 +1,675 @@
+use super::board::chess_move::{parse_square, square_to_string, Move, MoveError};
+use super::board::{Board, GameStatus, Position};
+use super::pgn::{PgnGame, PgnMove};
+
+/// Colours of arrows and highlighted squares, as used by the `[%cal ...]` and
+/// `[%csl ...]` comment commands.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AnnotationColor {
+    Green,
+    Red,
+    Yellow,
+    Blue,
+}
+
+impl AnnotationColor {
+    pub fn from_char(c: char) -> Option<Self> {
+        match c {
+            'G' => Some(Self::Green),
+            'R' => Some(Self::Red),
+            'Y' => Some(Self::Yellow),
+            'B' => Some(Self::Blue),
+            _ => None,
+        }
+    }
+
+    pub fn to_char(self) -> char {
+        match self {
+            Self::Green => 'G',
+            Self::Red => 'R',
+            Self::Yellow => 'Y',
+            Self::Blue => 'B',
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Arrow {
+    pub from: Position,
+    pub to: Position,
+    pub color: AnnotationColor,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Highlight {
+    pub square: Position,
+    pub color: AnnotationColor,
+}
+
+/// A position in the tree, reached by its move. The first child continues the
+/// line and the others are variations of it.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Node {
+    /// The move leading here, `None` only for the root.
+    pub m: Option<Move>,
+    pub nags: Vec<u8>,
+    /// Comments before the move, as written at the start of a variation.
+    pub comments_before: Vec<String>,
+    pub comments: Vec<String>,
+    pub arrows: Vec<Arrow>,
+    pub highlights: Vec<Highlight>,
+    pub children: Vec<Node>,
+}
+
+impl Node {
+    pub fn new(m: Move) -> Self {
+        Self {
+            m: Some(m),
+            ..Default::default()
+        }
+    }
+
+    fn from_pgn_move(pgn_move: &PgnMove) -> Self {
+        let mut node = Self::new(pgn_move.m);
+        node.nags = pgn_move.nags.clone();
+        node.comments_before = pgn_move.comments_before.clone();
+
+        for comment in &pgn_move.comments {
+            let text = extract_annotations(comment, &mut node.arrows, &mut node.highlights);
+
+            if !text.is_empty() {
+                node.comments.push(text);
+            }
+        }
+
+        node
+    }
+
+    /// `None` for a node without a move, which can only stand for the root.
+    fn to_pgn_move(&self) -> Option<PgnMove> {
+        let mut pgn_move = PgnMove::new(self.m?);
+        pgn_move.nags = self.nags.clone();
+        pgn_move.comments_before = self.comments_before.clone();
+        pgn_move.comments = self.comments.clone();
+
+        let commands = annotation_commands(&self.arrows, &self.highlights);
+
+        if !commands.is_empty() {
+            match pgn_move.comments.first_mut() {
+                Some(comment) => *comment = format!("{commands} {comment}"),
+                None => pgn_move.comments.push(commands),
+            }
+        }
+
+        Some(pgn_move)
+    }
+
+    /// The moves continuing from this node, with the other children as
+    /// variations. Lines stop at a node without a move.
+    fn continuation(&self) -> Vec<PgnMove> {
+        let mut line = Vec::new();
+        let mut node = self;
+
+        while let Some((main, alternatives)) = node.children.split_first() {
+            let mut pgn_move = match main.to_pgn_move() {
+                Some(pgn_move) => pgn_move,
+                None => break,
+            };
+
+            pgn_move.variations = alternatives
+                .iter()
+                .filter_map(|alternative| {
+                    let mut variation = vec![alternative.to_pgn_move()?];
+                    variation.extend(alternative.continuation());
+                    Some(variation)
+                })
+                .collect();
+
+            line.push(pgn_move);
+            node = main;
+        }
+
+        line
+    }
+
+    /// Adds `line` below this node, with the variations of each move as
+    /// alternatives to it.
+    fn add_line(&mut self, line: &[PgnMove]) {
+        if let Some((first, rest)) = line.split_first() {
+            let mut child = Self::from_pgn_move(first);
+            child.add_line(rest);
+            self.children.push(child);
+
+            for variation in &first.variations {
+                self.add_line(variation);
+            }
+        }
+    }
+}
+
+/// A game with variations and a cursor for walking through it. The cursor is
+/// the path of child indices from the root, and the board at the cursor is
+/// kept up to date as it moves.
+#[derive(Clone)]
+pub struct GameTree {
+    pub tags: Vec<(String, String)>,
+    pub start: Board,
+    pub root: Node,
+    pub result: GameStatus,
+    path: Vec<usize>,
+    board: Board,
+}
+
+impl GameTree {
+    pub fn new(start: &Board) -> Self {
+        Self::from_pgn(&PgnGame::new(start))
+    }
+
+    pub fn from_pgn(game: &PgnGame) -> Self {
+        let mut root = Node::default();
+        root.add_line(&game.moves);
+
+        Self {
+            tags: game.tags.clone(),
+            start: game.start.clone(),
+            root,
+            result: game.result,
+            path: Vec::new(),
+            board: game.start.clone(),
+        }
+    }
+
+    pub fn to_pgn(&self) -> PgnGame {
+        PgnGame {
+            tags: self.tags.clone(),
+            start: self.start.clone(),
+            moves: self.root.continuation(),
+            result: self.result,
+        }
+    }
+
+    /// The moves of the main line from the start.
+    pub fn mainline(&self) -> Vec<Move> {
+        let mut moves = Vec::new();
+        let mut node = &self.root;
+
+        while let Some(child) = node.children.first() {
+            moves.extend(child.m);
+            node = child;
+        }
+
+        moves
+    }
+
+    /// The position at the cursor.
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    /// Child indices from the root to the cursor.
+    pub fn path(&self) -> &[usize] {
+        &self.path
+    }
+
+    /// The moves from the start to the cursor.
+    pub fn moves(&self) -> Vec<Move> {
+        let mut moves = Vec::new();
+        let mut node = &self.root;
+
+        for &index in &self.path {
+            node = &node.children[index];
+            moves.extend(node.m);
+        }
+
+        moves
+    }
+
+    pub fn current(&self) -> &Node {
+        let mut node = &self.root;
+
+        for &index in &self.path {
+            node = &node.children[index];
+        }
+
+        node
+    }
+
+    pub fn current_mut(&mut self) -> &mut Node {
+        let mut node = &mut self.root;
+
+        for &index in &self.path {
+            node = &mut node.children[index];
+        }
+
+        node
+    }
+
+    pub fn is_at_start(&self) -> bool {
+        self.path.is_empty()
+    }
+
+    /// Whether the cursor is on the main line.
+    pub fn is_on_mainline(&self) -> bool {
+        self.path.iter().all(|&index| index == 0)
+    }
+
+    /// Follows the line the cursor is on. Returns false at its end.
+    pub fn forward(&mut self) -> bool {
+        self.enter_variation(0)
+    }
+
+    /// Moves the cursor to child `index`, where 0 continues the line and the
+    /// others start variations. Returns false when there is no such child.
+    pub fn enter_variation(&mut self, index: usize) -> bool {
+        let m = match self.current().children.get(index).and_then(|child| child.m) {
+            Some(m) => m,
+            None => return false,
+        };
+
+        self.board.make_move_unchecked(m);
+        self.path.push(index);
+        true
+    }
+
+    /// Takes back the move at the cursor. Returns false at the start.
+    pub fn back(&mut self) -> bool {
+        if self.path.pop().is_none() {
+            return false;
+        }
+
+        self.replay();
+        true
+    }
+
+    pub fn go_to_start(&mut self) {
+        self.path.clear();
+        self.board = self.start.clone();
+    }
+
+    /// Follows the line the cursor is on to its end.
+    pub fn go_to_end(&mut self) {
+        while self.forward() {}
+    }
+
+    /// Plays `m` at the cursor and moves to it. A move already in the tree is
+    /// followed; a new one continues the line when there is nothing after the
+    /// cursor and starts a variation otherwise.
+    pub fn add_move(&mut self, m: Move) -> Result<(), MoveError> {
+        if !self.board.get_legal_moves().contains(&m) {
+            return Err(MoveError::IllegalMoveError);
+        }
+
+        let node = self.current_mut();
+
+        let index = match node.children.iter().position(|child| child.m == Some(m)) {
+            Some(index) => index,
+            None => {
+                node.children.push(Node::new(m));
+                node.children.len() - 1
+            }
+        };
+
+        self.enter_variation(index);
+        Ok(())
+    }
+
+    /// Makes the variation the cursor is in the main continuation at the point
+    /// where it branches off, with the old continuation as its first
+    /// variation. Returns false when the cursor is on the main line.
+    pub fn promote_variation(&mut self) -> bool {
+        let depth = match self.path.iter().rposition(|&index| index != 0) {
+            Some(depth) => depth,
+            None => return false,
+        };
+
+        let index = self.path[depth];
+        let parent = self.node_at_mut(depth);
+        let promoted = parent.children.remove(index);
+        parent.children.insert(0, promoted);
+
+        self.path[depth] = 0;
+        true
+    }
+
+    /// Removes the variation the cursor is in, from where it branches off,
+    /// and moves the cursor to the branching point. Returns false when the
+    /// cursor is on the main line.
+    pub fn delete_variation(&mut self) -> bool {
+        let depth = match self.path.iter().rposition(|&index| index != 0) {
+            Some(depth) => depth,
+            None => return false,
+        };
+
+        let index = self.path[depth];
+        self.node_at_mut(depth).children.remove(index);
+
+        self.path.truncate(depth);
+        self.replay();
+        true
+    }
+
+    /// The node reached by the first `depth` steps of the cursor's path.
+    fn node_at_mut(&mut self, depth: usize) -> &mut Node {
+        let mut node = &mut self.root;
+
+        for &index in &self.path[..depth] {
+            node = &mut node.children[index];
+        }
+
+        node
+    }
+
+    /// Recomputes the board at the cursor from the start.
+    fn replay(&mut self) {
+        let mut board = self.start.clone();
+
+        for m in self.moves() {
+            board.make_move_unchecked(m);
+        }
+
+        self.board = board;
+    }
+}
+
+/// Moves the `[%cal ...]` arrows and `[%csl ...]` highlights out of `comment`
+/// and returns the text left over.
+fn extract_annotations(
+    comment: &str,
+    arrows: &mut Vec<Arrow>,
+    highlights: &mut Vec<Highlight>,
+) -> String {
+    let mut text = String::new();
+    let mut rest = comment;
+
+    while let Some(start) = rest.find("[%") {
+        let end = match rest[start..].find(']') {
+            Some(end) => start + end,
+            None => break,
+        };
+
+        // Wrapping may have put a line break after the command name.
+        let command = &rest[start + 2..end];
+
+        let parsed = match command.split_once(char::is_whitespace) {
+            Some(("cal", arguments)) => parse_arrows(arguments).map(|new| arrows.extend(new)),
+            Some(("csl", arguments)) => {
+                parse_highlights(arguments).map(|new| highlights.extend(new))
+            }
+            _ => None,
+        };
+
+        // Other commands, such as clocks, stay in the comment.
+        if parsed.is_none() {
+            text.push_str(&rest[..=end]);
+        } else {
+            text.push_str(&rest[..start]);
+        }
+
+        rest = &rest[end + 1..];
+    }
+
+    text.push_str(rest);
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn parse_arrows(arguments: &str) -> Option<Vec<Arrow>> {
+    arguments
+        .split(',')
+        .map(|arrow| {
+            let arrow = arrow.trim();
+            let color = AnnotationColor::from_char(arrow.chars().next()?)?;
+
+            if arrow.len() != 5 || !arrow.is_ascii() {
+                return None;
+            }
+
+            Some(Arrow {
+                from: parse_square(&arrow[1..3])?,
+                to: parse_square(&arrow[3..5])?,
+                color,
+            })
+        })
+        .collect()
+}
+
+fn parse_highlights(arguments: &str) -> Option<Vec<Highlight>> {
+    arguments
+        .split(',')
+        .map(|highlight| {
+            let highlight = highlight.trim();
+            let color = AnnotationColor::from_char(highlight.chars().next()?)?;
+
+            if highlight.len() != 3 || !highlight.is_ascii() {
+                return None;
+            }
+
+            Some(Highlight {
+                square: parse_square(&highlight[1..3])?,
+                color,
+            })
+        })
+        .collect()
+}
+
+/// The comment commands for `arrows` and `highlights`, or an empty string.
+fn annotation_commands(arrows: &[Arrow], highlights: &[Highlight]) -> String {
+    let mut commands = Vec::new();
+
+    if !highlights.is_empty() {
+        let squares: Vec<String> = highlights
+            .iter()
+            .map(|highlight| {
+                format!(
+                    "{}{}",
+                    highlight.color.to_char(),
+                    square_to_string(highlight.square)
+                )
+            })
+            .collect();
+
+        commands.push(format!("[%csl {}]", squares.join(",")));
+    }
+
+    if !arrows.is_empty() {
+        let arrows: Vec<String> = arrows
+            .iter()
+            .map(|arrow| {
+                format!(
+                    "{}{}{}",
+                    arrow.color.to_char(),
+                    square_to_string(arrow.from),
+                    square_to_string(arrow.to)
+                )
+            })
+            .collect();
+
+        commands.push(format!("[%cal {}]", arrows.join(",")));
+    }
+
+    commands.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chess::board::START_FEN;
+    use crate::chess::pgn::parse_game;
+
+    fn tree(movetext: &str) -> GameTree {
+        GameTree::from_pgn(&parse_game(movetext).unwrap())
+    }
+
+    fn uci(tree: &GameTree, uci: &str) -> Move {
+        Move::from_uci(tree.board(), uci).unwrap()
+    }
+
+    /// The movetext of the tree, without the tags.
+    fn movetext(tree: &GameTree) -> String {
+        let pgn = tree.to_pgn().to_pgn();
+        pgn.split_once("\n\n").unwrap().1.trim_end().to_string()
+    }
+
+    #[test]
+    fn added_moves_continue_the_line_or_start_a_variation() {
+        let mut tree = GameTree::new(&Board::from_fen(START_FEN).unwrap());
+
+        tree.add_move(uci(&tree, "e2e4")).unwrap();
+        tree.add_move(uci(&tree, "e7e5")).unwrap();
+        assert_eq!(tree.path(), [0, 0]);
+
+        tree.back();
+        tree.add_move(uci(&tree, "c7c5")).unwrap();
+        assert_eq!(tree.path(), [0, 1]);
+
+        // A move already in the tree is followed rather than added again.
+        tree.back();
+        tree.add_move(uci(&tree, "e7e5")).unwrap();
+        assert_eq!(tree.path(), [0, 0]);
+        assert_eq!(tree.root.children[0].children.len(), 2);
+
+        let illegal = Move::from_uci(tree.board(), "e4e5").unwrap();
+        assert!(matches!(
+            tree.add_move(illegal),
+            Err(MoveError::IllegalMoveError)
+        ));
+        assert_eq!(tree.path(), [0, 0]);
+
+        assert_eq!(movetext(&tree), "1. e4 e5 (1... c5) *");
+    }
+
+    #[test]
+    fn promoting_a_variation_swaps_it_with_the_main_line() {
+        let mut tree = tree("1. e4 e5 (1... c5 2. Nf3 (2. c3) 2... d6) 2. Nf3 *");
+
+        assert!(!tree.promote_variation());
+
+        tree.forward();
+        tree.enter_variation(1);
+        tree.enter_variation(1);
+        let board = tree.board().to_fen();
+
+        // Only the innermost variation moves up.
+        assert!(tree.promote_variation());
+        assert_eq!(tree.path(), [0, 1, 0]);
+        assert_eq!(tree.board().to_fen(), board);
+        assert_eq!(
+            movetext(&tree),
+            "1. e4 e5 (1... c5 2. c3 (2. Nf3 d6)) 2. Nf3 *"
+        );
+
+        assert!(tree.promote_variation());
+        assert_eq!(tree.path(), [0, 0, 0]);
+        assert!(tree.is_on_mainline());
+        assert_eq!(tree.board().to_fen(), board);
+        assert_eq!(
+            movetext(&tree),
+            "1. e4 c5 (1... e5 2. Nf3) 2. c3 (2. Nf3 d6) *"
+        );
+
+        assert!(!tree.promote_variation());
+    }
+
+    #[test]
+    fn deleting_a_variation_returns_to_where_it_branches_off() {
+        let mut tree = tree("1. e4 e5 (1... c5 2. Nf3 d6) 2. Nf3 *");
+        let mainline = tree.mainline();
+
+        tree.forward();
+        let after_e4 = tree.board().to_fen();
+
+        tree.enter_variation(1);
+        tree.go_to_end();
+        assert_eq!(tree.path(), [0, 1, 0, 0]);
+
+        assert!(tree.delete_variation());
+        assert_eq!(tree.path(), [0]);
+        assert_eq!(tree.board().to_fen(), after_e4);
+        assert_eq!(tree.mainline(), mainline);
+        assert_eq!(movetext(&tree), "1. e4 e5 2. Nf3 *");
+
+        assert!(!tree.delete_variation());
+    }
+
+    #[test]
+    fn arrows_and_highlights_are_taken_out_of_comments() {
+        let tree = tree(
+            "1. e4 {[%csl Gd4,Re5] [%cal Ge2e4, Bg1f3] good [%clk 0:01:00]} \
+             e5 {[%cal Ge7e5]} 2. Nf3 {[%cal Xe1e2] [%csl Rd9]} *",
+        );
+
+        let e4 = &tree.root.children[0];
+        let square = |name| parse_square(name).unwrap();
+
+        assert_eq!(
+            e4.highlights,
+            [
+                Highlight {
+                    square: square("d4"),
+                    color: AnnotationColor::Green,
+                },
+                Highlight {
+                    square: square("e5"),
+                    color: AnnotationColor::Red,
+                },
+            ]
+        );
+        assert_eq!(
+            e4.arrows,
+            [
+                Arrow {
+                    from: square("e2"),
+                    to: square("e4"),
+                    color: AnnotationColor::Green,
+                },
+                Arrow {
+                    from: square("g1"),
+                    to: square("f3"),
+                    color: AnnotationColor::Blue,
+                },
+            ]
+        );
+        // Other commands stay in the comment.
+        assert_eq!(e4.comments, ["good [%clk 0:01:00]"]);
+
+        // A comment of nothing but arrows is dropped.
+        let e5 = &e4.children[0];
+        assert_eq!(e5.arrows.len(), 1);
+        assert!(e5.comments.is_empty());
+
+        // Commands that do not parse are left alone.
+        let nf3 = &e5.children[0];
+        assert!(nf3.arrows.is_empty() && nf3.highlights.is_empty());
+        assert_eq!(nf3.comments, ["[%cal Xe1e2] [%csl Rd9]"]);
+    }
+
+    #[test]
+    fn arrows_survive_a_pgn_round_trip() {
+        let tree = tree(
+            "1. e4 {[%csl Gd4] [%cal Ge2e4,Rg8f6] the centre} e5 (1... c5 {[%cal Bb1c3]}) \
+             2. Nf3 *",
+        );
+
+        assert_eq!(
+            movetext(&tree),
+            "1. e4 {[%csl Gd4] [%cal Ge2e4,Rg8f6] the centre} 1... e5 (1... c5 {[%cal\n\
+             Bb1c3]}) 2. Nf3 *"
+        );
+
+        let reread = GameTree::from_pgn(&parse_game(&tree.to_pgn().to_pgn()).unwrap());
+        assert_eq!(reread.root, tree.root);
+    }
+
+    #[test]
+    fn nodes_without_a_move_are_skipped() {
+        let mut tree = tree("1. e4 e5 *");
+        tree.root.children[0].children.push(Node::default());
+        tree.root.children.insert(0, Node::default());
+
+        assert_eq!(movetext(&tree), "*");
+        assert!(!tree.forward());
+
+        assert!(tree.enter_variation(1));
+        assert!(!tree.enter_variation(1));
+        assert_eq!(tree.path(), [1]);
+    }
+}
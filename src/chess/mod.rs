@@ -1 +1,13 @@
 pub mod board;
+pub mod book;
+pub mod datagen;
+pub mod epd;
+pub mod evaluation;
+pub mod game_tree;
+pub mod options;
+pub mod pgn;
+pub mod search;
+pub mod solver;
+pub mod syzygy;
+pub mod tablebase;
+pub mod tournament;
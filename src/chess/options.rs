@@ -0,0 +1,153 @@
+use std::fmt;
+
+use super::search::skill::{MAX_ELO, MAX_SKILL_LEVEL, MIN_ELO};
+
+/// Tunable engine switches, settable by their UCI option names so self-play
+/// runs can measure the effect of each one.
+#[derive(Debug, Clone)]
+pub struct EngineOptions {
+    pub null_move_pruning: bool,
+    pub late_move_reductions: bool,
+    pub futility_pruning: bool,
+    pub reverse_futility_pruning: bool,
+    pub razoring: bool,
+    pub check_extensions: bool,
+    pub aspiration_windows: bool,
+    pub threads: usize,
+    pub hash_size_mb: usize,
+    pub multi_pv: usize,
+    pub use_nnue: bool,
+    pub eval_file: Option<String>,
+    pub eval_params_file: Option<String>,
+    /// 0 to 20, where 20 is full strength.
+    pub skill_level: usize,
+    /// Play at `uci_elo` instead of `skill_level`.
+    pub limit_strength: bool,
+    pub uci_elo: usize,
+    /// Polyglot opening book consulted before searching.
+    pub book_file: Option<String>,
+    /// Last full move number at which the book is consulted.
+    pub book_depth: usize,
+    /// Always play the book move with the highest weight instead of picking at
+    /// random by weight.
+    pub book_best_move: bool,
+    /// Directory of generated endgame tables, probed in search.
+    pub tablebase_path: Option<String>,
+    /// Directories of Syzygy tables, separated like `PATH`.
+    pub syzygy_path: Option<String>,
+    /// Score cursed wins and blessed losses as the draws the 50-move rule
+    /// makes them.
+    pub syzygy_50_move_rule: bool,
+}
+
+impl Default for EngineOptions {
+    fn default() -> Self {
+        Self {
+            null_move_pruning: true,
+            late_move_reductions: true,
+            futility_pruning: true,
+            reverse_futility_pruning: true,
+            razoring: true,
+            check_extensions: true,
+            aspiration_windows: true,
+            threads: 1,
+            hash_size_mb: 16,
+            multi_pv: 1,
+            use_nnue: false,
+            eval_file: None,
+            eval_params_file: None,
+            skill_level: MAX_SKILL_LEVEL,
+            limit_strength: false,
+            uci_elo: MAX_ELO,
+            book_file: None,
+            book_depth: 16,
+            book_best_move: false,
+            tablebase_path: None,
+            syzygy_path: None,
+            syzygy_50_move_rule: true,
+        }
+    }
+}
+
+impl EngineOptions {
+    pub fn set_option(&mut self, name: &str, value: &str) -> Result<(), OptionError> {
+        match name {
+            "NullMove" => self.null_move_pruning = parse_check(name, value)?,
+            "LMR" => self.late_move_reductions = parse_check(name, value)?,
+            "Futility" => self.futility_pruning = parse_check(name, value)?,
+            "ReverseFutility" => self.reverse_futility_pruning = parse_check(name, value)?,
+            "Razoring" => self.razoring = parse_check(name, value)?,
+            "CheckExtensions" => self.check_extensions = parse_check(name, value)?,
+            "AspirationWindows" => self.aspiration_windows = parse_check(name, value)?,
+            "Threads" => self.threads = parse_spin(name, value, 1, 256)?,
+            "Hash" => self.hash_size_mb = parse_spin(name, value, 1, 65536)?,
+            "MultiPV" => self.multi_pv = parse_spin(name, value, 1, 256)?,
+            "UseNNUE" => self.use_nnue = parse_check(name, value)?,
+            "EvalFile" => self.eval_file = parse_string(value),
+            "EvalParamsFile" => self.eval_params_file = parse_string(value),
+            "Skill Level" => self.skill_level = parse_spin(name, value, 0, MAX_SKILL_LEVEL)?,
+            "UCI_LimitStrength" => self.limit_strength = parse_check(name, value)?,
+            "UCI_Elo" => self.uci_elo = parse_spin(name, value, MIN_ELO, MAX_ELO)?,
+            "BookFile" => self.book_file = parse_string(value),
+            "BookDepth" => self.book_depth = parse_spin(name, value, 0, 1000)?,
+            "BookBestMove" => self.book_best_move = parse_check(name, value)?,
+            "TablebasePath" => self.tablebase_path = parse_string(value),
+            "SyzygyPath" => self.syzygy_path = parse_string(value),
+            "Syzygy50MoveRule" => self.syzygy_50_move_rule = parse_check(name, value)?,
+
+            _ => return Err(OptionError::UnknownOption(name.to_string())),
+        }
+        Ok(())
+    }
+}
+
+fn parse_check(name: &str, value: &str) -> Result<bool, OptionError> {
+    match value {
+        "true" => Ok(true),
+        "false" => Ok(false),
+        _ => Err(OptionError::InvalidValue(
+            name.to_string(),
+            value.to_string(),
+        )),
+    }
+}
+
+/// UCI GUIs send `<empty>` to clear a string option.
+fn parse_string(value: &str) -> Option<String> {
+    match value {
+        "" | "<empty>" => None,
+        _ => Some(value.to_string()),
+    }
+}
+
+fn parse_spin(name: &str, value: &str, min: usize, max: usize) -> Result<usize, OptionError> {
+    match value.parse::<usize>() {
+        Ok(value) if (min..=max).contains(&value) => Ok(value),
+        _ => Err(OptionError::InvalidValue(
+            name.to_string(),
+            value.to_string(),
+        )),
+    }
+}
+
+#[derive(Debug)]
+pub enum OptionError {
+    UnknownOption(String),
+    InvalidValue(String, String),
+}
+
+impl fmt::Display for OptionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::UnknownOption(name) => {
+                write!(f, "Unknown option: {name}")
+            }
+
+            Self::InvalidValue(name, value) => {
+                write!(f, "Invalid value for option {name}: {value}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for OptionError {}
@@ -0,0 +1,200 @@
+use std::fmt;
+
+use super::PgnError;
+
+/// A position in a PGN file, both counted from 1.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Location {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}, column {}", self.line, self.column)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Token {
+    TagOpen,
+    TagClose,
+    /// Tag names, move numbers, moves and results.
+    Symbol(String),
+    Str(String),
+    Period,
+    Asterisk,
+    Comment(String),
+    Nag(u8),
+    VariationOpen,
+    VariationClose,
+}
+
+/// Splits the text of one game into tokens, each with the location it starts at.
+pub struct Lexer<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+    location: Location,
+}
+
+impl<'a> Lexer<'a> {
+    pub fn new(text: &'a str, first_line: usize) -> Self {
+        Self {
+            chars: text.chars().peekable(),
+            location: Location {
+                line: first_line,
+                column: 1,
+            },
+        }
+    }
+
+    fn advance(&mut self) -> Option<char> {
+        let c = self.chars.next()?;
+
+        if c == '\n' {
+            self.location.line += 1;
+            self.location.column = 1;
+        } else {
+            self.location.column += 1;
+        }
+
+        Some(c)
+    }
+
+    fn skip_line(&mut self) -> String {
+        let mut skipped = String::new();
+
+        while let Some(c) = self.chars.peek().copied() {
+            if c == '\n' {
+                break;
+            }
+
+            skipped.push(c);
+            self.advance();
+        }
+
+        skipped
+    }
+
+    pub fn tokenize(mut self) -> Result<Vec<(Location, Token)>, PgnError> {
+        let mut tokens = Vec::new();
+
+        while let Some(&c) = self.chars.peek() {
+            let start = self.location;
+
+            // A `%` in the first column escapes the rest of the line.
+            if c == '%' && start.column == 1 {
+                self.skip_line();
+                continue;
+            }
+
+            if c.is_whitespace() {
+                self.advance();
+                continue;
+            }
+
+            let token = match c {
+                '[' | ']' | '(' | ')' | '.' | '*' => {
+                    self.advance();
+
+                    match c {
+                        '[' => Token::TagOpen,
+                        ']' => Token::TagClose,
+                        '(' => Token::VariationOpen,
+                        ')' => Token::VariationClose,
+                        '.' => Token::Period,
+                        _ => Token::Asterisk,
+                    }
+                }
+
+                '{' => {
+                    self.advance();
+                    let mut comment = String::new();
+
+                    loop {
+                        match self.advance() {
+                            Some('}') => break,
+                            Some(c) => comment.push(c),
+                            None => return Err(PgnError::UnterminatedComment(start)),
+                        }
+                    }
+
+                    Token::Comment(comment.trim().to_string())
+                }
+
+                ';' => {
+                    self.advance();
+                    Token::Comment(self.skip_line().trim().to_string())
+                }
+
+                '"' => {
+                    self.advance();
+                    let mut value = String::new();
+
+                    loop {
+                        match self.advance() {
+                            Some('"') => break,
+                            Some('\\') => match self.advance() {
+                                Some(c) => value.push(c),
+                                None => return Err(PgnError::UnterminatedString(start)),
+                            },
+                            Some('\n') | None => return Err(PgnError::UnterminatedString(start)),
+                            Some(c) => value.push(c),
+                        }
+                    }
+
+                    Token::Str(value)
+                }
+
+                '$' => {
+                    self.advance();
+                    let mut digits = String::new();
+
+                    while let Some(c) = self.chars.peek().copied().filter(char::is_ascii_digit) {
+                        digits.push(c);
+                        self.advance();
+                    }
+
+                    let nag = digits
+                        .parse()
+                        .map_err(|_| PgnError::UnexpectedToken(start, format!("${digits}")))?;
+
+                    Token::Nag(nag)
+                }
+
+                c if c.is_ascii_alphanumeric() => {
+                    let mut symbol = String::new();
+
+                    while let Some(c) = self.chars.peek().copied().filter(|&c| is_symbol_char(c)) {
+                        symbol.push(c);
+                        self.advance();
+                    }
+
+                    Token::Symbol(symbol)
+                }
+
+                // Suffix annotations separated from their move, as in `e4 !?`.
+                '!' | '?' => {
+                    let mut symbol = String::new();
+
+                    while let Some(c) = self.chars.peek().copied().filter(|&c| c == '!' || c == '?')
+                    {
+                        symbol.push(c);
+                        self.advance();
+                    }
+
+                    Token::Symbol(symbol)
+                }
+
+                c => return Err(PgnError::UnexpectedToken(start, c.to_string())),
+            };
+
+            tokens.push((start, token));
+        }
+
+        Ok(tokens)
+    }
+}
+
+fn is_symbol_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "_+#=:-/!?".contains(c)
+}
@@ -0,0 +1,735 @@
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::mem;
+use std::path::Path;
+
+use self::lexer::{Lexer, Token};
+use super::board::chess_move::{Move, MoveError};
+use super::board::{Board, GameStatus};
+
+pub mod lexer;
+pub mod writer;
+
+pub use self::lexer::Location;
+pub use self::writer::MoveAnnotation;
+
+/// The tags every PGN game carries, in the order they are written.
+pub const SEVEN_TAG_ROSTER: [&str; 7] =
+    ["Event", "Site", "Date", "Round", "White", "Black", "Result"];
+
+/// A move with its annotations and the alternatives to it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PgnMove {
+    pub m: Move,
+    /// Numeric annotation glyphs. Suffixes such as `!?` are stored as theirs.
+    pub nags: Vec<u8>,
+    /// Comments before the move, which only the first move of a line has.
+    pub comments_before: Vec<String>,
+    pub comments: Vec<String>,
+    /// Lines played instead of this move, from the position before it.
+    pub variations: Vec<Vec<PgnMove>>,
+}
+
+impl PgnMove {
+    pub fn new(m: Move) -> Self {
+        Self {
+            m,
+            nags: Vec::new(),
+            comments_before: Vec::new(),
+            comments: Vec::new(),
+            variations: Vec::new(),
+        }
+    }
+}
+
+/// A game read from PGN, with its tags, main line and variations.
+#[derive(Clone)]
+pub struct PgnGame {
+    /// Tags in the order they were read.
+    pub tags: Vec<(String, String)>,
+    pub start: Board,
+    pub moves: Vec<PgnMove>,
+    /// `Ongoing` for games ending in `*`.
+    pub result: GameStatus,
+}
+
+impl PgnGame {
+    /// A game without moves from `start`, with the seven tag roster unknown.
+    pub fn new(start: &Board) -> Self {
+        let tags = SEVEN_TAG_ROSTER
+            .iter()
+            .map(|&name| {
+                let value = match name {
+                    "Date" => "????.??.??",
+                    "Result" => "*",
+                    _ => "?",
+                };
+
+                (name.to_string(), value.to_string())
+            })
+            .collect();
+
+        Self {
+            tags,
+            start: start.clone(),
+            moves: Vec::new(),
+            result: GameStatus::Ongoing,
+        }
+    }
+
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(tag, _)| tag == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Replaces the value of tag `name`, adding the tag if it is missing.
+    pub fn set_tag(&mut self, name: &str, value: &str) {
+        match self.tags.iter_mut().find(|(tag, _)| tag == name) {
+            Some((_, old_value)) => *old_value = value.to_string(),
+            None => self.tags.push((name.to_string(), value.to_string())),
+        }
+    }
+
+    /// The moves of the main line.
+    pub fn mainline(&self) -> Vec<Move> {
+        self.moves.iter().map(|pgn_move| pgn_move.m).collect()
+    }
+
+    /// The result from white's point of view, or `None` for unfinished games.
+    pub fn score(&self) -> Option<f64> {
+        match self.result {
+            GameStatus::WhiteWin => Some(1.0),
+            GameStatus::BlackWin => Some(0.0),
+            GameStatus::Draw => Some(0.5),
+            GameStatus::Ongoing => None,
+        }
+    }
+}
+
+/// The result as written in the `Result` tag and at the end of the movetext.
+pub fn result_to_string(result: GameStatus) -> &'static str {
+    match result {
+        GameStatus::WhiteWin => "1-0",
+        GameStatus::BlackWin => "0-1",
+        GameStatus::Draw => "1/2-1/2",
+        GameStatus::Ongoing => "*",
+    }
+}
+
+fn parse_result(result: &str) -> Option<GameStatus> {
+    match result {
+        "1-0" => Some(GameStatus::WhiteWin),
+        "0-1" => Some(GameStatus::BlackWin),
+        "1/2-1/2" => Some(GameStatus::Draw),
+        "*" => Some(GameStatus::Ongoing),
+        _ => None,
+    }
+}
+
+/// The glyph for a suffix annotation.
+fn suffix_nag(suffix: &str) -> Option<u8> {
+    match suffix {
+        "!" => Some(1),
+        "?" => Some(2),
+        "!!" => Some(3),
+        "??" => Some(4),
+        "!?" => Some(5),
+        "?!" => Some(6),
+        _ => None,
+    }
+}
+
+/// Parses the text of a single game.
+pub fn parse_game(text: &str) -> Result<PgnGame, PgnError> {
+    let tokens = Lexer::new(text, 1).tokenize()?;
+    Parser { tokens, index: 0 }.parse_game()
+}
+
+struct Parser {
+    tokens: Vec<(Location, Token)>,
+    index: usize,
+}
+
+impl Parser {
+    fn next(&mut self) -> Option<(Location, Token)> {
+        let token = self.tokens.get(self.index).cloned();
+        self.index += 1;
+        token
+    }
+
+    fn parse_game(&mut self) -> Result<PgnGame, PgnError> {
+        let mut tags = Vec::new();
+        let mut fen = None;
+
+        while let Some((location, Token::TagOpen)) = self.tokens.get(self.index).cloned() {
+            self.index += 1;
+
+            match (self.next(), self.next(), self.next()) {
+                (
+                    Some((_, Token::Symbol(name))),
+                    Some((value_location, Token::Str(value))),
+                    Some((_, Token::TagClose)),
+                ) => {
+                    if name == "FEN" {
+                        fen = Some((value_location, value.clone()));
+                    }
+
+                    tags.push((name, value));
+                }
+                _ => return Err(PgnError::MalformedTag(location)),
+            }
+        }
+
+        let start = match fen {
+            Some((location, fen)) => parse_fen(&fen).ok_or(PgnError::InvalidFen(location, fen))?,
+            None => Board::from_fen("start").unwrap(),
+        };
+
+        let (moves, termination) = self.parse_line(start.clone(), None)?;
+
+        // Games cut off before their termination marker keep the tag's result.
+        let tag_result = tags
+            .iter()
+            .find(|(name, _)| name == "Result")
+            .and_then(|(_, result)| parse_result(result));
+
+        Ok(PgnGame {
+            tags,
+            start,
+            moves,
+            result: termination.or(tag_result).unwrap_or(GameStatus::Ongoing),
+        })
+    }
+
+    /// Parses moves from `board` to the end of the game or, for a variation
+    /// opened at `open`, to its closing parenthesis. Returns the moves and the
+    /// result the game ended with, if any.
+    fn parse_line(
+        &mut self,
+        mut board: Board,
+        open: Option<Location>,
+    ) -> Result<(Vec<PgnMove>, Option<GameStatus>), PgnError> {
+        let mut moves: Vec<PgnMove> = Vec::new();
+        let mut previous_board: Option<Board> = None;
+        let mut pending_comments = Vec::new();
+
+        loop {
+            let (location, token) = match self.next() {
+                Some(token) => token,
+                None => match open {
+                    Some(open) => return Err(PgnError::UnterminatedVariation(open)),
+                    None => return Ok((moves, None)),
+                },
+            };
+
+            let unexpected = |text: &str| PgnError::UnexpectedToken(location, text.to_string());
+
+            match token {
+                Token::Symbol(symbol) => {
+                    // Move numbers.
+                    if symbol.chars().all(|c| c.is_ascii_digit()) {
+                        continue;
+                    }
+
+                    if let Some(result) = parse_result(&symbol) {
+                        if open.is_some() {
+                            return Err(unexpected(&symbol));
+                        }
+
+                        return Ok((moves, Some(result)));
+                    }
+
+                    let san = symbol.trim_end_matches(['!', '?']);
+                    let nag = suffix_nag(&symbol[san.len()..]);
+
+                    if san.is_empty() {
+                        match (moves.last_mut(), nag) {
+                            (Some(last), Some(nag)) => last.nags.push(nag),
+                            _ => return Err(unexpected(&symbol)),
+                        }
+
+                        continue;
+                    }
+
+                    let m = board
+                        .move_from_san(san)
+                        .map_err(|err| PgnError::IllegalMove(location, san.to_string(), err))?;
+
+                    let mut pgn_move = PgnMove::new(m);
+                    pgn_move.nags.extend(nag);
+                    pgn_move.comments_before = mem::take(&mut pending_comments);
+
+                    previous_board = Some(board.clone());
+                    board.make_move_unchecked(m);
+                    moves.push(pgn_move);
+                }
+
+                Token::Asterisk => {
+                    if open.is_some() {
+                        return Err(unexpected("*"));
+                    }
+
+                    return Ok((moves, Some(GameStatus::Ongoing)));
+                }
+
+                Token::Period => (),
+
+                Token::Comment(comment) => match moves.last_mut() {
+                    Some(last) => last.comments.push(comment),
+                    None => pending_comments.push(comment),
+                },
+
+                Token::Nag(nag) => match moves.last_mut() {
+                    Some(last) => last.nags.push(nag),
+                    None => return Err(unexpected(&format!("${nag}"))),
+                },
+
+                Token::VariationOpen => {
+                    let before = previous_board.clone().ok_or_else(|| unexpected("("))?;
+                    let (variation, _) = self.parse_line(before, Some(location))?;
+
+                    if !variation.is_empty() {
+                        moves.last_mut().unwrap().variations.push(variation);
+                    }
+                }
+
+                Token::VariationClose => match open {
+                    Some(_) => return Ok((moves, None)),
+                    None => return Err(unexpected(")")),
+                },
+
+                Token::TagOpen => return Err(unexpected("[")),
+                Token::TagClose => return Err(unexpected("]")),
+                Token::Str(value) => return Err(unexpected(&format!("\"{value}\""))),
+            }
+        }
+    }
+}
+
+/// Parses the value of a FEN tag, which often leaves out the move counters.
+fn parse_fen(fen: &str) -> Option<Board> {
+    match fen.split_whitespace().count() {
+        4 => Board::from_fen(&format!("{fen} 0 1")).ok(),
+        6 => Board::from_fen(fen).ok(),
+        _ => None,
+    }
+}
+
+/// Reads games one at a time, so files of any size can be processed. A
+/// malformed game is reported with the location of the problem and reading
+/// carries on with the next game.
+pub struct PgnReader<R: BufRead> {
+    reader: R,
+    line_number: usize,
+    /// A tag line read while looking for the end of the previous game.
+    pending_line: Option<String>,
+}
+
+impl PgnReader<BufReader<File>> {
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(Self::new(BufReader::new(File::open(path)?)))
+    }
+}
+
+impl<R: BufRead> PgnReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            line_number: 0,
+            pending_line: None,
+        }
+    }
+
+    fn next_line(&mut self) -> io::Result<Option<String>> {
+        if let Some(line) = self.pending_line.take() {
+            return Ok(Some(line));
+        }
+
+        let mut line = String::new();
+
+        if self.reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+
+        self.line_number += 1;
+        Ok(Some(line))
+    }
+
+    /// The text of the next game and the line it starts on, or `None` at the
+    /// end. A game ends after its result, or where a tag follows its movetext
+    /// when the result is missing.
+    fn read_game_text(&mut self) -> io::Result<Option<(String, usize)>> {
+        let mut text = String::new();
+        let mut first_line = 0;
+        let mut in_movetext = false;
+        let mut in_comment = false;
+        let mut finished = false;
+
+        while let Some(line) = self.next_line()? {
+            let line = line.trim_start_matches('\u{feff}');
+            let trimmed = line.trim();
+
+            if text.is_empty() {
+                if trimmed.is_empty() {
+                    continue;
+                }
+
+                first_line = self.line_number;
+            }
+
+            if finished && !trimmed.is_empty() {
+                self.pending_line = Some(line.to_string());
+                break;
+            }
+
+            if trimmed.starts_with('%') && !in_comment {
+                text.push_str(line);
+                continue;
+            }
+
+            if trimmed.starts_with('[') && !in_comment {
+                if in_movetext {
+                    self.pending_line = Some(line.to_string());
+                    break;
+                }
+            } else if !trimmed.is_empty() {
+                in_movetext = true;
+            }
+
+            let words = scan_line(trimmed, &mut in_comment);
+
+            // Tag values are quoted, so only movetext can end the game.
+            if in_movetext && words.iter().any(|word| parse_result(word).is_some()) {
+                finished = true;
+            }
+
+            text.push_str(line);
+        }
+
+        match text.is_empty() {
+            true => Ok(None),
+            false => Ok(Some((text, first_line))),
+        }
+    }
+}
+
+/// The words of `line` outside comments and strings. `in_comment` carries a
+/// brace comment over from one line to the next, so a tag-like line inside
+/// it does not start a new game.
+fn scan_line<'a>(line: &'a str, in_comment: &mut bool) -> Vec<&'a str> {
+    let mut words = Vec::new();
+    let mut word_start = None;
+    let mut in_string = false;
+    let mut escaped = false;
+
+    for (index, c) in line.char_indices() {
+        let is_word = !*in_comment
+            && !in_string
+            && !c.is_whitespace()
+            && !matches!(c, '{' | '}' | ';' | '"' | '(' | ')' | '[' | ']');
+
+        match (word_start, is_word) {
+            (None, true) => word_start = Some(index),
+            (Some(start), false) => {
+                words.push(&line[start..index]);
+                word_start = None;
+            }
+            _ => (),
+        }
+
+        if *in_comment {
+            *in_comment = c != '}';
+        } else if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => (),
+            }
+        } else {
+            match c {
+                '{' => *in_comment = true,
+                '"' => in_string = true,
+                ';' => break,
+                _ => (),
+            }
+        }
+    }
+
+    if let Some(start) = word_start {
+        words.push(&line[start..]);
+    }
+    words
+}
+
+impl<R: BufRead> Iterator for PgnReader<R> {
+    type Item = Result<PgnGame, PgnError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (text, first_line) = match self.read_game_text() {
+            Ok(Some(game_text)) => game_text,
+            Ok(None) => return None,
+            Err(err) => return Some(Err(err.into())),
+        };
+
+        let game = Lexer::new(&text, first_line)
+            .tokenize()
+            .and_then(|tokens| Parser { tokens, index: 0 }.parse_game());
+
+        Some(game)
+    }
+}
+
+#[derive(Debug)]
+pub enum PgnError {
+    Io(io::Error),
+    UnexpectedToken(Location, String),
+    UnterminatedComment(Location),
+    UnterminatedString(Location),
+    UnterminatedVariation(Location),
+    MalformedTag(Location),
+    InvalidFen(Location, String),
+    IllegalMove(Location, String, MoveError),
+}
+
+impl From<io::Error> for PgnError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl fmt::Display for PgnError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Io(err) => {
+                write!(f, "I/O error: {err}")
+            }
+
+            Self::UnexpectedToken(location, token) => {
+                write!(f, "Unexpected '{token}' at {location}")
+            }
+
+            Self::UnterminatedComment(location) => {
+                write!(f, "Comment opened at {location} is never closed")
+            }
+
+            Self::UnterminatedString(location) => {
+                write!(f, "String opened at {location} is never closed")
+            }
+
+            Self::UnterminatedVariation(location) => {
+                write!(f, "Variation opened at {location} is never closed")
+            }
+
+            Self::MalformedTag(location) => {
+                write!(f, "Malformed tag at {location}")
+            }
+
+            Self::InvalidFen(location, fen) => {
+                write!(f, "Invalid FEN tag at {location}: '{fen}'")
+            }
+
+            Self::IllegalMove(location, san, err) => {
+                write!(f, "Cannot play '{san}' at {location}: {err}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for PgnError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_games(text: &str) -> Vec<PgnGame> {
+        PgnReader::new(text.as_bytes())
+            .map(|game| game.unwrap())
+            .collect()
+    }
+
+    /// The moves of `line`, played from `board`, in SAN.
+    fn sans(board: &Board, line: &[PgnMove]) -> Vec<String> {
+        let mut board = board.clone();
+
+        line.iter()
+            .map(|pgn_move| {
+                let san = board.move_to_san(pgn_move.m);
+                board.make_move_unchecked(pgn_move.m);
+                san
+            })
+            .collect()
+    }
+
+    fn at(line: usize, column: usize) -> Location {
+        Location { line, column }
+    }
+
+    #[test]
+    fn braces_in_tag_values_do_not_open_comments() {
+        let games = read_games(
+            "[Event \"Open {2024\"]\n\
+             [Result \"1-0\"]\n\
+             \n\
+             1. e4 e5 1-0\n\
+             \n\
+             [Event \"Open }\\\" {\"]\n\
+             [Result \"0-1\"]\n\
+             \n\
+             1. d4 d5 0-1\n",
+        );
+
+        assert_eq!(games.len(), 2);
+        assert_eq!(games[0].tag("Event"), Some("Open {2024"));
+        assert_eq!(games[1].tag("Event"), Some("Open }\" {"));
+        assert_eq!(games[1].result, GameStatus::BlackWin);
+    }
+
+    #[test]
+    fn games_without_tags_end_at_their_result() {
+        let games = read_games(
+            "1. e4 e5 2. Nf3 1-0\n\
+             1. d4 {a comment [with brackets] 1-0} d5\n\
+             2. c4 1/2-1/2\n\
+             \n\
+             1. c4 *\n",
+        );
+
+        let results: Vec<GameStatus> = games.iter().map(|game| game.result).collect();
+
+        assert_eq!(
+            results,
+            [GameStatus::WhiteWin, GameStatus::Draw, GameStatus::Ongoing]
+        );
+        assert_eq!(games[1].moves.len(), 3);
+    }
+
+    #[test]
+    fn tags_inside_comments_do_not_start_games() {
+        let games = read_games(
+            "[Result \"*\"]\n\
+             \n\
+             1. e4 {\n\
+             [Event \"not a tag\"]\n\
+             } e5 *\n",
+        );
+
+        assert_eq!(games.len(), 1);
+        assert_eq!(games[0].moves.len(), 2);
+    }
+
+    #[test]
+    fn variations_nest() {
+        let game =
+            parse_game("1. e4 e5 (1... c5 2. Nf3 (2. c3 d5) 2... d6) (1... e6) 2. Nf3 *").unwrap();
+
+        let mut after_e4 = game.start.clone();
+        after_e4.make_move_unchecked(game.moves[0].m);
+
+        let mut after_c5 = after_e4.clone();
+        after_c5.make_move_unchecked(game.moves[1].variations[0][0].m);
+
+        assert_eq!(sans(&game.start, &game.moves), ["e4", "e5", "Nf3"]);
+        assert_eq!(game.moves[1].variations.len(), 2);
+        assert_eq!(
+            sans(&after_e4, &game.moves[1].variations[0]),
+            ["c5", "Nf3", "d6"]
+        );
+        assert_eq!(sans(&after_e4, &game.moves[1].variations[1]), ["e6"]);
+        assert_eq!(
+            sans(&after_c5, &game.moves[1].variations[0][1].variations[0]),
+            ["c3", "d5"]
+        );
+    }
+
+    #[test]
+    fn nags_and_suffixes_attach_to_their_move() {
+        let game = parse_game("1. e4! $14 e5?! 2. Nf3 !! $36 Nc6 $2 *").unwrap();
+
+        let nags: Vec<&[u8]> = game.moves.iter().map(|m| m.nags.as_slice()).collect();
+
+        assert_eq!(nags, [&[1, 14][..], &[6], &[3, 36], &[2]]);
+    }
+
+    #[test]
+    fn semicolon_comments_run_to_the_end_of_the_line() {
+        let games = read_games(
+            "[Result \"*\"]\n\
+             \n\
+             1. e4 ; best by test { not a brace comment 1-0\n\
+             e5 {open} *\n",
+        );
+
+        assert_eq!(games.len(), 1);
+        assert_eq!(
+            games[0].moves[0].comments,
+            ["best by test { not a brace comment 1-0"]
+        );
+        assert_eq!(games[0].moves[1].comments, ["open"]);
+        assert_eq!(games[0].result, GameStatus::Ongoing);
+    }
+
+    #[test]
+    fn errors_point_at_the_problem() {
+        let errors: Vec<PgnError> = PgnReader::new(
+            "[Event \"first\"]\n\
+             \n\
+             1. e4 e5 2. Ke3 *\n\
+             \n\
+             [Event \"second\"]\n\
+             \n\
+             1. d4 (1. c4 e5\n\
+             \n\
+             [Event \"third\"]\n\
+             \n\
+             1. e4 e5 ) *\n"
+                .as_bytes(),
+        )
+        .filter_map(Result::err)
+        .collect();
+
+        assert!(
+            matches!(
+                &errors[..],
+                [
+                    PgnError::IllegalMove(first, san, MoveError::IllegalMoveError),
+                    PgnError::UnterminatedVariation(second),
+                    PgnError::UnexpectedToken(third, token),
+                ] if *first == at(3, 13)
+                    && san == "Ke3"
+                    && *second == at(7, 7)
+                    && *third == at(11, 10)
+                    && token == ")"
+            ),
+            "{errors:?}"
+        );
+    }
+
+    #[test]
+    fn imports_en_passant_captures_and_underpromotions() {
+        let movetext = "1. e4 Nf6 2. e5 d5 3. exd6 e6 4. dxc7 Qd7 5. cxb8=N Rxb8";
+        let game = parse_game(&format!("{movetext} *")).unwrap();
+
+        let mut board = game.start.clone();
+        let mut sans = Vec::new();
+
+        for m in game.mainline() {
+            sans.push(board.move_to_san(m));
+            board.make_move_unchecked(m);
+        }
+
+        let expected: Vec<&str> = movetext
+            .split_whitespace()
+            .filter(|token| !token.ends_with('.'))
+            .collect();
+
+        assert_eq!(sans, expected);
+        assert_eq!(
+            board.to_fen(),
+            "1rb1kb1r/pp1q1ppp/4pn2/8/8/8/PPPP1PPP/RNBQKBNR w KQk - 0 6"
+        );
+    }
+}
@@ -0,0 +1,385 @@
+use std::fmt;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use super::{result_to_string, PgnGame, PgnMove, SEVEN_TAG_ROSTER};
+use crate::chess::board::chess_move::Move;
+use crate::chess::board::{Board, Color, GameStatus, START_FEN};
+use crate::chess::search::pv::Score;
+
+/// Movetext lines are wrapped to fit in this many columns.
+pub const MAX_LINE_LENGTH: usize = 80;
+
+/// The clock and evaluation after a move, written as a comment in the
+/// `[%eval ...]` and `[%clk ...]` form most GUIs display.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct MoveAnnotation {
+    /// The evaluation from white's point of view.
+    pub eval: Option<Score>,
+    /// Time left on the clock of the side that moved.
+    pub clock: Option<Duration>,
+}
+
+impl fmt::Display for MoveAnnotation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut commands = Vec::new();
+
+        match self.eval {
+            Some(Score::Centipawns(centipawns)) => {
+                commands.push(format!("[%eval {:.2}]", centipawns as f64 / 100.0))
+            }
+            Some(Score::Mate(moves)) => commands.push(format!("[%eval #{moves}]")),
+            None => (),
+        }
+
+        if let Some(clock) = self.clock {
+            let seconds = clock.as_secs();
+
+            commands.push(format!(
+                "[%clk {}:{:02}:{:02}]",
+                seconds / 3600,
+                seconds / 60 % 60,
+                seconds % 60
+            ));
+        }
+
+        write!(f, "{}", commands.join(" "))
+    }
+}
+
+/// `time` as a PGN date, such as `2023.01.31`, in UTC.
+pub fn pgn_date(time: SystemTime) -> String {
+    let days = time
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+        / 86400;
+
+    // Converts days since 1970-01-01 to a civil date, with years starting in
+    // March so that leap days come last.
+    let days = days as i64 + 719468;
+    let era = days / 146097;
+    let day_of_era = days - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + (month <= 2) as i64;
+
+    format!("{year:04}.{month:02}.{day:02}")
+}
+
+impl PgnGame {
+    /// The game of `moves` played from `start`, such as a board's `history`.
+    /// The result is taken from the final position.
+    pub fn from_moves(start: &Board, moves: &[Move]) -> Self {
+        let mut game = Self::new(start);
+        let mut board = start.clone();
+
+        for &m in moves {
+            board.make_move_unchecked(m);
+            game.moves.push(PgnMove::new(m));
+        }
+
+        game.set_result(board.get_game_status());
+        game
+    }
+
+    /// Sets the result and the `Result` tag.
+    pub fn set_result(&mut self, result: GameStatus) {
+        self.result = result;
+        self.set_tag("Result", result_to_string(result));
+    }
+
+    /// The game in PGN export format: the seven tag roster first, `SetUp` and
+    /// `FEN` for games that do not start from the initial position, then the
+    /// remaining tags and the movetext wrapped at `MAX_LINE_LENGTH` columns.
+    pub fn to_pgn(&self) -> String {
+        let mut pgn = String::new();
+        let start_fen = self.start.to_fen();
+
+        for name in SEVEN_TAG_ROSTER {
+            let value = match name {
+                "Result" => result_to_string(self.result),
+                "Date" => self.tag(name).unwrap_or("????.??.??"),
+                _ => self.tag(name).unwrap_or("?"),
+            };
+
+            push_tag(&mut pgn, name, value);
+        }
+
+        if start_fen != START_FEN {
+            push_tag(&mut pgn, "SetUp", "1");
+            push_tag(&mut pgn, "FEN", &start_fen);
+        }
+
+        for (name, value) in &self.tags {
+            if !SEVEN_TAG_ROSTER.contains(&name.as_str()) && name != "SetUp" && name != "FEN" {
+                push_tag(&mut pgn, name, value);
+            }
+        }
+
+        let mut tokens = Vec::new();
+        line_tokens(&mut tokens, &self.start, &self.moves);
+        tokens.push(result_to_string(self.result).to_string());
+
+        pgn.push('\n');
+        pgn.push_str(&wrap(&tokens));
+        pgn.push('\n');
+        pgn
+    }
+}
+
+fn push_tag(pgn: &mut String, name: &str, value: &str) {
+    let value = value.replace('\\', "\\\\").replace('"', "\\\"");
+    pgn.push_str(&format!("[{name} \"{value}\"]\n"));
+}
+
+/// Appends the movetext tokens of `moves`, played from `board`. Variations
+/// are written after the move they replace, with their parentheses attached
+/// to their first and last tokens.
+fn line_tokens(tokens: &mut Vec<String>, board: &Board, moves: &[PgnMove]) {
+    let mut board = board.clone();
+    let mut needs_number = true;
+
+    for pgn_move in moves {
+        for comment in &pgn_move.comments_before {
+            push_comment(tokens, comment);
+            needs_number = true;
+        }
+
+        match board.active_turn {
+            Color::White => tokens.push(format!("{}.", board.fullmove_number)),
+            Color::Black if needs_number => tokens.push(format!("{}...", board.fullmove_number)),
+            Color::Black => (),
+        }
+
+        tokens.push(board.move_to_san(pgn_move.m));
+        needs_number = false;
+
+        for nag in &pgn_move.nags {
+            tokens.push(format!("${nag}"));
+        }
+
+        for comment in &pgn_move.comments {
+            push_comment(tokens, comment);
+            needs_number = true;
+        }
+
+        for variation in pgn_move
+            .variations
+            .iter()
+            .filter(|variation| !variation.is_empty())
+        {
+            let first = tokens.len();
+            line_tokens(tokens, &board, variation);
+
+            tokens[first].insert(0, '(');
+            tokens.last_mut().unwrap().push(')');
+            needs_number = true;
+        }
+
+        board.make_move_unchecked(pgn_move.m);
+    }
+}
+
+/// Appends a comment one word at a time, so it can be wrapped. Comments
+/// cannot contain a closing brace, so any are dropped.
+fn push_comment(tokens: &mut Vec<String>, comment: &str) {
+    let comment = comment.replace('}', "");
+    let words: Vec<&str> = comment.split_whitespace().collect();
+
+    match words[..] {
+        [] => tokens.push(String::from("{}")),
+        [word] => tokens.push(format!("{{{word}}}")),
+        [first, ref middle @ .., last] => {
+            tokens.push(format!("{{{first}"));
+            tokens.extend(middle.iter().map(|word| word.to_string()));
+            tokens.push(format!("{last}}}"));
+        }
+    }
+}
+
+/// Joins tokens with spaces into lines of at most `MAX_LINE_LENGTH` columns,
+/// except for single tokens that are longer.
+fn wrap(tokens: &[String]) -> String {
+    let mut text = String::new();
+    let mut line_length = 0;
+
+    for token in tokens {
+        if line_length > 0 && line_length + 1 + token.len() > MAX_LINE_LENGTH {
+            text.push('\n');
+            line_length = 0;
+        } else if line_length > 0 {
+            text.push(' ');
+            line_length += 1;
+        }
+
+        text.push_str(token);
+        line_length += token.len();
+    }
+
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::parse_game;
+    use super::*;
+
+    fn day(days: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(days * 86400)
+    }
+
+    /// The movetext of `game`, without the tags.
+    fn movetext(game: &PgnGame) -> String {
+        let pgn = game.to_pgn();
+        pgn.split_once("\n\n").unwrap().1.to_string()
+    }
+
+    #[test]
+    fn annotations_use_eval_and_clk_commands() {
+        for (eval, clock, text) in [
+            (
+                Some(Score::Centipawns(35)),
+                Some(Duration::from_secs(3723)),
+                "[%eval 0.35] [%clk 1:02:03]",
+            ),
+            (Some(Score::Centipawns(-120)), None, "[%eval -1.20]"),
+            (Some(Score::Centipawns(0)), None, "[%eval 0.00]"),
+            (Some(Score::Mate(2)), None, "[%eval #2]"),
+            (Some(Score::Mate(-3)), None, "[%eval #-3]"),
+            // Partial seconds are cut off rather than rounded.
+            (None, Some(Duration::from_millis(59_999)), "[%clk 0:00:59]"),
+            (None, Some(Duration::from_secs(36_000)), "[%clk 10:00:00]"),
+            (None, None, ""),
+        ] {
+            assert_eq!(MoveAnnotation { eval, clock }.to_string(), text);
+        }
+    }
+
+    #[test]
+    fn dates_count_leap_days() {
+        for (days, date) in [
+            (0, "1970.01.01"),
+            (1095, "1972.12.31"),
+            (11016, "2000.02.29"),
+            (11017, "2000.03.01"),
+            (19416, "2023.02.28"),
+            (19417, "2023.03.01"),
+            (19782, "2024.02.29"),
+            // Centuries are only leap years every 400 years.
+            (47540, "2100.02.28"),
+            (47541, "2100.03.01"),
+            (157113, "2400.02.29"),
+        ] {
+            assert_eq!(pgn_date(day(days)), date, "{days}");
+        }
+
+        // Part of a day does not move the date on.
+        assert_eq!(
+            pgn_date(day(11016) + Duration::from_secs(86399)),
+            "2000.02.29"
+        );
+
+        // Times before the epoch are clamped to it.
+        assert_eq!(pgn_date(UNIX_EPOCH - Duration::from_secs(1)), "1970.01.01");
+    }
+
+    #[test]
+    fn black_moves_are_numbered_after_comments_and_variations() {
+        let game = parse_game(
+            "1. e4 {best by test} e5 2. Nf3 (2. Bc4 Nf6) Nc6 3. Bb5 $1 a6 \
+             (3... Nf6 4. O-O) 4. Ba4 *",
+        )
+        .unwrap();
+
+        assert_eq!(
+            movetext(&game),
+            "1. e4 {best by test} 1... e5 2. Nf3 (2. Bc4 Nf6) 2... Nc6 3. Bb5 $1 a6 (3... Nf6\n\
+             4. O-O) 4. Ba4 *\n"
+        );
+    }
+
+    #[test]
+    fn movetext_wraps_at_80_columns() {
+        let mut game = parse_game(
+            "1. d4 Nf6 2. c4 e6 3. Nc3 Bb4 4. e3 O-O 5. Bd3 d5 6. Nf3 c5 7. O-O Nc6 \
+             8. a3 Bxc3 9. bxc3 dxc4 10. Bxc4 Qc7 11. Bd3 e5 12. Qc2 Re8 *",
+        )
+        .unwrap();
+
+        game.moves[0].comments.push("a".repeat(90));
+        game.moves[5]
+            .comments
+            .push("the most common answer to the Nimzo-Indian and still a main line today".into());
+
+        let movetext = movetext(&game);
+
+        // A comment word too long for any line gets a line of its own.
+        assert_eq!(
+            movetext,
+            format!(
+                "1. d4\n\
+                 {{{}}}\n\
+                 1... Nf6 2. c4 e6 3. Nc3 Bb4 {{the most common answer to the Nimzo-Indian and\n\
+                 still a main line today}} 4. e3 O-O 5. Bd3 d5 6. Nf3 c5 7. O-O Nc6 8. a3 Bxc3 9.\n\
+                 bxc3 dxc4 10. Bxc4 Qc7 11. Bd3 e5 12. Qc2 Re8 *\n",
+                "a".repeat(90)
+            )
+        );
+
+        for line in movetext.lines().filter(|line| line.contains(' ')) {
+            assert!(line.len() <= MAX_LINE_LENGTH, "{line}");
+        }
+    }
+
+    #[test]
+    fn exports_a_game() {
+        let mut game = parse_game(
+            "[Event \"Club \\\"Open\\\"\"]\n\
+             [Site \"Leiden\"]\n\
+             [Round \"3\"]\n\
+             [White \"Euwe\"]\n\
+             [Black \"Alekhine\"]\n\
+             [Result \"1-0\"]\n\
+             [Annotator \"Staff\"]\n\
+             [SetUp \"1\"]\n\
+             [FEN \"4k3/8/8/8/8/8/4P3/4K2R w K - 0 1\"]\n\
+             \n\
+             1. O-O Kd7 {the king runs} 2. e4 (2. Rf7+ Ke6) 2... Kc6 1-0\n",
+        )
+        .unwrap();
+
+        game.set_tag("Date", &pgn_date(day(19782)));
+        game.moves[0].comments.push(
+            MoveAnnotation {
+                eval: Some(Score::Centipawns(512)),
+                clock: Some(Duration::from_secs(299)),
+            }
+            .to_string(),
+        );
+
+        assert_eq!(
+            game.to_pgn(),
+            "[Event \"Club \\\"Open\\\"\"]\n\
+             [Site \"Leiden\"]\n\
+             [Date \"2024.02.29\"]\n\
+             [Round \"3\"]\n\
+             [White \"Euwe\"]\n\
+             [Black \"Alekhine\"]\n\
+             [Result \"1-0\"]\n\
+             [SetUp \"1\"]\n\
+             [FEN \"4k3/8/8/8/8/8/4P3/4K2R w K - 0 1\"]\n\
+             [Annotator \"Staff\"]\n\
+             \n\
+             1. O-O {[%eval 5.12] [%clk 0:04:59]} 1... Kd7 {the king runs} 2. e4 (2. Rf7+\n\
+             Ke6) 2... Kc6 1-0\n"
+        );
+    }
+}
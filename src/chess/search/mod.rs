@@ -0,0 +1,1136 @@
+use std::sync::{
+    atomic::{AtomicBool, AtomicU64, Ordering},
+    Arc,
+};
+use std::thread;
+use std::time::Duration;
+
+use self::pv::PvLine;
+use self::skill::Skill;
+use self::time::TimeManager;
+use self::tt::{encode_move, Bound, TranspositionTable, TtEntry};
+use super::board::{chess_move::Move, Board, Color, PieceKind};
+use super::book::{BookSelection, PolyglotBook};
+use super::evaluation::{self, nnue::Accumulator, piece_value, Evaluator, PAWN_VALUE, QUEEN_VALUE};
+use super::options::EngineOptions;
+use super::syzygy::{Syzygy, WdlScore, WIN_RANK};
+use super::tablebase::{Dtm, Tablebase, Wdl};
+
+pub mod pv;
+pub mod skill;
+pub mod time;
+pub mod tt;
+
+pub const MAX_PLY: usize = 64;
+pub const INFINITY: i32 = 32000;
+pub const MATE_SCORE: i32 = 30000;
+
+/// Scores beyond this bound are mate scores.
+pub const MATE_BOUND: i32 = MATE_SCORE - MAX_PLY as i32;
+
+/// Score of a position the tablebases show won, less the ply, when the mate is
+/// too far away for a mate score.
+const TABLEBASE_WIN: i32 = MATE_BOUND - MAX_PLY as i32;
+
+const DEFAULT_DEPTH: u8 = 4;
+
+/// How many nodes are searched between two looks at the clock.
+const TIME_CHECK_INTERVAL: u64 = 256;
+
+const ASPIRATION_WINDOW: i32 = 25;
+const REVERSE_FUTILITY_MARGIN: i32 = 80;
+const RAZOR_MARGIN: i32 = 300;
+const FUTILITY_MARGINS: [i32; 4] = [0, 100, 200, 300];
+const DELTA_MARGIN: i32 = 200;
+
+/// Search limits as given by the UCI `go` command. Times are in milliseconds.
+#[derive(Debug, Clone, Default)]
+pub struct SearchLimits {
+    pub depth: Option<u8>,
+    pub nodes: Option<u64>,
+    pub movetime: Option<u64>,
+    pub wtime: Option<u64>,
+    pub btime: Option<u64>,
+    pub winc: Option<u64>,
+    pub binc: Option<u64>,
+    pub movestogo: Option<u64>,
+}
+
+impl SearchLimits {
+    fn has_clock(&self) -> bool {
+        self.movetime.is_some() || self.wtime.is_some() || self.btime.is_some()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SearchResult {
+    pub best_move: Option<Move>,
+    pub score: i32,
+    pub depth: u8,
+    pub nodes: u64,
+    pub time: Duration,
+    pub pv: Vec<Move>,
+    /// Every line of a MultiPV search, best first. Holds the main line otherwise.
+    pub lines: Vec<PvLine>,
+}
+
+pub struct Searcher {
+    options: EngineOptions,
+    thread_id: usize,
+    tt: Arc<TranspositionTable>,
+    nodes: u64,
+    shared_nodes: Arc<AtomicU64>,
+    node_limit: Option<u64>,
+    time_manager: TimeManager,
+    stop: Arc<AtomicBool>,
+    stopped: bool,
+    root_best_move: Option<Move>,
+    /// Moves searched at the root: every legal move unless the Syzygy tables
+    /// rule some out.
+    root_moves: Vec<Move>,
+    excluded_root_moves: Vec<Move>,
+    killers: [[Option<Move>; 2]; MAX_PLY],
+    pv: Vec<Vec<Move>>,
+    evaluator: Evaluator,
+    accumulators: Vec<Option<Accumulator>>,
+    skill: Skill,
+    noise_seed: u64,
+    book: Option<Arc<PolyglotBook>>,
+    tablebase: Option<Arc<Tablebase>>,
+    syzygy: Option<Arc<Syzygy>>,
+    /// Whether to probe the Syzygy tables below the root. Off once the root
+    /// moves are ranked by DTZ.
+    syzygy_in_search: bool,
+    /// Files named in the options that failed to load, each left out of play.
+    warnings: Vec<String>,
+    /// Hashes of the positions played before the root, oldest first.
+    game_history: Vec<u64>,
+    /// The game history followed by the positions on the current search path.
+    position_hashes: Vec<u64>,
+    /// Positions before this index in `position_hashes` lie behind a null move
+    /// and cannot be repeated.
+    repetition_floor: usize,
+}
+
+impl Searcher {
+    pub fn new(options: EngineOptions) -> Self {
+        let tt = Arc::new(TranspositionTable::new(options.hash_size_mb));
+        let mut warnings = Vec::new();
+
+        let evaluator = Evaluator::from_options(&options).unwrap_or_else(|err| {
+            warnings.push(format!("Falling back to the default evaluation: {err}"));
+            Evaluator::default()
+        });
+
+        let skill = Skill::from_options(&options);
+
+        let book = options.book_file.as_ref().and_then(|book_file| {
+            PolyglotBook::load(book_file)
+                .map_err(|err| warnings.push(format!("Playing without an opening book: {err}")))
+                .ok()
+                .map(Arc::new)
+        });
+
+        let tablebase = options.tablebase_path.as_ref().and_then(|path| {
+            Tablebase::load_dir(path)
+                .map_err(|err| warnings.push(format!("Playing without tablebases: {err}")))
+                .ok()
+                .map(Arc::new)
+        });
+
+        let syzygy = options.syzygy_path.as_ref().and_then(|path| {
+            Syzygy::load(path)
+                .map_err(|err| warnings.push(format!("Playing without Syzygy tablebases: {err}")))
+                .ok()
+                .map(Arc::new)
+        });
+
+        Self {
+            options,
+            thread_id: 0,
+            tt,
+            nodes: 0,
+            shared_nodes: Arc::new(AtomicU64::new(0)),
+            node_limit: None,
+            time_manager: TimeManager::new(&SearchLimits::default(), Color::White),
+            stop: Arc::new(AtomicBool::new(false)),
+            stopped: false,
+            root_best_move: None,
+            root_moves: Vec::new(),
+            excluded_root_moves: Vec::new(),
+            killers: [[None; 2]; MAX_PLY],
+            pv: vec![Vec::new(); MAX_PLY + 1],
+            evaluator,
+            accumulators: vec![None; MAX_PLY + 1],
+            skill,
+            noise_seed: 0,
+            book,
+            tablebase,
+            syzygy,
+            syzygy_in_search: false,
+            warnings,
+            game_history: Vec::new(),
+            position_hashes: Vec::new(),
+            repetition_floor: 0,
+        }
+    }
+
+    /// Problems met while loading the files named in the options. The searcher
+    /// plays on without them; it is up to the caller to report these.
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+
+    /// Hashes of the positions played before the one to search, oldest first.
+    /// The search scores a return to any of them as a draw.
+    pub fn set_game_history(&mut self, hashes: &[u64]) {
+        self.game_history = hashes.to_vec();
+    }
+
+    /// Flag that aborts the running search when set from another thread.
+    pub fn stop_flag(&self) -> Arc<AtomicBool> {
+        Arc::clone(&self.stop)
+    }
+
+    pub fn clear_hash(&self) {
+        self.tt.clear();
+    }
+
+    /// A searcher for a helper thread sharing this searcher's hash table, stop
+    /// flag and node counter.
+    fn helper(&self, thread_id: usize) -> Self {
+        Self {
+            options: self.options.clone(),
+            thread_id,
+            tt: Arc::clone(&self.tt),
+            nodes: 0,
+            shared_nodes: Arc::clone(&self.shared_nodes),
+            node_limit: self.node_limit,
+            time_manager: self.time_manager.clone(),
+            stop: Arc::clone(&self.stop),
+            stopped: false,
+            root_best_move: None,
+            root_moves: self.root_moves.clone(),
+            excluded_root_moves: Vec::new(),
+            killers: [[None; 2]; MAX_PLY],
+            pv: vec![Vec::new(); MAX_PLY + 1],
+            evaluator: self.evaluator.clone(),
+            accumulators: vec![None; MAX_PLY + 1],
+            skill: self.skill,
+            noise_seed: self.noise_seed,
+            book: None,
+            tablebase: self.tablebase.clone(),
+            syzygy: self.syzygy.clone(),
+            syzygy_in_search: self.syzygy_in_search,
+            warnings: Vec::new(),
+            game_history: self.game_history.clone(),
+            position_hashes: Vec::new(),
+            repetition_floor: 0,
+        }
+    }
+
+    /// Searches `board` within `limits` on `Threads` threads.
+    ///
+    /// Helper threads run their own iterative deepening on the same position and
+    /// only communicate through the hash table. The result always comes from the
+    /// main thread, so a single thread searches deterministically. Positions
+    /// in the tablebases are not searched; the move with the best distance to
+    /// mate is played. Positions in the Syzygy tables are searched among the
+    /// moves that keep the best result.
+    pub fn search(&mut self, board: &Board, limits: &SearchLimits) -> SearchResult {
+        if let Some(m) = self.book_move(board) {
+            return SearchResult {
+                best_move: Some(m),
+                score: 0,
+                depth: 0,
+                nodes: 0,
+                time: Duration::ZERO,
+                pv: vec![m],
+                lines: Vec::new(),
+            };
+        }
+
+        if let Some((m, dtm)) = self.tablebase.as_ref().and_then(|tb| tb.best_move(board)) {
+            return SearchResult {
+                best_move: Some(m),
+                score: tablebase_score(dtm),
+                depth: 0,
+                nodes: 0,
+                time: Duration::ZERO,
+                pv: vec![m],
+                lines: Vec::new(),
+            };
+        }
+
+        let syzygy_score = self.rank_root_moves(board);
+
+        self.node_limit = limits.nodes;
+        self.time_manager = TimeManager::new(limits, board.active_turn);
+        self.shared_nodes.store(0, Ordering::Relaxed);
+        self.stop.store(false, Ordering::Relaxed);
+
+        let mut max_depth = match limits.depth {
+            Some(depth) => depth.min(MAX_PLY as u8 - 1),
+            None if limits.nodes.is_some() || limits.has_clock() => MAX_PLY as u8 - 1,
+            None => DEFAULT_DEPTH,
+        };
+
+        if self.skill.is_enabled() {
+            max_depth = max_depth.min(self.skill.depth_limit());
+            self.node_limit = Some(limits.nodes.map_or(self.skill.node_limit(), |nodes| {
+                nodes.min(self.skill.node_limit())
+            }));
+            self.noise_seed = rand::random();
+        }
+
+        let mut result = if self.options.threads <= 1 {
+            self.iterative_deepening(board, max_depth)
+        } else {
+            self.parallel_search(board, max_depth)
+        };
+
+        // The search rarely sees far enough to tell a won ending from a drawn
+        // one, so the tables have the last word unless it found a mate.
+        if let Some(score) = syzygy_score {
+            for line in &mut result.lines {
+                if line.score.abs() < MATE_BOUND {
+                    line.score = score;
+                }
+            }
+
+            if result.score.abs() < MATE_BOUND {
+                result.score = score;
+            }
+        }
+
+        if self.skill.is_enabled() {
+            if let Some(line) = self.skill.pick_line(&result.lines) {
+                result.best_move = line.moves.first().copied().or(result.best_move);
+                result.score = line.score;
+                result.pv = line.moves.clone();
+            }
+        }
+        result
+    }
+
+    /// A move from the opening book while the game is within `BookDepth` moves.
+    fn book_move(&self, board: &Board) -> Option<Move> {
+        let book = self.book.as_ref()?;
+
+        if board.fullmove_number as usize > self.options.book_depth {
+            return None;
+        }
+
+        let selection = if self.options.book_best_move {
+            BookSelection::BestMove
+        } else {
+            BookSelection::WeightedRandom
+        };
+
+        book.choose_move(board, selection)
+    }
+
+    /// Limits the root moves to those the Syzygy tables rank best and returns
+    /// the score of the position from the tables.
+    fn rank_root_moves(&mut self, board: &Board) -> Option<i32> {
+        self.root_moves = board.get_legal_moves();
+        self.syzygy_in_search = self.syzygy.is_some();
+
+        let ranking = self.syzygy.as_ref()?.rank_root_moves(board)?;
+        let best_rank = ranking.moves[0].1;
+
+        self.root_moves = ranking
+            .moves
+            .iter()
+            .filter(|&&(_, rank)| rank == best_rank)
+            .map(|&(m, _)| m)
+            .collect();
+
+        let score = syzygy_score(best_rank, self.options.syzygy_50_move_rule);
+
+        // Moves ranked by DTZ already make progress, and probing WDL below
+        // them would only make every winning move look the same.
+        if ranking.by_dtz || score <= 0 {
+            self.syzygy_in_search = false;
+        }
+
+        Some(score)
+    }
+
+    fn parallel_search(&mut self, board: &Board, max_depth: u8) -> SearchResult {
+        thread::scope(|scope| {
+            let helpers: Vec<_> = (1..self.options.threads)
+                .map(|thread_id| {
+                    let mut helper = self.helper(thread_id);
+                    scope.spawn(move || helper.iterative_deepening(board, max_depth))
+                })
+                .collect();
+
+            let mut result = self.iterative_deepening(board, max_depth);
+
+            self.stop.store(true, Ordering::Relaxed);
+
+            for helper in helpers {
+                helper.join().expect("Search thread panicked");
+            }
+
+            result.nodes = self.shared_nodes.load(Ordering::Relaxed);
+            result
+        })
+    }
+
+    fn iterative_deepening(&mut self, board: &Board, max_depth: u8) -> SearchResult {
+        self.nodes = 0;
+        self.stopped = false;
+        self.root_best_move = None;
+        self.killers = [[None; 2]; MAX_PLY];
+        self.position_hashes = self.game_history.clone();
+        self.repetition_floor = 0;
+
+        if let Evaluator::Nnue(network) = &self.evaluator {
+            self.accumulators[0] = Some(network.refresh(board));
+        }
+
+        let mut result = SearchResult {
+            best_move: self.root_moves.first().copied(),
+            score: 0,
+            depth: 0,
+            nodes: 0,
+            time: Duration::ZERO,
+            pv: Vec::new(),
+            lines: Vec::new(),
+        };
+
+        // Helpers only ever look for the best move.
+        let multi_pv = if self.thread_id == 0 {
+            self.options
+                .multi_pv
+                .max(self.skill.multi_pv())
+                .clamp(1, self.root_moves.len().max(1))
+        } else {
+            1
+        };
+
+        // Odd helpers skip the first iteration so threads spread across depths.
+        let first_depth = 1 + (self.thread_id % 2) as u8;
+
+        for depth in first_depth..=max_depth {
+            let lines = self.search_lines(board, depth, multi_pv, &result.lines);
+
+            if self.stopped || lines.is_empty() {
+                break;
+            }
+
+            let best_move = lines[0].moves.first().copied().or(result.best_move);
+            let score = lines[0].score;
+
+            if depth > 1 {
+                self.time_manager
+                    .update(best_move != result.best_move, result.score, score);
+            }
+
+            result.best_move = best_move;
+            result.score = score;
+            result.depth = depth;
+            result.pv = lines[0].moves.clone();
+            result.lines = lines;
+
+            let mate_found = multi_pv == 1 && score.abs() >= MATE_BOUND;
+
+            if mate_found || self.time_manager.soft_limit_reached() {
+                break;
+            }
+        }
+
+        result.nodes = self.shared_nodes.load(Ordering::Relaxed);
+        result.time = self.time_manager.elapsed();
+        result
+    }
+
+    /// Finds the `multi_pv` best root moves at `depth`, searching each line with
+    /// the moves of the lines before it excluded from the root.
+    fn search_lines(
+        &mut self,
+        board: &Board,
+        depth: u8,
+        multi_pv: usize,
+        previous_lines: &[PvLine],
+    ) -> Vec<PvLine> {
+        let mut lines: Vec<PvLine> = Vec::with_capacity(multi_pv);
+
+        self.excluded_root_moves.clear();
+
+        for pv_index in 0..multi_pv {
+            let previous_line = previous_lines.get(pv_index);
+
+            self.root_best_move = previous_line.and_then(|line| line.moves.first().copied());
+
+            let previous_score = previous_line.map_or(0, |line| line.score);
+            let score = self.aspiration_search(board, depth as i32, previous_score);
+
+            if self.stopped {
+                return Vec::new();
+            }
+
+            let moves = self.pv[0].clone();
+
+            match moves.first() {
+                Some(&m) => self.excluded_root_moves.push(m),
+                None => break,
+            }
+
+            lines.push(PvLine {
+                multipv: pv_index + 1,
+                depth,
+                score,
+                moves,
+            });
+        }
+
+        self.excluded_root_moves.clear();
+
+        lines.sort_by_key(|line| -line.score);
+
+        for (pv_index, line) in lines.iter_mut().enumerate() {
+            line.multipv = pv_index + 1;
+        }
+        lines
+    }
+
+    fn aspiration_search(&mut self, board: &Board, depth: i32, previous_score: i32) -> i32 {
+        if !self.options.aspiration_windows || depth < 4 {
+            return self.negamax(board, depth, -INFINITY, INFINITY, 0, true);
+        }
+
+        let mut delta = ASPIRATION_WINDOW;
+        let mut alpha = (previous_score - delta).max(-INFINITY);
+        let mut beta = (previous_score + delta).min(INFINITY);
+
+        loop {
+            let score = self.negamax(board, depth, alpha, beta, 0, true);
+
+            if self.stopped {
+                return score;
+            }
+
+            if score <= alpha {
+                alpha = (score - delta).max(-INFINITY);
+            } else if score >= beta {
+                beta = (score + delta).min(INFINITY);
+            } else {
+                return score;
+            }
+
+            delta *= 2;
+        }
+    }
+
+    fn negamax(
+        &mut self,
+        board: &Board,
+        depth: i32,
+        mut alpha: i32,
+        beta: i32,
+        ply: usize,
+        allow_null: bool,
+    ) -> i32 {
+        self.pv[ply].clear();
+
+        if self.should_stop() {
+            return 0;
+        }
+
+        let in_check = board.is_in_check();
+
+        let depth = if in_check && self.options.check_extensions {
+            depth + 1
+        } else {
+            depth
+        };
+
+        if depth <= 0 {
+            return self.quiescence(board, alpha, beta, ply);
+        }
+
+        self.count_node();
+
+        if ply > 0 && board.halfmove_clock >= 100 {
+            return 0;
+        }
+
+        if ply >= MAX_PLY - 1 {
+            return self.evaluate(board, ply);
+        }
+
+        let hash = board.hash();
+
+        self.position_hashes.truncate(self.game_history.len() + ply);
+
+        if ply > 0 && self.is_repetition(hash) {
+            return 0;
+        }
+
+        self.position_hashes.push(hash);
+
+        if ply > 0 {
+            if let Some(wdl) = self.tablebase.as_ref().and_then(|tb| tb.probe_wdl(board)) {
+                return match wdl {
+                    Wdl::Win => TABLEBASE_WIN - ply as i32,
+                    Wdl::Draw => 0,
+                    Wdl::Loss => -TABLEBASE_WIN + ply as i32,
+                };
+            }
+        }
+
+        // Only positions right after a capture or pawn move are probed, as the
+        // 50-move counter decides whether a long win still counts.
+        if ply > 0 && self.syzygy_in_search && board.halfmove_clock == 0 {
+            if let Some(wdl) = self.syzygy.as_ref().and_then(|tb| tb.probe_wdl(board)) {
+                let fifty_move_rule = self.options.syzygy_50_move_rule;
+
+                return match wdl {
+                    WdlScore::Win => TABLEBASE_WIN - ply as i32,
+                    WdlScore::CursedWin if !fifty_move_rule => TABLEBASE_WIN - ply as i32,
+                    WdlScore::CursedWin => 2,
+                    WdlScore::Draw => 0,
+                    WdlScore::BlessedLoss if !fifty_move_rule => -TABLEBASE_WIN + ply as i32,
+                    WdlScore::BlessedLoss => -2,
+                    WdlScore::Loss => -TABLEBASE_WIN + ply as i32,
+                };
+            }
+        }
+
+        let pv_node = beta - alpha > 1;
+        let tt_entry = self.tt.probe(hash, ply);
+
+        if let Some(entry) = tt_entry {
+            let cutoff = match entry.bound {
+                Bound::Exact => true,
+                Bound::Lower => entry.score >= beta,
+                Bound::Upper => entry.score <= alpha,
+            };
+
+            if !pv_node && ply > 0 && entry.depth >= depth && cutoff {
+                return entry.score;
+            }
+        }
+
+        let mut moves = board.get_legal_moves();
+
+        if moves.is_empty() {
+            return if in_check {
+                -MATE_SCORE + ply as i32
+            } else {
+                0
+            };
+        }
+
+        let static_eval = self.evaluate(board, ply);
+
+        if !pv_node && !in_check && ply > 0 {
+            if self.options.reverse_futility_pruning
+                && depth <= 6
+                && beta.abs() < MATE_BOUND
+                && static_eval - REVERSE_FUTILITY_MARGIN * depth >= beta
+            {
+                return static_eval;
+            }
+
+            if self.options.razoring && depth <= 2 && static_eval + RAZOR_MARGIN * depth < alpha {
+                let score = self.quiescence(board, alpha, beta, ply);
+
+                if score < alpha {
+                    return score;
+                }
+            }
+
+            // Zugzwang is only a real danger when the side to move has nothing but
+            // pawns left, so null moves are skipped in those positions.
+            if self.options.null_move_pruning
+                && allow_null
+                && depth >= 3
+                && static_eval >= beta
+                && evaluation::non_pawn_material(board, board.active_turn) > 0
+            {
+                let reduction = 2 + depth / 4;
+
+                let mut child = board.clone();
+                child.make_null_move();
+
+                self.accumulators[ply + 1] = self.accumulators[ply].clone();
+
+                let floor =
+                    std::mem::replace(&mut self.repetition_floor, self.position_hashes.len());
+
+                let score = -self.negamax(
+                    &child,
+                    depth - 1 - reduction,
+                    -beta,
+                    -beta + 1,
+                    ply + 1,
+                    false,
+                );
+
+                self.repetition_floor = floor;
+
+                if self.stopped {
+                    return 0;
+                }
+
+                if score >= beta {
+                    return if score >= MATE_BOUND { beta } else { score };
+                }
+            }
+        }
+
+        let futility_pruning = self.options.futility_pruning
+            && !pv_node
+            && !in_check
+            && depth < FUTILITY_MARGINS.len() as i32
+            && alpha.abs() < MATE_BOUND
+            && static_eval + FUTILITY_MARGINS[depth as usize] <= alpha;
+
+        self.order_moves(&mut moves, ply, tt_entry.map(|entry| entry.move_code));
+
+        let original_alpha = alpha;
+        let mut best_score = -INFINITY;
+        let mut best_move = None;
+        let mut searched = 0;
+
+        for m in moves {
+            if ply == 0 && (!self.root_moves.contains(&m) || self.excluded_root_moves.contains(&m))
+            {
+                continue;
+            }
+
+            let quiet = !is_capture(m) && !is_promotion(m);
+
+            let mut child = board.clone();
+            child.make_move_unchecked(m);
+            self.update_accumulator(ply, board, m, &child);
+
+            let gives_check = child.is_in_check();
+
+            if futility_pruning && searched > 0 && quiet && !gives_check {
+                continue;
+            }
+
+            let score = if searched == 0 {
+                -self.negamax(&child, depth - 1, -beta, -alpha, ply + 1, true)
+            } else {
+                let reduction = if self.options.late_move_reductions
+                    && depth >= 3
+                    && searched >= 3
+                    && quiet
+                    && !in_check
+                    && !gives_check
+                {
+                    late_move_reduction(depth, searched)
+                } else {
+                    0
+                };
+
+                let mut score = -self.negamax(
+                    &child,
+                    depth - 1 - reduction,
+                    -alpha - 1,
+                    -alpha,
+                    ply + 1,
+                    true,
+                );
+
+                if score > alpha && reduction > 0 {
+                    score = -self.negamax(&child, depth - 1, -alpha - 1, -alpha, ply + 1, true);
+                }
+
+                if score > alpha && score < beta {
+                    score = -self.negamax(&child, depth - 1, -beta, -alpha, ply + 1, true);
+                }
+                score
+            };
+
+            searched += 1;
+
+            if self.stopped {
+                return 0;
+            }
+
+            if score > best_score {
+                best_score = score;
+                best_move = Some(m);
+
+                if score > alpha {
+                    alpha = score;
+                    self.update_pv(ply, m);
+
+                    if score >= beta {
+                        if quiet {
+                            self.store_killer(ply, m);
+                        }
+                        break;
+                    }
+                }
+            }
+        }
+
+        let bound = if best_score >= beta {
+            Bound::Lower
+        } else if best_score > original_alpha {
+            Bound::Exact
+        } else {
+            Bound::Upper
+        };
+
+        // A root search with excluded moves does not know the real best move.
+        if ply == 0 && !self.excluded_root_moves.is_empty() {
+            return best_score;
+        }
+
+        self.tt.store(
+            hash,
+            ply,
+            TtEntry {
+                move_code: best_move.map_or(0, encode_move),
+                score: best_score,
+                depth,
+                bound,
+            },
+        );
+
+        best_score
+    }
+
+    fn quiescence(&mut self, board: &Board, mut alpha: i32, beta: i32, ply: usize) -> i32 {
+        self.pv[ply].clear();
+
+        if self.should_stop() {
+            return 0;
+        }
+
+        self.count_node();
+
+        if ply >= MAX_PLY - 1 {
+            return self.evaluate(board, ply);
+        }
+
+        let in_check = board.is_in_check();
+
+        // A side in check may not stand pat. It searches every evasion instead,
+        // and is mated if there is none.
+        let stand_pat = if in_check {
+            -MATE_SCORE + ply as i32
+        } else {
+            self.evaluate(board, ply)
+        };
+
+        if stand_pat >= beta {
+            return stand_pat;
+        }
+
+        alpha = alpha.max(stand_pat);
+
+        let mut moves: Vec<Move> = board
+            .get_legal_moves()
+            .into_iter()
+            .filter(|&m| in_check || is_capture(m))
+            .collect();
+
+        moves.sort_by_key(|&m| -mvv_lva(m));
+
+        for m in moves {
+            // Skip captures that cannot raise the score even if the victim came for free.
+            if !in_check
+                && (stand_pat + captured_value(m) + DELTA_MARGIN < alpha || !board.see_ge(m, 0))
+            {
+                continue;
+            }
+
+            let mut child = board.clone();
+            child.make_move_unchecked(m);
+            self.update_accumulator(ply, board, m, &child);
+
+            let score = -self.quiescence(&child, -beta, -alpha, ply + 1);
+
+            if self.stopped {
+                return 0;
+            }
+
+            if score >= beta {
+                return score;
+            }
+
+            if score > alpha {
+                alpha = score;
+                self.update_pv(ply, m);
+            }
+        }
+
+        alpha
+    }
+
+    /// Whether `hash` already occurred with the same side to move, in the game
+    /// or on the search path. Positions before an irreversible move cannot
+    /// match, so they need no special care.
+    fn is_repetition(&self, hash: u64) -> bool {
+        self.position_hashes[self.repetition_floor..]
+            .iter()
+            .rev()
+            .skip(1)
+            .step_by(2)
+            .any(|&seen| seen == hash)
+    }
+
+    fn should_stop(&mut self) -> bool {
+        if self.stopped {
+            return true;
+        }
+
+        if self.stop.load(Ordering::Relaxed) {
+            self.stopped = true;
+        }
+
+        if let Some(node_limit) = self.node_limit {
+            if self.shared_nodes.load(Ordering::Relaxed) >= node_limit {
+                self.stopped = true;
+            }
+        }
+
+        if self.nodes.is_multiple_of(TIME_CHECK_INTERVAL) && self.time_manager.hard_limit_reached()
+        {
+            self.stopped = true;
+        }
+        self.stopped
+    }
+
+    fn evaluate(&self, board: &Board, ply: usize) -> i32 {
+        let score = self
+            .evaluator
+            .evaluate(board, self.accumulators[ply].as_ref());
+
+        if self.skill.is_enabled() {
+            score + self.skill.noise(board.hash(), self.noise_seed)
+        } else {
+            score
+        }
+    }
+
+    /// Derives the accumulator of `child`, reached by playing `m` on `board`,
+    /// from the accumulator of `board`.
+    fn update_accumulator(&mut self, ply: usize, board: &Board, m: Move, child: &Board) {
+        let network = match &self.evaluator {
+            Evaluator::Nnue(network) => network,
+            Evaluator::Handcrafted(_) => return,
+        };
+
+        let mut accumulator = match &self.accumulators[ply] {
+            Some(accumulator) => accumulator.clone(),
+            None => network.refresh(board),
+        };
+
+        network.update(&mut accumulator, board, m, child);
+
+        self.accumulators[ply + 1] = Some(accumulator);
+    }
+
+    fn count_node(&mut self) {
+        self.nodes += 1;
+        self.shared_nodes.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn order_moves(&self, moves: &mut [Move], ply: usize, tt_move_code: Option<u16>) {
+        let root_best_move = if ply == 0 { self.root_best_move } else { None };
+
+        moves.sort_by_cached_key(|&m| {
+            let score = if Some(encode_move(m)) == tt_move_code {
+                2_000_000
+            } else if Some(m) == root_best_move {
+                1_000_000
+            } else if is_capture(m) {
+                100_000 + mvv_lva(m)
+            } else if matches!(
+                m,
+                Move::PieceMove {
+                    promotion: Some(PieceKind::Queen),
+                    ..
+                }
+            ) {
+                95_000
+            } else if Some(m) == self.killers[ply][0] {
+                90_000
+            } else if Some(m) == self.killers[ply][1] {
+                80_000
+            } else if matches!(m, Move::CastleKingside | Move::CastleQueenside) {
+                1_000
+            } else {
+                0
+            };
+
+            -score
+        });
+    }
+
+    fn store_killer(&mut self, ply: usize, m: Move) {
+        if self.killers[ply][0] != Some(m) {
+            self.killers[ply][1] = self.killers[ply][0];
+            self.killers[ply][0] = Some(m);
+        }
+    }
+
+    fn update_pv(&mut self, ply: usize, m: Move) {
+        let (head, tail) = self.pv.split_at_mut(ply + 1);
+
+        head[ply].clear();
+        head[ply].push(m);
+        head[ply].extend_from_slice(&tail[0]);
+    }
+}
+
+/// Searches `board` with a fresh searcher.
+pub fn search(board: &Board, limits: &SearchLimits, options: &EngineOptions) -> SearchResult {
+    Searcher::new(options.clone()).search(board, limits)
+}
+
+/// The score of a position from its distance to mate, as a mate score when
+/// the search could have found the mate.
+fn tablebase_score(dtm: Dtm) -> i32 {
+    match dtm {
+        Dtm::Win(plies) if (plies as usize) < MAX_PLY => MATE_SCORE - plies as i32,
+        Dtm::Win(_) => TABLEBASE_WIN,
+        Dtm::Draw => 0,
+        Dtm::Loss(plies) if (plies as usize) < MAX_PLY => -MATE_SCORE + plies as i32,
+        Dtm::Loss(_) => -TABLEBASE_WIN,
+    }
+}
+
+/// The score of a position from the Syzygy rank of its best root move. Wins
+/// the 50-move rule may still spoil score a little above a draw, more the
+/// safer they are, and losses the same below.
+fn syzygy_score(rank: i32, fifty_move_rule: bool) -> i32 {
+    let bound = if fifty_move_rule { WIN_RANK - 100 } else { 1 };
+
+    if rank >= bound {
+        TABLEBASE_WIN
+    } else if rank > 0 {
+        (rank - (WIN_RANK - 200)).max(3) * PAWN_VALUE / 200
+    } else if rank == 0 {
+        0
+    } else if rank > -bound {
+        (rank + (WIN_RANK - 200)).min(-3) * PAWN_VALUE / 200
+    } else {
+        -TABLEBASE_WIN
+    }
+}
+
+fn late_move_reduction(depth: i32, searched: usize) -> i32 {
+    let reduction = ((depth as f64).ln() * (searched as f64).ln() / 2.0) as i32;
+    reduction.clamp(1, depth - 2)
+}
+
+pub fn is_capture(m: Move) -> bool {
+    matches!(
+        m,
+        Move::PieceMove {
+            target_piece: Some(_),
+            ..
+        }
+    )
+}
+
+pub fn is_promotion(m: Move) -> bool {
+    matches!(
+        m,
+        Move::PieceMove {
+            promotion: Some(_),
+            ..
+        }
+    )
+}
+
+fn captured_value(m: Move) -> i32 {
+    match m {
+        Move::PieceMove {
+            target_piece: Some(target_piece),
+            ..
+        } => piece_value(target_piece.piece_kind),
+        _ => 0,
+    }
+}
+
+fn mvv_lva(m: Move) -> i32 {
+    match m {
+        Move::PieceMove {
+            origin_piece,
+            target_piece: Some(target_piece),
+            ..
+        } => {
+            piece_value(target_piece.piece_kind) * 10
+                - piece_value(origin_piece.piece_kind).min(QUEEN_VALUE)
+        }
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn searcher() -> Searcher {
+        Searcher::new(EngineOptions::default())
+    }
+
+    #[test]
+    fn quiescence_sees_checkmate() {
+        let board = Board::from_fen("k7/8/8/8/8/8/5PPP/r5K1 w - - 0 1").unwrap();
+
+        assert_eq!(
+            searcher().quiescence(&board, -INFINITY, INFINITY, 0),
+            -MATE_SCORE
+        );
+    }
+
+    #[test]
+    fn repetition_scores_as_draw() {
+        let mut board = Board::from_fen("1n2k3/8/8/8/8/8/8/3QK3 w - - 0 1").unwrap();
+        let mut history = Vec::new();
+
+        for lan in ["d1d2", "b8c6", "d2d1"] {
+            history.push(board.hash());
+            board.make_move_unchecked(Move::from_lan(&board, lan).unwrap());
+        }
+
+        let limits = SearchLimits {
+            depth: Some(3),
+            ..Default::default()
+        };
+
+        let mut searcher = searcher();
+        let losing = searcher.search(&board, &limits);
+
+        searcher.clear_hash();
+        searcher.set_game_history(&history);
+        let drawn = searcher.search(&board, &limits);
+
+        assert!(losing.score < -PAWN_VALUE);
+        assert_eq!(drawn.score, 0);
+        assert_eq!(
+            drawn.best_move,
+            Some(Move::from_lan(&board, "c6b8").unwrap())
+        );
+    }
+
+    #[test]
+    fn fifty_move_rule_scores_as_draw() {
+        let limits = SearchLimits {
+            depth: Some(3),
+            ..Default::default()
+        };
+
+        // Two more quiet plies reach the 50-move limit, and the queen cannot
+        // mate in that time.
+        let fresh = Board::from_fen("7k/8/8/8/8/8/8/KQ6 w - - 0 80").unwrap();
+        let expiring = Board::from_fen("7k/8/8/8/8/8/8/KQ6 w - - 98 80").unwrap();
+
+        assert!(searcher().search(&fresh, &limits).score > QUEEN_VALUE / 2);
+        assert_eq!(searcher().search(&expiring, &limits).score, 0);
+    }
+}
@@ -0,0 +1,88 @@
+use std::fmt;
+
+use super::{MATE_BOUND, MATE_SCORE};
+use crate::chess::board::{chess_move::Move, Board};
+
+/// A search score as reported to the user.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Score {
+    Centipawns(i32),
+    /// Moves until mate, negative when the side to move is getting mated.
+    Mate(i32),
+}
+
+impl Score {
+    pub fn from_search_score(score: i32) -> Self {
+        if score >= MATE_BOUND {
+            Score::Mate((MATE_SCORE - score + 1) / 2)
+        } else if score <= -MATE_BOUND {
+            Score::Mate(-(MATE_SCORE + score) / 2)
+        } else {
+            Score::Centipawns(score)
+        }
+    }
+}
+
+impl fmt::Display for Score {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Centipawns(centipawns) => write!(f, "cp {centipawns}"),
+            Self::Mate(moves) => write!(f, "mate {moves}"),
+        }
+    }
+}
+
+/// One principal variation of a MultiPV search.
+#[derive(Debug, Clone)]
+pub struct PvLine {
+    /// 1-based rank of the line, as in UCI's `multipv`.
+    pub multipv: usize,
+    pub depth: u8,
+    pub score: i32,
+    pub moves: Vec<Move>,
+}
+
+impl PvLine {
+    pub fn score(&self) -> Score {
+        Score::from_search_score(self.score)
+    }
+
+    /// The line in UCI notation, played out from `board`.
+    pub fn to_uci(&self, board: &Board) -> Vec<String> {
+        let mut board = board.clone();
+
+        self.moves
+            .iter()
+            .map(|&m| {
+                let uci = m.to_uci(board.active_turn);
+                board.make_move_unchecked(m);
+                uci
+            })
+            .collect()
+    }
+
+    /// The line in standard algebraic notation, played out from `board`.
+    pub fn to_san(&self, board: &Board) -> Vec<String> {
+        let mut board = board.clone();
+
+        self.moves
+            .iter()
+            .map(|&m| {
+                let san = board.move_to_san(m);
+                board.make_move_unchecked(m);
+                san
+            })
+            .collect()
+    }
+
+    /// Formats the line as a UCI `info` string.
+    pub fn to_info_string(&self, board: &Board) -> String {
+        format!(
+            "info depth {} multipv {} score {} pv {}",
+            self.depth,
+            self.multipv,
+            self.score(),
+            self.to_uci(board).join(" ")
+        )
+    }
+}
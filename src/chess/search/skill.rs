@@ -0,0 +1,130 @@
+use rand::Rng;
+
+use super::pv::PvLine;
+use crate::chess::board::chess_move::Move;
+use crate::chess::board::zobrist::splitmix64;
+use crate::chess::evaluation::PAWN_VALUE;
+use crate::chess::options::EngineOptions;
+
+pub const MAX_SKILL_LEVEL: usize = 20;
+
+/// Estimated Elo of each skill level below full strength, as measured by the
+/// `calibrate` binary against the full-strength engine.
+pub const LEVEL_ELO: [i32; MAX_SKILL_LEVEL] = [
+    600, 700, 800, 900, 1000, 1100, 1200, 1300, 1400, 1500, 1600, 1700, 1800, 1900, 2000, 2100,
+    2200, 2300, 2400, 2500,
+];
+
+pub const MIN_ELO: usize = LEVEL_ELO[0] as usize;
+pub const MAX_ELO: usize = LEVEL_ELO[MAX_SKILL_LEVEL - 1] as usize;
+
+/// Root lines searched so a weaker level has sub-optimal moves to choose from.
+const SKILL_MULTI_PV: usize = 4;
+
+/// Strength limiting settings for one search.
+#[derive(Debug, Clone, Copy)]
+pub struct Skill {
+    /// Fractional so UCI_Elo can fall between two levels.
+    level: f64,
+}
+
+impl Skill {
+    pub fn new(level: f64) -> Self {
+        Self {
+            level: level.clamp(0.0, MAX_SKILL_LEVEL as f64),
+        }
+    }
+
+    pub fn from_options(options: &EngineOptions) -> Self {
+        if options.limit_strength {
+            Self::new(level_for_elo(options.uci_elo as i32))
+        } else {
+            Self::new(options.skill_level as f64)
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.level < MAX_SKILL_LEVEL as f64
+    }
+
+    pub fn depth_limit(&self) -> u8 {
+        1 + (self.level / 2.0) as u8
+    }
+
+    pub fn node_limit(&self) -> u64 {
+        (200.0 * 2f64.powf(self.level / 2.0)) as u64
+    }
+
+    pub fn multi_pv(&self) -> usize {
+        if self.is_enabled() {
+            SKILL_MULTI_PV
+        } else {
+            1
+        }
+    }
+
+    /// Largest evaluation error added at this level, in centipawns.
+    pub fn noise_amplitude(&self) -> i32 {
+        ((MAX_SKILL_LEVEL as f64 - self.level) * 10.0) as i32
+    }
+
+    /// Evaluation noise for the position with `hash`. The same position gets the
+    /// same noise throughout one search, so the hash table stays consistent.
+    pub fn noise(&self, hash: u64, seed: u64) -> i32 {
+        let amplitude = self.noise_amplitude();
+
+        if amplitude == 0 {
+            return 0;
+        }
+
+        let mut state = hash ^ seed;
+        let range = 2 * amplitude as u64 + 1;
+
+        (splitmix64(&mut state) % range) as i32 - amplitude
+    }
+
+    /// Picks among the MultiPV `lines`, best first. Weaker levels are more likely
+    /// to pick a worse line, but never one more than a pawn worse than the best.
+    pub fn pick_line<'a>(&self, lines: &'a [PvLine]) -> Option<&'a PvLine> {
+        let top_score = lines.first()?.score;
+        let last_score = lines.last()?.score;
+
+        let delta = (top_score - last_score).min(PAWN_VALUE) as f64;
+        let weakness = 120.0 - 2.0 * self.level;
+
+        let mut rng = rand::thread_rng();
+
+        lines
+            .iter()
+            .filter(|line| top_score - line.score <= PAWN_VALUE)
+            .map(|line| {
+                let push = (weakness * (top_score - line.score) as f64
+                    + delta * rng.gen_range(0.0..weakness))
+                    / 128.0;
+
+                (line.score as f64 + push, line)
+            })
+            .max_by(|(a, _), (b, _)| a.total_cmp(b))
+            .map(|(_, line)| line)
+    }
+
+    pub fn pick_move(&self, lines: &[PvLine]) -> Option<Move> {
+        self.pick_line(lines)
+            .and_then(|line| line.moves.first().copied())
+    }
+}
+
+/// The fractional skill level expected to play at `elo`.
+pub fn level_for_elo(elo: i32) -> f64 {
+    if elo <= LEVEL_ELO[0] {
+        return 0.0;
+    }
+
+    for (level, pair) in LEVEL_ELO.windows(2).enumerate() {
+        if elo <= pair[1] {
+            return level as f64 + (elo - pair[0]) as f64 / (pair[1] - pair[0]).max(1) as f64;
+        }
+    }
+
+    (MAX_SKILL_LEVEL - 1) as f64
+}
@@ -0,0 +1,116 @@
+use std::time::{Duration, Instant};
+
+use super::SearchLimits;
+use crate::chess::board::Color;
+
+/// Time kept in reserve for communication latency.
+const MOVE_OVERHEAD: u64 = 50;
+const DEFAULT_MOVES_TO_GO: u64 = 30;
+
+/// The soft limit never grows beyond this multiple of the base allocation.
+const MAX_EXTENSION: f64 = 3.0;
+const UNSTABLE_BEST_MOVE_EXTENSION: f64 = 0.5;
+const SCORE_DROP_THRESHOLD: i32 = 30;
+const SCORE_DROP_EXTENSION: f64 = 0.5;
+
+/// Splits the clock into a soft limit, checked between iterations, and a hard
+/// limit at which the search is aborted mid-iteration.
+#[derive(Debug, Clone)]
+pub struct TimeManager {
+    start: Instant,
+    soft_limit: Option<Duration>,
+    hard_limit: Option<Duration>,
+    extension: f64,
+}
+
+impl TimeManager {
+    pub fn new(limits: &SearchLimits, side: Color) -> Self {
+        let start = Instant::now();
+
+        if let Some(movetime) = limits.movetime {
+            let movetime = Duration::from_millis(movetime.saturating_sub(MOVE_OVERHEAD).max(1));
+
+            return Self {
+                start,
+                soft_limit: Some(movetime),
+                hard_limit: Some(movetime),
+                extension: 1.0,
+            };
+        }
+
+        let (time, increment) = match side {
+            Color::White => (limits.wtime, limits.winc),
+            Color::Black => (limits.btime, limits.binc),
+        };
+
+        let time = match time {
+            Some(time) => time,
+            None => {
+                return Self {
+                    start,
+                    soft_limit: None,
+                    hard_limit: None,
+                    extension: 1.0,
+                }
+            }
+        };
+
+        let increment = increment.unwrap_or(0);
+        let moves_to_go = limits.movestogo.unwrap_or(DEFAULT_MOVES_TO_GO).max(1);
+        let available = time.saturating_sub(MOVE_OVERHEAD).max(1);
+
+        let soft_limit = (available / moves_to_go + increment * 3 / 4).min(available);
+        let hard_limit = (soft_limit * 4).min(available / 2).max(soft_limit);
+
+        Self {
+            start,
+            soft_limit: Some(Duration::from_millis(soft_limit)),
+            hard_limit: Some(Duration::from_millis(hard_limit)),
+            extension: 1.0,
+        }
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    pub fn hard_limit_reached(&self) -> bool {
+        self.hard_limit
+            .is_some_and(|hard_limit| self.elapsed() >= hard_limit)
+    }
+
+    /// Returns true if there is no point starting another iteration.
+    pub fn soft_limit_reached(&self) -> bool {
+        let soft_limit = match self.soft_limit {
+            Some(soft_limit) => soft_limit.mul_f64(self.extension),
+            None => return false,
+        };
+
+        let soft_limit = match self.hard_limit {
+            Some(hard_limit) => soft_limit.min(hard_limit),
+            None => soft_limit,
+        };
+
+        self.elapsed() >= soft_limit
+    }
+
+    /// Gives the search more time after an iteration that changed its mind
+    /// about the best move or saw the score fall.
+    pub fn update(&mut self, best_move_changed: bool, previous_score: i32, score: i32) {
+        let mut extension = 1.0;
+
+        if best_move_changed {
+            extension += UNSTABLE_BEST_MOVE_EXTENSION;
+        }
+
+        if previous_score - score >= SCORE_DROP_THRESHOLD {
+            extension += SCORE_DROP_EXTENSION;
+        }
+
+        if previous_score - score >= SCORE_DROP_THRESHOLD * 2 {
+            extension += SCORE_DROP_EXTENSION;
+        }
+
+        self.extension = extension.min(MAX_EXTENSION);
+    }
+}
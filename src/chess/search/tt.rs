@@ -0,0 +1,162 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use super::MATE_BOUND;
+use crate::chess::board::chess_move::Move;
+use crate::chess::board::PieceKind;
+
+const ENTRY_SIZE: usize = 16;
+
+const CASTLE_KINGSIDE_CODE: u16 = 1 << 12;
+const CASTLE_QUEENSIDE_CODE: u16 = 1 << 13;
+const PIECE_MOVE_FLAG: u16 = 1 << 14;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Bound {
+    Exact,
+    Lower,
+    Upper,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct TtEntry {
+    pub move_code: u16,
+    pub score: i32,
+    pub depth: i32,
+    pub bound: Bound,
+}
+
+/// Packs a move into 16 bits. The pieces are left out since they can be read
+/// back from the board the move is played on, apart from the promotion piece,
+/// which goes in the bits the castle codes use.
+pub fn encode_move(m: Move) -> u16 {
+    match m {
+        Move::CastleKingside => CASTLE_KINGSIDE_CODE,
+        Move::CastleQueenside => CASTLE_QUEENSIDE_CODE,
+
+        Move::PieceMove {
+            origin_square,
+            target_square,
+            promotion,
+            ..
+        } => {
+            let origin = (origin_square.0 * 8 + origin_square.1) as u16;
+            let target = (target_square.0 * 8 + target_square.1) as u16;
+
+            let promotion = match promotion {
+                None | Some(PieceKind::Queen) => 0,
+                Some(PieceKind::Knight) => 1,
+                Some(PieceKind::Bishop) => 2,
+                _ => 3,
+            };
+
+            PIECE_MOVE_FLAG | origin | target << 6 | promotion << 12
+        }
+    }
+}
+
+/// Hash table shared by every search thread.
+///
+/// Each slot stores the key xor-ed with its data next to the data itself, so a
+/// slot torn by two threads writing at once fails verification on probe
+/// instead of returning another position's entry.
+pub struct TranspositionTable {
+    slots: Vec<[AtomicU64; 2]>,
+}
+
+impl TranspositionTable {
+    pub fn new(size_mb: usize) -> Self {
+        let slot_count = size_mb.max(1) * 1024 * 1024 / ENTRY_SIZE;
+
+        let slot_count = if slot_count.is_power_of_two() {
+            slot_count
+        } else {
+            slot_count.next_power_of_two() / 2
+        };
+
+        Self {
+            slots: (0..slot_count)
+                .map(|_| [AtomicU64::new(0), AtomicU64::new(0)])
+                .collect(),
+        }
+    }
+
+    pub fn clear(&self) {
+        for slot in &self.slots {
+            slot[0].store(0, Ordering::Relaxed);
+            slot[1].store(0, Ordering::Relaxed);
+        }
+    }
+
+    fn slot(&self, hash: u64) -> &[AtomicU64; 2] {
+        &self.slots[hash as usize & (self.slots.len() - 1)]
+    }
+
+    pub fn probe(&self, hash: u64, ply: usize) -> Option<TtEntry> {
+        let slot = self.slot(hash);
+
+        let key = slot[0].load(Ordering::Relaxed);
+        let data = slot[1].load(Ordering::Relaxed);
+
+        if data == 0 || key ^ data != hash {
+            return None;
+        }
+
+        let bound = match data >> 48 & 0b11 {
+            0 => Bound::Exact,
+            1 => Bound::Lower,
+            _ => Bound::Upper,
+        };
+
+        Some(TtEntry {
+            move_code: data as u16,
+            score: score_from_tt(data >> 16 & 0xFFFF) - mate_adjustment(data, ply),
+            depth: (data >> 32 & 0xFFFF) as i16 as i32,
+            bound,
+        })
+    }
+
+    pub fn store(&self, hash: u64, ply: usize, entry: TtEntry) {
+        let slot = self.slot(hash);
+
+        let score = if entry.score >= MATE_BOUND {
+            entry.score + ply as i32
+        } else if entry.score <= -MATE_BOUND {
+            entry.score - ply as i32
+        } else {
+            entry.score
+        };
+
+        let bound = match entry.bound {
+            Bound::Exact => 0,
+            Bound::Lower => 1,
+            Bound::Upper => 2,
+        };
+
+        let data = entry.move_code as u64
+            | (score as i16 as u16 as u64) << 16
+            | (entry.depth as i16 as u16 as u64) << 32
+            | bound << 48
+            | 1 << 50;
+
+        slot[0].store(hash ^ data, Ordering::Relaxed);
+        slot[1].store(data, Ordering::Relaxed);
+    }
+}
+
+fn score_from_tt(bits: u64) -> i32 {
+    bits as u16 as i16 as i32
+}
+
+/// Mate scores are stored relative to the node they were found in and turned
+/// back into distances from the root on probe.
+fn mate_adjustment(data: u64, ply: usize) -> i32 {
+    let score = score_from_tt(data >> 16 & 0xFFFF);
+
+    if score >= MATE_BOUND {
+        ply as i32
+    } else if score <= -MATE_BOUND {
+        -(ply as i32)
+    } else {
+        0
+    }
+}
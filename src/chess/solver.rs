@@ -0,0 +1,312 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use super::board::{chess_move::Move, Board};
+use super::search::is_capture;
+
+/// An attacking move together with every defence against it. The tree ends at
+/// an attacking move that mates, which has no defences.
+#[derive(Debug, Clone)]
+pub struct AttackNode {
+    pub attack: Move,
+    pub defences: Vec<DefenceNode>,
+}
+
+/// A defence and the attacking move that continues the forced mate after it.
+#[derive(Debug, Clone)]
+pub struct DefenceNode {
+    pub defence: Move,
+    pub continuation: AttackNode,
+}
+
+/// A proven forced mate.
+#[derive(Clone)]
+pub struct MateSolution {
+    pub board: Board,
+    /// Length of the shortest forced mate, in attacking moves.
+    pub mate_in: u32,
+    pub key: AttackNode,
+    /// Other first moves that also force mate within the requested number of
+    /// moves, which make the problem unsound.
+    pub cooks: Vec<Move>,
+}
+
+impl MateSolution {
+    pub fn is_sound(&self) -> bool {
+        self.cooks.is_empty()
+    }
+}
+
+/// Proves or refutes a forced mate in at most `moves` moves for the side to
+/// move, returning the full solution tree with every defence.
+pub fn solve_mate(board: &Board, moves: u32) -> Option<MateSolution> {
+    let mut solver = MateSolver::default();
+
+    let mate_in = (1..=moves).find(|&depth| solver.has_mate(board, depth))?;
+    let key = solver.build_attack(board, mate_in)?;
+
+    let cooks = solver
+        .attacking_moves(board, moves)
+        .into_iter()
+        .filter(|&m| m != key.attack && solver.attack_mates(board, m, moves))
+        .collect();
+
+    Some(MateSolution {
+        board: board.clone(),
+        mate_in,
+        key,
+        cooks,
+    })
+}
+
+/// Exhaustive checks-first search with a cache of proven and refuted positions.
+#[derive(Default)]
+struct MateSolver {
+    cache: HashMap<(u64, u32), bool>,
+}
+
+impl MateSolver {
+    /// Whether the side to move in `board` mates in at most `depth` moves.
+    fn has_mate(&mut self, board: &Board, depth: u32) -> bool {
+        if depth == 0 {
+            return false;
+        }
+
+        let key = (board.hash(), depth);
+
+        if let Some(&proven) = self.cache.get(&key) {
+            return proven;
+        }
+
+        let proven = self
+            .attacking_moves(board, depth)
+            .into_iter()
+            .any(|m| self.attack_mates(board, m, depth));
+
+        self.cache.insert(key, proven);
+        proven
+    }
+
+    /// Whether playing `m` forces mate within `depth` moves, counting `m`.
+    fn attack_mates(&mut self, board: &Board, m: Move, depth: u32) -> bool {
+        let mut child = board.clone();
+        child.make_move_unchecked(m);
+
+        let defences = child.get_legal_moves();
+
+        if defences.is_empty() {
+            return child.is_in_check();
+        }
+
+        defences.into_iter().all(|defence| {
+            let mut grandchild = child.clone();
+            grandchild.make_move_unchecked(defence);
+
+            self.has_mate(&grandchild, depth - 1)
+        })
+    }
+
+    /// Legal moves with checks first, then captures. On the last move only
+    /// checks can mate.
+    fn attacking_moves(&self, board: &Board, depth: u32) -> Vec<Move> {
+        let mut moves: Vec<(u8, Move)> = board
+            .get_legal_moves()
+            .into_iter()
+            .filter_map(|m| {
+                let mut child = board.clone();
+                child.make_move_unchecked(m);
+
+                let priority = match (child.is_in_check(), is_capture(m)) {
+                    (true, _) => 0,
+                    (false, true) => 1,
+                    (false, false) => 2,
+                };
+
+                (depth > 1 || priority == 0).then_some((priority, m))
+            })
+            .collect();
+
+        moves.sort_by_key(|&(priority, _)| priority);
+        moves.into_iter().map(|(_, m)| m).collect()
+    }
+
+    /// The proof tree for a position already known to mate in `depth`. Each
+    /// defence is answered with the shortest continuing mate.
+    fn build_attack(&mut self, board: &Board, depth: u32) -> Option<AttackNode> {
+        let attack = self
+            .attacking_moves(board, depth)
+            .into_iter()
+            .find(|&m| self.attack_mates(board, m, depth))?;
+
+        let mut child = board.clone();
+        child.make_move_unchecked(attack);
+
+        let mut defences = Vec::new();
+
+        for defence in child.get_legal_moves() {
+            let mut grandchild = child.clone();
+            grandchild.make_move_unchecked(defence);
+
+            let remaining = (1..depth).find(|&remaining| self.has_mate(&grandchild, remaining))?;
+
+            defences.push(DefenceNode {
+                defence,
+                continuation: self.build_attack(&grandchild, remaining)?,
+            });
+        }
+
+        Some(AttackNode { attack, defences })
+    }
+}
+
+impl fmt::Display for MateSolution {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Mate in {}", self.mate_in)?;
+        writeln!(f, "1. {}!", self.board.move_to_san(self.key.attack))?;
+        write_defences(f, &self.board, &self.key, 1)?;
+
+        if !self.cooks.is_empty() {
+            let cooks: Vec<String> = self
+                .cooks
+                .iter()
+                .map(|&m| self.board.move_to_san(m))
+                .collect();
+
+            writeln!(f, "Cooks: {}", cooks.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Writes each defence against `node.attack` on its own line, followed by the
+/// attacking reply and, indented below, the defences against that reply.
+fn write_defences(
+    f: &mut fmt::Formatter,
+    board: &Board,
+    node: &AttackNode,
+    move_number: u32,
+) -> fmt::Result {
+    let indent = "  ".repeat(move_number as usize);
+
+    let mut child = board.clone();
+    child.make_move_unchecked(node.attack);
+
+    for defence in &node.defences {
+        let mut grandchild = child.clone();
+        grandchild.make_move_unchecked(defence.defence);
+
+        writeln!(
+            f,
+            "{indent}{move_number}... {} {}. {}",
+            child.move_to_san(defence.defence),
+            move_number + 1,
+            grandchild.move_to_san(defence.continuation.attack)
+        )?;
+
+        write_defences(f, &grandchild, &defence.continuation, move_number + 1)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn board(fen: &str) -> Board {
+        Board::from_fen(fen).unwrap()
+    }
+
+    fn uci(board: &Board, m: &str) -> Move {
+        Move::from_uci(board, m).unwrap()
+    }
+
+    fn is_mate(board: &Board) -> bool {
+        board.is_in_check() && board.get_legal_moves().is_empty()
+    }
+
+    #[test]
+    fn solves_a_mate_in_one() {
+        let board = board("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1");
+        let solution = solve_mate(&board, 1).unwrap();
+
+        assert_eq!(solution.mate_in, 1);
+        assert_eq!(solution.key.attack, uci(&board, "a1a8"));
+        assert!(solution.key.defences.is_empty());
+        assert!(solution.is_sound());
+        assert_eq!(solution.to_string(), "Mate in 1\n1. Ra8#!\n");
+    }
+
+    #[test]
+    fn answers_every_defence_in_a_mate_in_two() {
+        let board = board("8/1k2N3/6K1/p7/5p1B/8/2Q5/8 w - - 0 1");
+
+        assert!(solve_mate(&board, 1).is_none());
+
+        let solution = solve_mate(&board, 2).unwrap();
+        assert_eq!(solution.mate_in, 2);
+        assert_eq!(solution.key.attack, uci(&board, "h4f2"));
+        assert!(solution.is_sound());
+
+        let mut after_key = board.clone();
+        after_key.make_move_unchecked(solution.key.attack);
+
+        let mut defences: Vec<Move> = solution
+            .key
+            .defences
+            .iter()
+            .map(|node| node.defence)
+            .collect();
+        let mut legal = after_key.get_legal_moves();
+
+        defences.sort_by_key(|m| m.to_uci(after_key.active_turn));
+        legal.sort_by_key(|m| m.to_uci(after_key.active_turn));
+        assert_eq!(defences, legal);
+        assert_eq!(defences.len(), 5);
+
+        for node in &solution.key.defences {
+            let mut position = after_key.clone();
+            position.make_move_unchecked(node.defence);
+            position.make_move_unchecked(node.continuation.attack);
+
+            assert!(is_mate(&position));
+            assert!(node.continuation.defences.is_empty());
+        }
+
+        assert!(solution.to_string().contains("1... Ka6 2. Qc6#\n"));
+    }
+
+    #[test]
+    fn reports_cooks() {
+        let board = board("6k1/5ppp/8/8/8/8/8/RR4K1 w - - 0 1");
+        let solution = solve_mate(&board, 1).unwrap();
+
+        assert_eq!(solution.key.attack, uci(&board, "a1a8"));
+        assert_eq!(solution.cooks, vec![uci(&board, "b1b8")]);
+        assert!(!solution.is_sound());
+        assert!(solution.to_string().ends_with("Cooks: Rb8#\n"));
+    }
+
+    #[test]
+    fn stalemate_refutes_a_try() {
+        let board = board("k7/8/2K5/8/8/8/8/1R6 w - - 0 1");
+
+        // 1. Rb7 leaves black without a move but not in check.
+        let try_move = uci(&board, "b1b7");
+        let mut stalemate = board.clone();
+        stalemate.make_move_unchecked(try_move);
+        assert!(stalemate.get_legal_moves().is_empty() && !stalemate.is_in_check());
+
+        assert!(!MateSolver::default().attack_mates(&board, try_move, 2));
+
+        let solution = solve_mate(&board, 2).unwrap();
+        assert_eq!(solution.key.attack, uci(&board, "c6c7"));
+        assert!(solution.is_sound());
+    }
+
+    #[test]
+    fn no_mate_within_the_limit() {
+        let board = board("8/8/8/8/8/5K2/8/5k1R w - - 0 1");
+
+        assert!(solve_mate(&board, 2).is_none());
+    }
+}
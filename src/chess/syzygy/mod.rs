@@ -0,0 +1,840 @@
+//! Probing of Syzygy endgame tablebases.
+//!
+//! Syzygy files come in pairs per material: a `.rtbw` file with the
+//! win/draw/loss value of every position and a `.rtbz` file with the distance
+//! to the next capture or pawn move (DTZ) that keeps the win. Both count wins
+//! that need more than 50 moves without a capture or pawn move as cursed wins,
+//! and the losses that go with them as blessed losses.
+//!
+//! Tables are found by name in the `SyzygyPath` directories and read on first
+//! use.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::ops::Neg;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+use self::table::{DtzProbe, Table, TableKind, MAX_PIECES};
+use super::board::chess_move::Move;
+use super::board::{Board, Color, PieceKind};
+
+mod table;
+
+/// Separates the directories of `SyzygyPath`.
+#[cfg(windows)]
+pub const PATH_SEPARATOR: char = ';';
+#[cfg(not(windows))]
+pub const PATH_SEPARATOR: char = ':';
+
+/// Rank of a root move that wins within the 50-move rule. Slower wins rank
+/// lower as the 50-move counter runs out, and losses rank from the negation.
+pub const WIN_RANK: i32 = 1000;
+
+/// Piece kinds in the order of table names, kings first.
+const NAME_ORDER: [PieceKind; 6] = [
+    PieceKind::King,
+    PieceKind::Queen,
+    PieceKind::Rook,
+    PieceKind::Bishop,
+    PieceKind::Knight,
+    PieceKind::Pawn,
+];
+
+/// A position value from the side to move's point of view.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum WdlScore {
+    Loss,
+    /// A loss the 50-move rule turns into a draw.
+    BlessedLoss,
+    Draw,
+    /// A win the 50-move rule turns into a draw.
+    CursedWin,
+    Win,
+}
+
+impl WdlScore {
+    fn from_i32(value: i32) -> Option<Self> {
+        match value {
+            -2 => Some(Self::Loss),
+            -1 => Some(Self::BlessedLoss),
+            0 => Some(Self::Draw),
+            1 => Some(Self::CursedWin),
+            2 => Some(Self::Win),
+            _ => None,
+        }
+    }
+
+    /// The DTZ of a position whose best move is a capture or a pawn move.
+    fn dtz_before_zeroing(self) -> i32 {
+        match self {
+            Self::Loss => -1,
+            Self::BlessedLoss => -101,
+            Self::Draw => 0,
+            Self::CursedWin => 101,
+            Self::Win => 1,
+        }
+    }
+
+    fn signum(self) -> i32 {
+        match self {
+            Self::Loss | Self::BlessedLoss => -1,
+            Self::Draw => 0,
+            Self::CursedWin | Self::Win => 1,
+        }
+    }
+}
+
+impl Neg for WdlScore {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        match self {
+            Self::Loss => Self::Win,
+            Self::BlessedLoss => Self::CursedWin,
+            Self::Draw => Self::Draw,
+            Self::CursedWin => Self::BlessedLoss,
+            Self::Win => Self::Loss,
+        }
+    }
+}
+
+/// The pieces of a table, such as KRPvKR, white being the side named first.
+#[derive(Debug, Clone)]
+struct Material {
+    name: String,
+    /// Counts by colour and piece kind.
+    counts: [[u8; 6]; 2],
+    /// Pawns of the side whose pawns lead the encoding, then of the other.
+    pawn_count: [usize; 2],
+}
+
+impl Material {
+    fn parse(name: &str) -> Option<Self> {
+        let (white, black) = name.split_once('v')?;
+        let mut counts = [[0; 6]; 2];
+
+        for (side, pieces) in [white, black].into_iter().enumerate() {
+            for c in pieces.chars() {
+                let kind = NAME_ORDER.into_iter().find(|&kind| kind_char(kind) == c)?;
+
+                counts[side][kind as usize] += 1;
+            }
+
+            if counts[side][PieceKind::King as usize] != 1 {
+                return None;
+            }
+        }
+
+        let pawns = counts.map(|side| side[PieceKind::Pawn as usize] as usize);
+
+        // The side with fewer pawns leads, so that its pawns spread over the
+        // files; white when both have as many.
+        let pawn_count = if pawns[1] == 0 || (pawns[0] > 0 && pawns[1] >= pawns[0]) {
+            pawns
+        } else {
+            [pawns[1], pawns[0]]
+        };
+
+        let material = Self {
+            name: name.to_string(),
+            counts,
+            pawn_count,
+        };
+
+        (material.piece_count() <= MAX_PIECES).then_some(material)
+    }
+
+    fn piece_count(&self) -> usize {
+        self.counts
+            .iter()
+            .flatten()
+            .map(|&count| count as usize)
+            .sum()
+    }
+
+    fn is_symmetric(&self) -> bool {
+        self.counts[0] == self.counts[1]
+    }
+
+    fn has_pawns(&self) -> bool {
+        self.pawn_count[0] + self.pawn_count[1] > 0
+    }
+
+    /// Whether either side has exactly one of some piece other than the king.
+    fn has_unique_pieces(&self) -> bool {
+        self.counts
+            .iter()
+            .any(|side| side[..PieceKind::King as usize].contains(&1))
+    }
+}
+
+fn kind_char(kind: PieceKind) -> char {
+    match kind {
+        PieceKind::Pawn => 'P',
+        PieceKind::Knight => 'N',
+        PieceKind::Bishop => 'B',
+        PieceKind::Rook => 'R',
+        PieceKind::Queen => 'Q',
+        PieceKind::King => 'K',
+    }
+}
+
+/// A position as the tables see it: pieces as codes 1 to 6 for white pawn to
+/// king, plus 8 for black, on squares counted from a1.
+struct TbPosition {
+    /// Material with white named first.
+    material: String,
+    white_to_move: bool,
+    pieces: Vec<(u8, u8)>,
+}
+
+impl TbPosition {
+    /// `None` with castling rights, which no table covers, or an en passant
+    /// capture, which the probing code does not play out.
+    fn from_board(board: &Board) -> Option<Self> {
+        let castle_flags = &board.castle_flags;
+
+        if castle_flags.white_kingside
+            || castle_flags.white_queenside
+            || castle_flags.black_kingside
+            || castle_flags.black_queenside
+        {
+            return None;
+        }
+
+        if board.capturable_en_passant_file().is_some() {
+            return None;
+        }
+
+        let mut pieces = Vec::new();
+        let mut counts = [[0; 6]; 2];
+
+        for (r_index, rank) in board.state.iter().enumerate() {
+            for (f_index, square) in rank.iter().enumerate() {
+                if let Some(piece) = square {
+                    let color = match piece.color {
+                        Color::White => 0,
+                        Color::Black => 8,
+                    };
+
+                    let square = ((7 - r_index) * 8 + f_index) as u8;
+                    pieces.push((piece.piece_kind as u8 + 1 + color, square));
+                    counts[(color / 8) as usize][piece.piece_kind as usize] += 1;
+                }
+            }
+        }
+
+        let name = |side: &[u8; 6]| -> String {
+            NAME_ORDER
+                .iter()
+                .flat_map(|&kind| {
+                    std::iter::repeat_n(kind_char(kind), side[kind as usize] as usize)
+                })
+                .collect()
+        };
+
+        Some(Self {
+            material: format!("{}v{}", name(&counts[0]), name(&counts[1])),
+            white_to_move: board.active_turn == Color::White,
+            pieces,
+        })
+    }
+
+    /// The material with the sides swapped.
+    fn mirrored_material(&self) -> String {
+        let (white, black) = self.material.split_once('v').unwrap();
+        format!("{black}v{white}")
+    }
+}
+
+/// The WDL and DTZ files of one material, loaded on first probe. A file that
+/// fails to load counts as missing.
+struct TableFiles {
+    material: Material,
+    wdl_path: PathBuf,
+    dtz_path: Option<PathBuf>,
+    wdl: OnceLock<Option<Table>>,
+    dtz: OnceLock<Option<Table>>,
+}
+
+impl TableFiles {
+    fn wdl(&self) -> Option<&Table> {
+        self.wdl
+            .get_or_init(|| Table::load(&self.wdl_path, TableKind::Wdl, &self.material).ok())
+            .as_ref()
+    }
+
+    fn dtz(&self) -> Option<&Table> {
+        self.dtz
+            .get_or_init(|| {
+                let path = self.dtz_path.as_ref()?;
+                Table::load(path, TableKind::Dtz, &self.material).ok()
+            })
+            .as_ref()
+    }
+}
+
+/// Root moves with their ranks, best first.
+#[derive(Debug, Clone)]
+pub struct RootRanking {
+    pub moves: Vec<(Move, i32)>,
+    /// Whether the ranks come from DTZ, which keeps a won game making
+    /// progress. Ranks from WDL alone only tell wins, draws and losses apart.
+    pub by_dtz: bool,
+}
+
+/// The Syzygy tables found in a set of directories.
+pub struct Syzygy {
+    tables: HashMap<String, TableFiles>,
+    max_pieces: usize,
+}
+
+impl Syzygy {
+    /// Finds the tables in `paths`, directories separated by
+    /// `PATH_SEPARATOR`. Tables are only read when first probed.
+    pub fn load(paths: &str) -> Result<Self, SyzygyError> {
+        let mut tables = HashMap::new();
+
+        for dir in paths.split(PATH_SEPARATOR).filter(|dir| !dir.is_empty()) {
+            for entry in fs::read_dir(dir)? {
+                let path = entry?.path();
+
+                let is_wdl = path
+                    .extension()
+                    .is_some_and(|extension| extension == TableKind::Wdl.extension());
+
+                let material = path
+                    .file_stem()
+                    .and_then(|stem| stem.to_str())
+                    .and_then(Material::parse);
+
+                if let (true, Some(material)) = (is_wdl, material) {
+                    let dtz_path = path.with_extension(TableKind::Dtz.extension());
+
+                    tables
+                        .entry(material.name.clone())
+                        .or_insert_with(|| TableFiles {
+                            material,
+                            wdl_path: path.clone(),
+                            dtz_path: dtz_path.exists().then_some(dtz_path),
+                            wdl: OnceLock::new(),
+                            dtz: OnceLock::new(),
+                        });
+                }
+            }
+        }
+
+        if tables.is_empty() {
+            return Err(SyzygyError::NoTables(paths.to_string()));
+        }
+
+        let max_pieces = tables
+            .values()
+            .map(|files| files.material.piece_count())
+            .max()
+            .unwrap_or(0);
+
+        Ok(Self { tables, max_pieces })
+    }
+
+    /// How many tables were found.
+    pub fn len(&self) -> usize {
+        self.tables.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tables.is_empty()
+    }
+
+    /// The most pieces of any table.
+    pub fn max_pieces(&self) -> usize {
+        self.max_pieces
+    }
+
+    /// Whether `board` has few enough pieces for the tables.
+    fn covers(&self, board: &Board) -> bool {
+        board.state.iter().flatten().flatten().count() <= self.max_pieces
+    }
+
+    /// The value of `board` for the side to move, or `None` when a table it
+    /// needs is missing or the position has castling rights.
+    pub fn probe_wdl(&self, board: &Board) -> Option<WdlScore> {
+        self.search(board, false).map(|(wdl, _)| wdl)
+    }
+
+    /// The distance in plies to the next capture or pawn move on the way to
+    /// the best outcome, negative when losing and 0 in a draw. Cursed wins and
+    /// blessed losses count 100 plies further.
+    pub fn probe_dtz(&self, board: &Board) -> Option<i32> {
+        let (wdl, zeroing_best) = self.search(board, true)?;
+
+        if wdl == WdlScore::Draw {
+            return Some(0);
+        }
+
+        // DTZ holds an arbitrary value when the best move zeroes anyway.
+        if zeroing_best {
+            return Some(wdl.dtz_before_zeroing());
+        }
+
+        let position = TbPosition::from_board(board)?;
+
+        match self.files(&position)?.dtz()?.probe_dtz(&position, wdl)? {
+            DtzProbe::Value(dtz) => {
+                let cursed = matches!(wdl, WdlScore::CursedWin | WdlScore::BlessedLoss);
+                Some((dtz + 100 * cursed as i32) * wdl.signum())
+            }
+
+            DtzProbe::ChangeSideToMove => self.dtz_from_children(board, wdl),
+        }
+    }
+
+    /// Ranks the legal moves of `board` by DTZ, or by WDL when a DTZ table is
+    /// missing, counting the 50-move rule from the board's halfmove clock.
+    /// `None` when the position cannot be probed.
+    pub fn rank_root_moves(&self, board: &Board) -> Option<RootRanking> {
+        if !self.covers(board) {
+            return None;
+        }
+
+        let mut ranking = match self.rank_by_dtz(board) {
+            Some(moves) => RootRanking {
+                moves,
+                by_dtz: true,
+            },
+
+            None => RootRanking {
+                moves: self.rank_by_wdl(board)?,
+                by_dtz: false,
+            },
+        };
+
+        if ranking.moves.is_empty() {
+            return None;
+        }
+
+        ranking.moves.sort_by_key(|&(_, rank)| -rank);
+        Some(ranking)
+    }
+
+    fn rank_by_dtz(&self, board: &Board) -> Option<Vec<(Move, i32)>> {
+        let halfmove_clock = board.halfmove_clock as i32;
+        let mut moves = Vec::new();
+
+        for m in board.get_legal_moves() {
+            let mut child = board.clone();
+            child.make_move_unchecked(m);
+
+            let mut dtz = if is_zeroing(m) {
+                (-self.probe_wdl(&child)?).dtz_before_zeroing()
+            } else {
+                let dtz = -self.probe_dtz(&child)?;
+                dtz + dtz.signum()
+            };
+
+            if dtz == 2 && is_mate(&child) {
+                dtz = 1;
+            }
+
+            let rank = if dtz > 0 {
+                if dtz + halfmove_clock <= 99 {
+                    WIN_RANK
+                } else {
+                    WIN_RANK - (dtz + halfmove_clock)
+                }
+            } else if dtz < 0 {
+                if -dtz * 2 + halfmove_clock < 100 {
+                    -WIN_RANK
+                } else {
+                    -WIN_RANK + (-dtz + halfmove_clock)
+                }
+            } else {
+                0
+            };
+
+            moves.push((m, rank));
+        }
+
+        Some(moves)
+    }
+
+    fn rank_by_wdl(&self, board: &Board) -> Option<Vec<(Move, i32)>> {
+        let mut moves = Vec::new();
+
+        for m in board.get_legal_moves() {
+            let mut child = board.clone();
+            child.make_move_unchecked(m);
+
+            let rank = match -self.probe_wdl(&child)? {
+                WdlScore::Loss => -WIN_RANK,
+                WdlScore::BlessedLoss => -WIN_RANK + 101,
+                WdlScore::Draw => 0,
+                WdlScore::CursedWin => WIN_RANK - 101,
+                WdlScore::Win => WIN_RANK,
+            };
+
+            moves.push((m, rank));
+        }
+
+        Some(moves)
+    }
+
+    /// The DTZ of a position whose table only stores the other side to move,
+    /// from the best of its moves.
+    fn dtz_from_children(&self, board: &Board, wdl: WdlScore) -> Option<i32> {
+        let mut min_dtz: Option<i32> = None;
+
+        for m in board.get_legal_moves() {
+            let zeroing = is_zeroing(m);
+
+            let mut child = board.clone();
+            child.make_move_unchecked(m);
+
+            // A zeroing move is valued by the DTZ before it is played, and the
+            // child only tells whether it keeps the result.
+            let mut dtz = if zeroing {
+                -self.search(&child, false)?.0.dtz_before_zeroing()
+            } else {
+                -self.probe_dtz(&child)?
+            };
+
+            if dtz == 1 && is_mate(&child) {
+                return Some(1);
+            }
+
+            if !zeroing {
+                dtz += dtz.signum();
+            }
+
+            if dtz.signum() == wdl.signum() && min_dtz.is_none_or(|min| dtz < min) {
+                min_dtz = Some(dtz);
+            }
+        }
+
+        Some(min_dtz.unwrap_or(-1))
+    }
+
+    /// The value of `board` after trying its captures, and pawn moves too when
+    /// `check_zeroing` is set, and whether its best move is one of them. The
+    /// tables may hold any value for positions whose best move zeroes the
+    /// 50-move counter.
+    fn search(&self, board: &Board, check_zeroing: bool) -> Option<(WdlScore, bool)> {
+        if !self.covers(board) {
+            return None;
+        }
+
+        let moves = board.get_legal_moves();
+        let mut best = WdlScore::Loss;
+        let mut searched = 0;
+
+        for &m in &moves {
+            let tried = if check_zeroing {
+                is_zeroing(m)
+            } else {
+                is_capture(m)
+            };
+
+            if !tried {
+                continue;
+            }
+
+            searched += 1;
+
+            let mut child = board.clone();
+            child.make_move_unchecked(m);
+
+            let value = -self.search(&child, false)?.0;
+
+            if value > best {
+                best = value;
+
+                if value == WdlScore::Win {
+                    return Some((value, true));
+                }
+            }
+        }
+
+        // With every move tried the table is not needed, and it could be wrong
+        // for a position where only captures are legal.
+        let no_more_moves = searched > 0 && searched == moves.len();
+
+        let value = if no_more_moves {
+            best
+        } else {
+            self.probe_wdl_table(board)?
+        };
+
+        if best >= value {
+            Some((best, best > WdlScore::Draw || no_more_moves))
+        } else {
+            Some((value, false))
+        }
+    }
+
+    fn probe_wdl_table(&self, board: &Board) -> Option<WdlScore> {
+        let position = TbPosition::from_board(board)?;
+
+        if position.pieces.len() == 2 {
+            return Some(WdlScore::Draw);
+        }
+
+        self.files(&position)?.wdl()?.probe_wdl(&position)
+    }
+
+    /// The files of the position's material, named from either side.
+    fn files(&self, position: &TbPosition) -> Option<&TableFiles> {
+        self.tables
+            .get(&position.material)
+            .or_else(|| self.tables.get(&position.mirrored_material()))
+    }
+}
+
+fn is_capture(m: Move) -> bool {
+    matches!(
+        m,
+        Move::PieceMove {
+            target_piece: Some(_),
+            ..
+        }
+    )
+}
+
+/// Whether `m` resets the 50-move counter.
+fn is_zeroing(m: Move) -> bool {
+    match m {
+        Move::PieceMove {
+            origin_piece,
+            target_piece,
+            ..
+        } => target_piece.is_some() || origin_piece.piece_kind == PieceKind::Pawn,
+        _ => false,
+    }
+}
+
+fn is_mate(board: &Board) -> bool {
+    board.is_in_check() && board.get_legal_moves().is_empty()
+}
+
+#[derive(Debug)]
+pub enum SyzygyError {
+    Io(io::Error),
+    NoTables(String),
+    CorruptTable(String),
+}
+
+impl fmt::Display for SyzygyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Io(err) => {
+                write!(f, "I/O error: {err}")
+            }
+
+            Self::NoTables(paths) => {
+                write!(f, "No Syzygy tables found in '{paths}'")
+            }
+
+            Self::CorruptTable(path) => {
+                write!(f, "Corrupt Syzygy table: {path}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for SyzygyError {}
+
+impl From<io::Error> for SyzygyError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::table::fixture::write_cursed;
+    use super::*;
+
+    // Three-man tables in the Syzygy format, written by `table::fixture` from
+    // the tables of `tablebase::generate`. WDL for every three-man material
+    // with white stronger, DTZ for KQvK and KRvK only.
+    const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/syzygy");
+
+    /// A directory that is removed when dropped.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!("{}-{name}", std::process::id()));
+            fs::create_dir_all(&path).unwrap();
+            Self(path)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn board(fen: &str) -> Board {
+        Board::from_fen(fen).unwrap()
+    }
+
+    #[test]
+    fn finds_the_fixture_tables() {
+        let syzygy = Syzygy::load(FIXTURES).unwrap();
+
+        assert_eq!(syzygy.len(), 5);
+        assert_eq!(syzygy.max_pieces(), 3);
+    }
+
+    #[test]
+    fn probes_kqvk() {
+        let syzygy = Syzygy::load(FIXTURES).unwrap();
+
+        for (fen, wdl, dtz) in [
+            ("7k/8/8/8/8/8/8/KQ6 w - - 0 1", WdlScore::Win, None),
+            ("7k/8/8/8/8/8/8/KQ6 b - - 0 1", WdlScore::Loss, None),
+            ("k7/8/1K6/8/8/8/7Q/8 w - - 0 1", WdlScore::Win, Some(1)),
+            ("k7/8/1K6/8/8/8/8/6Q1 b - - 0 1", WdlScore::Loss, Some(-2)),
+            // The king takes the queen.
+            ("8/8/8/8/8/8/6Qk/K7 b - - 0 1", WdlScore::Draw, Some(0)),
+            // Stalemate.
+            ("k7/8/1Q6/8/8/8/8/7K b - - 0 1", WdlScore::Draw, Some(0)),
+            // Black has the queen, so the table is probed with colours swapped.
+            ("K7/8/1k6/8/8/8/7q/8 b - - 0 1", WdlScore::Win, Some(1)),
+        ] {
+            let board = board(fen);
+
+            assert_eq!(syzygy.probe_wdl(&board), Some(wdl), "{fen}");
+
+            if let Some(dtz) = dtz {
+                assert_eq!(syzygy.probe_dtz(&board), Some(dtz), "{fen}");
+            }
+        }
+    }
+
+    #[test]
+    fn probes_krvk() {
+        let syzygy = Syzygy::load(FIXTURES).unwrap();
+
+        for (fen, wdl, dtz) in [
+            ("k7/8/1K6/8/8/8/8/7R w - - 0 1", WdlScore::Win, 1),
+            ("k7/8/1K6/8/8/8/8/7R b - - 0 1", WdlScore::Loss, -2),
+            ("8/8/8/8/8/8/6Rk/K7 b - - 0 1", WdlScore::Draw, 0),
+        ] {
+            let board = board(fen);
+
+            assert_eq!(syzygy.probe_wdl(&board), Some(wdl), "{fen}");
+            assert_eq!(syzygy.probe_dtz(&board), Some(dtz), "{fen}");
+        }
+
+        let ranking = syzygy
+            .rank_root_moves(&board("k7/8/1K6/8/8/8/8/7R w - - 0 1"))
+            .unwrap();
+
+        assert!(ranking.by_dtz);
+        assert!(ranking.moves.iter().all(|&(_, rank)| rank >= 0));
+    }
+
+    #[test]
+    fn probes_kpvk() {
+        let syzygy = Syzygy::load(FIXTURES).unwrap();
+
+        for (fen, wdl) in [
+            ("4k3/8/4K3/4P3/8/8/8/8 w - - 0 1", WdlScore::Win),
+            ("4k3/8/4K3/4P3/8/8/8/8 b - - 0 1", WdlScore::Loss),
+            // Stalemate.
+            ("4k3/4P3/4K3/8/8/8/8/8 b - - 0 1", WdlScore::Draw),
+            // The rook pawn cannot drive the king out of the corner.
+            ("k7/8/8/8/8/8/P7/7K w - - 0 1", WdlScore::Draw),
+            ("8/8/8/8/4p3/4k3/8/4K3 b - - 0 1", WdlScore::Win),
+            ("8/8/8/8/4p3/4k3/8/4K3 w - - 0 1", WdlScore::Loss),
+        ] {
+            assert_eq!(syzygy.probe_wdl(&board(fen)), Some(wdl), "{fen}");
+        }
+
+        // Without a DTZ table the root moves are ranked by WDL.
+        let ranking = syzygy
+            .rank_root_moves(&board("4k3/8/4K3/4P3/8/8/8/8 w - - 0 1"))
+            .unwrap();
+
+        assert!(!ranking.by_dtz);
+        assert_eq!(ranking.moves[0].1, WIN_RANK);
+    }
+
+    #[test]
+    fn only_the_rook_promotion_wins() {
+        let syzygy = Syzygy::load(FIXTURES).unwrap();
+
+        // Promoting to a queen stalemates the king in the corner.
+        for (fen, uci) in [
+            ("8/k1P5/8/K7/8/8/8/8 w - - 0 1", "c7c8r"),
+            ("8/8/8/8/k7/8/K1p5/8 b - - 0 1", "c2c1r"),
+        ] {
+            let board = board(fen);
+
+            assert_eq!(syzygy.probe_wdl(&board), Some(WdlScore::Win), "{fen}");
+
+            let ranking = syzygy.rank_root_moves(&board).unwrap();
+            let winning: Vec<_> = ranking
+                .moves
+                .iter()
+                .filter(|&&(_, rank)| rank == WIN_RANK)
+                .map(|&(m, _)| m)
+                .collect();
+
+            assert_eq!(winning, [Move::from_uci(&board, uci).unwrap()], "{fen}");
+        }
+    }
+
+    #[test]
+    fn probes_cursed_wins_and_blessed_losses() {
+        let dir = TempDir::new("syzygy-cursed");
+        write_cursed(Path::new(FIXTURES), &dir.0, "KRvK");
+
+        let syzygy = Syzygy::load(dir.0.to_str().unwrap()).unwrap();
+
+        for (fen, wdl, dtz) in [
+            ("k7/8/1K6/8/8/8/8/7R w - - 0 1", WdlScore::CursedWin, 101),
+            ("k7/8/1K6/8/8/8/8/7R b - - 0 1", WdlScore::BlessedLoss, -102),
+            ("8/8/8/8/8/8/6Rk/K7 b - - 0 1", WdlScore::Draw, 0),
+        ] {
+            let board = board(fen);
+
+            assert_eq!(syzygy.probe_wdl(&board), Some(wdl), "{fen}");
+            assert_eq!(syzygy.probe_dtz(&board), Some(dtz), "{fen}");
+        }
+
+        // Only the mate wins outright, the other moves leave a blessed loss.
+        let board = board("k7/8/1K6/8/8/8/8/6R1 w - - 0 1");
+        let ranking = syzygy.rank_root_moves(&board).unwrap();
+
+        assert!(ranking.by_dtz);
+        assert_eq!(
+            ranking.moves[0],
+            (Move::from_uci(&board, "g1g8").unwrap(), WIN_RANK)
+        );
+        assert!(ranking.moves[1..]
+            .iter()
+            .all(|&(_, rank)| rank > 0 && rank < WIN_RANK - 100));
+    }
+
+    #[test]
+    fn missing_tables_are_not_probed() {
+        let syzygy = Syzygy::load(FIXTURES).unwrap();
+
+        assert_eq!(
+            syzygy.probe_wdl(&board("k7/8/8/8/8/8/8/KBN5 w - - 0 1")),
+            None
+        );
+        assert_eq!(
+            syzygy.probe_dtz(&board("4k3/8/4K3/4P3/8/8/8/8 b - - 0 1")),
+            None
+        );
+    }
+}
@@ -0,0 +1,959 @@
+use std::fs;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use super::{Material, SyzygyError, TbPosition, WdlScore};
+
+#[cfg(test)]
+pub(super) mod fixture;
+
+// Syzygy files store one value per position index, compressed with recursive
+// pairing followed by a canonical Huffman code. A file holds a table per side
+// to move (WDL files of unequal material) and per file of the leading pawn
+// (tables with pawns), each split into blocks of Huffman symbols with a sparse
+// index for finding the block of a position.
+//
+// Squares here count from a1 = 0 to h8 = 63, as in the files.
+
+const WDL_MAGIC: [u8; 4] = [0x71, 0xe8, 0x23, 0x5d];
+const DTZ_MAGIC: [u8; 4] = [0xd7, 0x66, 0x0c, 0xa5];
+
+pub(super) const MAX_PIECES: usize = 7;
+
+/// Flags of a table.
+const STM: u8 = 1;
+const MAPPED: u8 = 2;
+const WIN_PLIES: u8 = 4;
+const LOSS_PLIES: u8 = 8;
+const WIDE: u8 = 16;
+const SINGLE_VALUE: u8 = 128;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) enum TableKind {
+    Wdl,
+    Dtz,
+}
+
+impl TableKind {
+    pub(super) fn extension(self) -> &'static str {
+        match self {
+            Self::Wdl => "rtbw",
+            Self::Dtz => "rtbz",
+        }
+    }
+
+    fn magic(self) -> [u8; 4] {
+        match self {
+            Self::Wdl => WDL_MAGIC,
+            Self::Dtz => DTZ_MAGIC,
+        }
+    }
+}
+
+/// A DTZ probe result. DTZ tables only hold one side to move.
+pub(super) enum DtzProbe {
+    Value(i32),
+    ChangeSideToMove,
+}
+
+/// One compressed table: a side to move and, with pawns, a file of the leading
+/// pawn.
+#[derive(Default, Clone)]
+struct PairsData {
+    flags: u8,
+    pieces: [u8; MAX_PIECES],
+    group_len: [usize; MAX_PIECES + 1],
+    group_idx: [u64; MAX_PIECES + 1],
+    block_size: usize,
+    span: usize,
+    block_count: usize,
+    block_length_size: usize,
+    min_sym_len: u8,
+    /// Offsets into the file.
+    lowest_sym: usize,
+    btree: usize,
+    sparse_index: usize,
+    sparse_index_size: usize,
+    block_length: usize,
+    data: usize,
+    base64: Vec<u64>,
+    symlen: Vec<u8>,
+    /// DTZ value maps per WDL outcome, as offsets into the map.
+    map_idx: [usize; 4],
+}
+
+pub(super) struct Table {
+    kind: TableKind,
+    material: Material,
+    bytes: Vec<u8>,
+    /// Indexed by side to move, then file of the leading pawn.
+    pairs: Vec<Vec<PairsData>>,
+    map: usize,
+}
+
+/// Little endian reads that fail instead of panicking on truncated files.
+fn read_u8(bytes: &[u8], offset: usize) -> Option<u8> {
+    bytes.get(offset).copied()
+}
+
+fn read_u16(bytes: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(
+        bytes.get(offset..offset + 2)?.try_into().unwrap(),
+    ))
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(
+        bytes.get(offset..offset + 4)?.try_into().unwrap(),
+    ))
+}
+
+/// Huffman codes are read ahead four bytes at a time, which may run past the
+/// end of the last block, so missing bytes read as zeros.
+fn read_u32_be(bytes: &[u8], offset: usize) -> u32 {
+    let mut word = [0; 4];
+
+    for (i, byte) in word.iter_mut().enumerate() {
+        *byte = bytes.get(offset + i).copied().unwrap_or(0);
+    }
+
+    u32::from_be_bytes(word)
+}
+
+impl Table {
+    pub(super) fn load(
+        path: &Path,
+        kind: TableKind,
+        material: &Material,
+    ) -> Result<Self, SyzygyError> {
+        let bytes = fs::read(path)?;
+        let corrupt = || SyzygyError::CorruptTable(path.display().to_string());
+
+        if bytes.get(0..4) != Some(&kind.magic()[..]) {
+            return Err(corrupt());
+        }
+
+        let mut table = Self {
+            kind,
+            material: material.clone(),
+            bytes,
+            pairs: Vec::new(),
+            map: 0,
+        };
+
+        table.read_header().ok_or_else(corrupt)?;
+        Ok(table)
+    }
+
+    fn sides(&self) -> usize {
+        if self.kind == TableKind::Wdl && !self.material.is_symmetric() {
+            2
+        } else {
+            1
+        }
+    }
+
+    fn files(&self) -> usize {
+        if self.material.has_pawns() {
+            4
+        } else {
+            1
+        }
+    }
+
+    fn read_header(&mut self) -> Option<()> {
+        let material = &self.material;
+        let bytes = &self.bytes;
+        let sides = self.sides();
+        let files = self.files();
+        let piece_count = material.piece_count();
+        let both_pawns = material.has_pawns() && material.pawn_count[1] > 0;
+
+        let mut pairs = vec![vec![PairsData::default(); files]; sides];
+
+        // The first byte only repeats what the name says.
+        let mut offset = 5;
+
+        for file in 0..files {
+            let order = read_u8(bytes, offset)?;
+            let pawn_order = if both_pawns {
+                read_u8(bytes, offset + 1)?
+            } else {
+                0xff
+            };
+
+            let orders = [
+                [order & 0xf, pawn_order & 0xf],
+                [order >> 4, pawn_order >> 4],
+            ];
+
+            offset += 1 + both_pawns as usize;
+
+            for k in 0..piece_count {
+                let piece = read_u8(bytes, offset)?;
+
+                for (side, side_pairs) in pairs.iter_mut().enumerate() {
+                    side_pairs[file].pieces[k] = if side == 1 { piece >> 4 } else { piece & 0xf };
+                }
+
+                offset += 1;
+            }
+
+            for (side, side_pairs) in pairs.iter_mut().enumerate() {
+                set_groups(&mut side_pairs[file], material, orders[side], file);
+            }
+        }
+
+        offset += offset & 1;
+
+        for file in 0..files {
+            for side_pairs in pairs.iter_mut() {
+                offset = set_sizes(&mut side_pairs[file], bytes, offset)?;
+            }
+        }
+
+        if self.kind == TableKind::Dtz {
+            self.map = offset;
+
+            for file_pairs in pairs[0].iter_mut() {
+                if file_pairs.flags & MAPPED == 0 {
+                    continue;
+                }
+
+                if file_pairs.flags & WIDE != 0 {
+                    offset += offset & 1;
+
+                    for i in 0..4 {
+                        file_pairs.map_idx[i] = (offset - self.map) / 2 + 1;
+                        offset += 2 * read_u16(bytes, offset)? as usize + 2;
+                    }
+                } else {
+                    for i in 0..4 {
+                        file_pairs.map_idx[i] = offset - self.map + 1;
+                        offset += read_u8(bytes, offset)? as usize + 1;
+                    }
+                }
+            }
+
+            offset += offset & 1;
+        }
+
+        for file in 0..files {
+            for side_pairs in pairs.iter_mut() {
+                side_pairs[file].sparse_index = offset;
+                offset += side_pairs[file].sparse_index_size * 6;
+            }
+        }
+
+        for file in 0..files {
+            for side_pairs in pairs.iter_mut() {
+                side_pairs[file].block_length = offset;
+                offset += side_pairs[file].block_length_size * 2;
+            }
+        }
+
+        for file in 0..files {
+            for side_pairs in pairs.iter_mut() {
+                offset = (offset + 0x3f) & !0x3f;
+                side_pairs[file].data = offset;
+                offset += side_pairs[file].block_count * side_pairs[file].block_size;
+            }
+        }
+
+        // The last block may stop short of its full size.
+        if pairs.iter().flatten().any(|pairs| pairs.data > bytes.len()) {
+            return None;
+        }
+
+        self.pairs = pairs;
+        Some(())
+    }
+
+    /// The WDL value of `position`, which must have this table's material.
+    pub(super) fn probe_wdl(&self, position: &TbPosition) -> Option<WdlScore> {
+        let (pairs, _, index) = self.index(position)?;
+        let value = self.decompress(pairs, index)?;
+
+        WdlScore::from_i32(value as i32 - 2)
+    }
+
+    /// The DTZ value of `position` in plies, without the sign of `wdl`.
+    pub(super) fn probe_dtz(&self, position: &TbPosition, wdl: WdlScore) -> Option<DtzProbe> {
+        let (pairs, file, index) = match self.index(position) {
+            Some(found) => found,
+            None if self.side_to_move_missing(position) => return Some(DtzProbe::ChangeSideToMove),
+            None => return None,
+        };
+
+        let flags = self.pairs[0][file].flags;
+        let mut value = self.decompress(pairs, index)? as usize;
+
+        if flags & MAPPED != 0 {
+            let map_idx = self.pairs[0][file].map_idx;
+
+            let slot = match wdl {
+                WdlScore::Win | WdlScore::Draw => 0,
+                WdlScore::Loss => 1,
+                WdlScore::CursedWin => 2,
+                WdlScore::BlessedLoss => 3,
+            };
+
+            value = if flags & WIDE != 0 {
+                read_u16(&self.bytes, self.map + 2 * (map_idx[slot] + value))? as usize
+            } else {
+                read_u8(&self.bytes, self.map + map_idx[slot] + value)? as usize
+            };
+        }
+
+        let mut plies = value as i32;
+
+        if (wdl == WdlScore::Win && flags & WIN_PLIES == 0)
+            || (wdl == WdlScore::Loss && flags & LOSS_PLIES == 0)
+            || wdl == WdlScore::CursedWin
+            || wdl == WdlScore::BlessedLoss
+        {
+            plies *= 2;
+        }
+
+        Some(DtzProbe::Value(plies + 1))
+    }
+
+    /// Whether a DTZ table only has the other side to move than `position`.
+    fn side_to_move_missing(&self, position: &TbPosition) -> bool {
+        self.kind == TableKind::Dtz && self.orient(position).is_some_and(|o| !o.stored)
+    }
+
+    /// How `position` maps onto the table: whether the colours are swapped,
+    /// the side to move in the table and, for DTZ, whether that side is stored.
+    fn orient(&self, position: &TbPosition) -> Option<Orientation> {
+        let symmetric_black = self.material.is_symmetric() && !position.white_to_move;
+        let black_stronger = position.material != self.material.name;
+        let flip = symmetric_black || black_stronger;
+        let side = (flip ^ !position.white_to_move) as usize;
+
+        let mut squares = Vec::with_capacity(position.pieces.len());
+        let mut file = 0;
+
+        if self.material.has_pawns() {
+            let flip_color = if flip { 8 } else { 0 };
+            let lead = *self.pairs.first()?.first()?.pieces.first()? ^ flip_color;
+
+            squares.extend(
+                position
+                    .pieces
+                    .iter()
+                    .filter(|&&(piece, _)| piece == lead)
+                    .map(|&(_, square)| if flip { square ^ 56 } else { square }),
+            );
+
+            let max =
+                (0..squares.len()).max_by_key(|&i| encoding().map_pawns[squares[i] as usize])?;
+            squares.swap(0, max);
+
+            file = (squares[0] % 8) as usize;
+
+            if file > 3 {
+                file = 7 - file;
+            }
+        }
+
+        let stored = match self.kind {
+            TableKind::Wdl => true,
+            TableKind::Dtz => {
+                (self.pairs[0][file].flags & STM) as usize == side
+                    || (self.material.is_symmetric() && !self.material.has_pawns())
+            }
+        };
+
+        Some(Orientation {
+            flip,
+            side,
+            file,
+            lead_squares: squares,
+            stored,
+        })
+    }
+
+    /// The compressed table, leading pawn file and index of `position`, or
+    /// `None` when a DTZ table does not store its side to move.
+    fn index(&self, position: &TbPosition) -> Option<(&PairsData, usize, u64)> {
+        let orientation = self.orient(position)?;
+
+        if !orientation.stored {
+            return None;
+        }
+
+        let Orientation {
+            flip,
+            side,
+            file,
+            lead_squares,
+            ..
+        } = orientation;
+
+        let flip_color = if flip { 8 } else { 0 };
+        let flip_squares = if flip { 56 } else { 0 };
+        let lead_count = lead_squares.len();
+
+        let pairs = self.pairs.get(side % self.pairs.len())?.get(file)?;
+
+        // Lead pawns first, then the other pieces in the table's order.
+        let mut squares = lead_squares;
+        let mut pieces = vec![0; lead_count];
+
+        let lead_piece = if self.material.has_pawns() {
+            Some(pairs.pieces[0] ^ flip_color)
+        } else {
+            None
+        };
+
+        for &(piece, square) in &position.pieces {
+            if Some(piece) != lead_piece {
+                squares.push(square ^ flip_squares);
+                pieces.push(piece ^ flip_color);
+            }
+        }
+
+        let size = squares.len();
+
+        if size != self.material.piece_count() {
+            return None;
+        }
+
+        for i in lead_count..size.saturating_sub(1) {
+            for j in i + 1..size {
+                if pairs.pieces[i] == pieces[j] {
+                    pieces.swap(i, j);
+                    squares.swap(i, j);
+                    break;
+                }
+            }
+        }
+
+        Some((
+            pairs,
+            file,
+            encode(pairs, &self.material, &mut squares, lead_count),
+        ))
+    }
+
+    /// The value stored at `index`.
+    fn decompress(&self, pairs: &PairsData, index: u64) -> Option<u16> {
+        let bytes = &self.bytes;
+
+        if pairs.flags & SINGLE_VALUE != 0 {
+            return Some(pairs.min_sym_len as u16);
+        }
+
+        let k = (index / pairs.span as u64) as usize;
+
+        if k >= pairs.sparse_index_size {
+            return None;
+        }
+
+        let mut block = read_u32(bytes, pairs.sparse_index + 6 * k)? as usize;
+        let mut offset = read_u16(bytes, pairs.sparse_index + 6 * k + 4)? as i64;
+
+        offset += (index % pairs.span as u64) as i64 - (pairs.span / 2) as i64;
+
+        let block_length = |block: usize| -> Option<i64> {
+            if block >= pairs.block_length_size {
+                return None;
+            }
+
+            Some(read_u16(bytes, pairs.block_length + 2 * block)? as i64)
+        };
+
+        while offset < 0 {
+            block = block.checked_sub(1)?;
+            offset += block_length(block)? + 1;
+        }
+
+        while offset > block_length(block)? {
+            offset -= block_length(block)? + 1;
+            block += 1;
+        }
+
+        let mut ptr = pairs.data + block * pairs.block_size;
+        let mut buffer =
+            (read_u32_be(bytes, ptr) as u64) << 32 | read_u32_be(bytes, ptr + 4) as u64;
+        let mut buffer_size = 64;
+        ptr += 8;
+
+        let min_sym_len = pairs.min_sym_len as usize;
+
+        let mut sym = loop {
+            let mut len = 0;
+
+            while buffer < *pairs.base64.get(len)? {
+                len += 1;
+            }
+
+            let sym = ((buffer - pairs.base64[len]) >> (64 - len - min_sym_len)) as usize
+                + read_u16(bytes, pairs.lowest_sym + 2 * len)? as usize;
+
+            let sym_len = *pairs.symlen.get(sym)? as i64;
+
+            if offset < sym_len + 1 {
+                break sym;
+            }
+
+            offset -= sym_len + 1;
+            len += min_sym_len;
+            buffer <<= len;
+            buffer_size -= len;
+
+            if buffer_size <= 32 {
+                buffer_size += 32;
+                buffer |= (read_u32_be(bytes, ptr) as u64) << (64 - buffer_size);
+                ptr += 4;
+            }
+        };
+
+        // Expand the pairs down to the single value at `offset`.
+        while pairs.symlen[sym] != 0 {
+            let (left, right) = self.btree(pairs, sym)?;
+            let left_len = *pairs.symlen.get(left)? as i64;
+
+            if offset < left_len + 1 {
+                sym = left;
+            } else {
+                offset -= left_len + 1;
+                sym = right;
+            }
+        }
+
+        self.btree(pairs, sym).map(|(left, _)| left as u16)
+    }
+
+    /// The two 12-bit halves of a symbol's entry: the pair it expands to, or
+    /// its value and 0xfff.
+    fn btree(&self, pairs: &PairsData, sym: usize) -> Option<(usize, usize)> {
+        btree_entry(&self.bytes, pairs.btree, sym)
+    }
+}
+
+struct Orientation {
+    flip: bool,
+    side: usize,
+    file: usize,
+    lead_squares: Vec<u8>,
+    stored: bool,
+}
+
+fn btree_entry(bytes: &[u8], btree: usize, sym: usize) -> Option<(usize, usize)> {
+    let entry = bytes.get(btree + 3 * sym..btree + 3 * sym + 3)?;
+
+    let left = ((entry[1] as usize & 0xf) << 8) | entry[0] as usize;
+    let right = ((entry[2] as usize) << 4) | (entry[1] as usize >> 4);
+
+    Some((left, right))
+}
+
+/// Splits the pieces into the groups they are encoded in and works out the
+/// multiplier of each group from the order stored in the file.
+fn set_groups(pairs: &mut PairsData, material: &Material, order: [u8; 2], file: usize) {
+    let encoding = encoding();
+    let piece_count = material.piece_count();
+
+    let mut n = 0;
+    let mut first_len: i32 = if material.has_pawns() {
+        0
+    } else if material.has_unique_pieces() {
+        3
+    } else {
+        2
+    };
+
+    pairs.group_len[0] = 1;
+
+    for i in 1..piece_count {
+        first_len -= 1;
+
+        if first_len > 0 || pairs.pieces[i] == pairs.pieces[i - 1] {
+            pairs.group_len[n] += 1;
+        } else {
+            n += 1;
+            pairs.group_len[n] = 1;
+        }
+    }
+
+    n += 1;
+    pairs.group_len[n] = 0;
+
+    let both_pawns = material.has_pawns() && material.pawn_count[1] > 0;
+    let mut next = if both_pawns { 2 } else { 1 };
+    let mut free_squares =
+        64 - pairs.group_len[0] - if both_pawns { pairs.group_len[1] } else { 0 };
+    let mut index: u64 = 1;
+    let mut k = 0;
+
+    while next < n || k == order[0] || k == order[1] {
+        if k == order[0] {
+            pairs.group_idx[0] = index;
+
+            index *= if material.has_pawns() {
+                encoding.lead_pawns_size[pairs.group_len[0]][file]
+            } else if material.has_unique_pieces() {
+                31332
+            } else {
+                462
+            };
+        } else if k == order[1] {
+            pairs.group_idx[1] = index;
+            index *= encoding.binomial[pairs.group_len[1]][48 - pairs.group_len[0]];
+        } else {
+            pairs.group_idx[next] = index;
+            index *= encoding.binomial[pairs.group_len[next]][free_squares];
+            free_squares -= pairs.group_len[next];
+            next += 1;
+        }
+
+        k += 1;
+    }
+
+    pairs.group_idx[n] = index;
+}
+
+/// Reads the compression parameters of one table and returns the offset after
+/// them.
+fn set_sizes(pairs: &mut PairsData, bytes: &[u8], mut offset: usize) -> Option<usize> {
+    pairs.flags = read_u8(bytes, offset)?;
+    offset += 1;
+
+    if pairs.flags & SINGLE_VALUE != 0 {
+        pairs.min_sym_len = read_u8(bytes, offset)?;
+        return Some(offset + 1);
+    }
+
+    let groups = pairs.group_len.iter().position(|&len| len == 0)?;
+    let table_size = pairs.group_idx[groups];
+
+    pairs.block_size = 1 << read_u8(bytes, offset)?;
+    pairs.span = 1 << read_u8(bytes, offset + 1)?;
+    pairs.sparse_index_size = table_size.div_ceil(pairs.span as u64) as usize;
+
+    let padding = read_u8(bytes, offset + 2)? as usize;
+    pairs.block_count = read_u32(bytes, offset + 3)? as usize;
+    pairs.block_length_size = pairs.block_count + padding;
+
+    let max_sym_len = read_u8(bytes, offset + 7)?;
+    pairs.min_sym_len = read_u8(bytes, offset + 8)?;
+    offset += 9;
+
+    if pairs.min_sym_len == 0 || max_sym_len < pairs.min_sym_len || max_sym_len > 32 {
+        return None;
+    }
+
+    // Canonical Huffman codes: longer codes have lower values, so each length
+    // gets the lowest 64-bit left-aligned code of that length.
+    pairs.lowest_sym = offset;
+    let lengths = (max_sym_len - pairs.min_sym_len + 1) as usize;
+    pairs.base64 = vec![0; lengths];
+
+    for i in (0..lengths - 1).rev() {
+        let lowest = read_u16(bytes, offset + 2 * i)? as u64;
+        let next_lowest = read_u16(bytes, offset + 2 * (i + 1))? as u64;
+
+        pairs.base64[i] = (pairs.base64[i + 1] + lowest).checked_sub(next_lowest)? / 2;
+    }
+
+    for (i, base) in pairs.base64.iter_mut().enumerate() {
+        *base <<= 64 - i - pairs.min_sym_len as usize;
+    }
+
+    offset += 2 * lengths;
+
+    let symbols = read_u16(bytes, offset)? as usize;
+    offset += 2;
+    pairs.btree = offset;
+
+    pairs.symlen = vec![0; symbols];
+    let mut visited = vec![false; symbols];
+
+    for sym in 0..symbols {
+        if !visited[sym] {
+            pairs.symlen[sym] = set_symlen(pairs, bytes, sym, &mut visited)?;
+        }
+    }
+
+    Some(offset + 3 * symbols + (symbols & 1))
+}
+
+/// How many values minus one a symbol expands to.
+fn set_symlen(pairs: &mut PairsData, bytes: &[u8], sym: usize, visited: &mut [bool]) -> Option<u8> {
+    visited[sym] = true;
+
+    let (left, right) = btree_entry(bytes, pairs.btree, sym)?;
+
+    if right == 0xfff {
+        return Some(0);
+    }
+
+    for child in [left, right] {
+        if !*visited.get(child)? {
+            pairs.symlen[child] = set_symlen(pairs, bytes, child, visited)?;
+        }
+    }
+
+    Some(
+        pairs.symlen[left]
+            .wrapping_add(pairs.symlen[right])
+            .wrapping_add(1),
+    )
+}
+
+/// The index of a position whose squares are in the table's piece order, with
+/// the leading pawns, `lead_count` of them, first.
+fn encode(pairs: &PairsData, material: &Material, squares: &mut [u8], lead_count: usize) -> u64 {
+    let encoding = encoding();
+    let size = squares.len();
+
+    if squares[0] % 8 > 3 {
+        for square in squares.iter_mut() {
+            *square ^= 7;
+        }
+    }
+
+    let mut index: u64;
+
+    if material.has_pawns() {
+        index = encoding.lead_pawn_idx[lead_count][squares[0] as usize];
+
+        squares[1..lead_count].sort_by_key(|&square| encoding.map_pawns[square as usize]);
+
+        for (i, &square) in squares.iter().enumerate().take(lead_count).skip(1) {
+            index += encoding.binomial[i][encoding.map_pawns[square as usize] as usize];
+        }
+    } else {
+        if squares[0] / 8 > 3 {
+            for square in squares.iter_mut() {
+                *square ^= 56;
+            }
+        }
+
+        // The first piece of the leading group off the a1-h8 diagonal goes
+        // below it.
+        for i in 0..pairs.group_len[0] {
+            let off = off_diagonal(squares[i]);
+
+            if off == 0 {
+                continue;
+            }
+
+            if off > 0 {
+                for square in squares[i..].iter_mut() {
+                    *square = ((*square >> 3) | (*square << 3)) & 63;
+                }
+            }
+
+            break;
+        }
+
+        index = if material.has_unique_pieces() {
+            encode_unique(encoding, squares)
+        } else {
+            encoding.map_kk[encoding.map_a1d1d4[squares[0] as usize] as usize][squares[1] as usize]
+        };
+    }
+
+    index *= pairs.group_idx[0];
+
+    let mut remaining_pawns = material.has_pawns() && material.pawn_count[1] > 0;
+    let mut start = pairs.group_len[0];
+    let mut next = 1;
+
+    while pairs.group_len[next] != 0 {
+        let end = start + pairs.group_len[next];
+        squares[start..end].sort_unstable();
+
+        let mut n = 0;
+
+        for i in start..end {
+            let adjust = squares[..start].iter().filter(|&&s| squares[i] > s).count();
+            let square = squares[i] as usize - adjust - 8 * remaining_pawns as usize;
+            n += encoding.binomial[i - start + 1][square];
+        }
+
+        remaining_pawns = false;
+        index += n * pairs.group_idx[next];
+        start = end;
+        next += 1;
+    }
+
+    debug_assert_eq!(start, size);
+    index
+}
+
+/// The leading group of three unique pieces, the first in the a1-d1-d4
+/// triangle.
+fn encode_unique(encoding: &Encoding, squares: &[u8]) -> u64 {
+    let [s0, s1, s2] = [squares[0], squares[1], squares[2]].map(|s| s as u64);
+    let adjust1 = (s1 > s0) as u64;
+    let adjust2 = (s2 > s0) as u64 + (s2 > s1) as u64;
+    let rank = |s: u64| s / 8;
+
+    if off_diagonal(squares[0]) != 0 {
+        (encoding.map_a1d1d4[s0 as usize] * 63 + (s1 - adjust1)) * 62 + s2 - adjust2
+    } else if off_diagonal(squares[1]) != 0 {
+        (6 * 63 + rank(s0) * 28 + encoding.map_b1h1h7[s1 as usize]) * 62 + s2 - adjust2
+    } else if off_diagonal(squares[2]) != 0 {
+        6 * 63 * 62
+            + 4 * 28 * 62
+            + rank(s0) * 7 * 28
+            + (rank(s1) - adjust1) * 28
+            + encoding.map_b1h1h7[s2 as usize]
+    } else {
+        6 * 63 * 62
+            + 4 * 28 * 62
+            + 4 * 7 * 28
+            + rank(s0) * 7 * 6
+            + (rank(s1) - adjust1) * 6
+            + (rank(s2) - adjust2)
+    }
+}
+
+/// Rank minus file: negative below the a1-h8 diagonal, positive above it.
+fn off_diagonal(square: u8) -> i32 {
+    (square / 8) as i32 - (square % 8) as i32
+}
+
+/// Lookup tables of the position encoding.
+struct Encoding {
+    /// `binomial[k][n]` ways to choose k of n.
+    binomial: [[u64; 64]; MAX_PIECES],
+    /// Pawn squares a2-h7 to 0..47, highest for the pawn that leads.
+    map_pawns: [u64; 64],
+    lead_pawn_idx: [[u64; 64]; MAX_PIECES],
+    lead_pawns_size: [[u64; 4]; MAX_PIECES],
+    /// Squares below the a1-h8 diagonal to 0..27.
+    map_b1h1h7: [u64; 64],
+    /// The a1-d1-d4 triangle to 0..9, the diagonal last.
+    map_a1d1d4: [u64; 64],
+    /// The 462 placements of two kings with the first in the triangle.
+    map_kk: [[u64; 64]; 10],
+}
+
+fn encoding() -> &'static Encoding {
+    static ENCODING: OnceLock<Encoding> = OnceLock::new();
+
+    ENCODING.get_or_init(|| {
+        let mut encoding = Encoding {
+            binomial: [[0; 64]; MAX_PIECES],
+            map_pawns: [0; 64],
+            lead_pawn_idx: [[0; 64]; MAX_PIECES],
+            lead_pawns_size: [[0; 4]; MAX_PIECES],
+            map_b1h1h7: [0; 64],
+            map_a1d1d4: [0; 64],
+            map_kk: [[0; 64]; 10],
+        };
+
+        let mut code = 0;
+
+        for square in 0..64 {
+            if off_diagonal(square) < 0 {
+                encoding.map_b1h1h7[square as usize] = code;
+                code += 1;
+            }
+        }
+
+        let mut diagonal = Vec::new();
+        code = 0;
+
+        for square in 0..=27u8 {
+            if off_diagonal(square) < 0 && square % 8 <= 3 {
+                encoding.map_a1d1d4[square as usize] = code;
+                code += 1;
+            } else if off_diagonal(square) == 0 && square % 8 <= 3 {
+                diagonal.push(square);
+            }
+        }
+
+        for square in diagonal {
+            encoding.map_a1d1d4[square as usize] = code;
+            code += 1;
+        }
+
+        let distance = |a: u8, b: u8| {
+            ((a / 8) as i32 - (b / 8) as i32)
+                .abs()
+                .max(((a % 8) as i32 - (b % 8) as i32).abs())
+        };
+
+        let mut both_on_diagonal = Vec::new();
+        code = 0;
+
+        for idx in 0..10 {
+            for s1 in 0..=27u8 {
+                // b1 is mapped to 0, like every square outside the triangle.
+                if encoding.map_a1d1d4[s1 as usize] != idx || (idx == 0 && s1 != 1) {
+                    continue;
+                }
+
+                for s2 in 0..64u8 {
+                    if distance(s1, s2) <= 1 {
+                        continue;
+                    }
+
+                    if off_diagonal(s1) == 0 && off_diagonal(s2) > 0 {
+                        continue;
+                    }
+
+                    if off_diagonal(s1) == 0 && off_diagonal(s2) == 0 {
+                        both_on_diagonal.push((idx, s2));
+                    } else {
+                        encoding.map_kk[idx as usize][s2 as usize] = code;
+                        code += 1;
+                    }
+                }
+            }
+        }
+
+        for (idx, s2) in both_on_diagonal {
+            encoding.map_kk[idx as usize][s2 as usize] = code;
+            code += 1;
+        }
+
+        encoding.binomial[0][0] = 1;
+
+        for n in 1..64 {
+            for k in 0..MAX_PIECES.min(n + 1) {
+                encoding.binomial[k][n] = if k > 0 {
+                    encoding.binomial[k - 1][n - 1]
+                } else {
+                    0
+                } + if k < n {
+                    encoding.binomial[k][n - 1]
+                } else {
+                    0
+                };
+            }
+        }
+
+        let mut available = 47;
+
+        for lead_count in 1..MAX_PIECES - 1 {
+            for file in 0..4u8 {
+                let mut idx = 0;
+
+                for rank in 1..7u8 {
+                    let square = rank * 8 + file;
+
+                    if lead_count == 1 {
+                        encoding.map_pawns[square as usize] = available;
+                        encoding.map_pawns[(square ^ 7) as usize] = available - 1;
+                        available = available.saturating_sub(2);
+                    }
+
+                    encoding.lead_pawn_idx[lead_count][square as usize] = idx;
+                    idx += encoding.binomial[lead_count - 1]
+                        [encoding.map_pawns[square as usize] as usize];
+                }
+
+                encoding.lead_pawns_size[lead_count][file as usize] = idx;
+            }
+        }
+
+        encoding
+    })
+}
@@ -0,0 +1,532 @@
+// Writes the three-man fixtures in tests/fixtures/syzygy from the tables of
+// `tablebase::generate`, since real Syzygy files cannot be fetched by the
+// tests. Every legal position is looked up in the generated table and stored
+// at the index the reader computes for it, so the files only check the reader
+// against itself for the layout, but against an independent solver for the
+// values.
+//
+// WDL values are compressed with a fixed code that pairs up runs of equal
+// values, which exercises the symbol tree. DTZ values are stored one symbol
+// per value, from the distance to mate: without pawns or anything for the
+// losing side to take, the next zeroing move is the mate.
+
+use std::path::Path;
+
+use super::*;
+use crate::chess::board::Board;
+use crate::chess::syzygy::TbPosition;
+use crate::chess::tablebase::{Dtm, Signature, Tablebase, Wdl};
+
+/// The fixture tables with their pieces in the order they are encoded, and
+/// whether they have a DTZ table. The minor pieces are there for promotions.
+const FIXTURES: [(&str, [u8; 3], bool); 5] = [
+    ("KQvK", [6, 5, 14], true),
+    ("KRvK", [4, 14, 6], true),
+    ("KBvK", [6, 3, 14], false),
+    ("KNvK", [6, 2, 14], false),
+    ("KPvK", [1, 6, 14], false),
+];
+
+const BLOCK_SIZE_LOG: u8 = 6;
+const SPAN_LOG: u8 = 7;
+
+fn fixture(name: &str) -> ([u8; 3], bool) {
+    let &(_, pieces, dtz) = FIXTURES
+        .iter()
+        .find(|(fixture, ..)| *fixture == name)
+        .expect("not a fixture table");
+
+    (pieces, dtz)
+}
+
+/// Writes the WDL table and, where the fixture has one, the DTZ table of
+/// `name` to `dir`.
+fn write_fixture(dir: &Path, name: &str) {
+    let (pieces, dtz) = fixture(name);
+    let material = Material::parse(name).unwrap();
+
+    let mut tablebase = Tablebase::new();
+    tablebase.generate(&Signature::parse(name).unwrap());
+
+    let wdl = values(
+        TableKind::Wdl,
+        &material,
+        &pieces,
+        |board| match tablebase.probe_wdl(board)? {
+            Wdl::Loss => Some(0),
+            Wdl::Draw => None,
+            Wdl::Win => Some(4),
+        },
+    );
+
+    write_wdl(dir, name, &pieces, &wdl);
+
+    // The reader adds one to every stored distance.
+    if dtz {
+        let dtz = values(TableKind::Dtz, &material, &pieces, |board| {
+            let plies = match tablebase.probe_dtm(board)? {
+                Dtm::Win(plies) => plies,
+                _ => return None,
+            };
+
+            Some(plies as u16 - 1)
+        });
+
+        write_dtz(dir, name, &pieces, WIN_PLIES | LOSS_PLIES, &dtz);
+    }
+}
+
+/// Rewrites the fixture tables of `name` from `fixtures` to `dir` with every
+/// win cursed and every loss blessed, which three-man tables never have.
+pub(in crate::chess::syzygy) fn write_cursed(fixtures: &Path, dir: &Path, name: &str) {
+    let (pieces, _) = fixture(name);
+    let material = Material::parse(name).unwrap();
+
+    let mut wdl = stored_values(fixtures, TableKind::Wdl, name, &material);
+
+    for value in wdl.iter_mut().flatten().flatten() {
+        *value = match *value {
+            0 => 1,
+            4 => 3,
+            value => value,
+        };
+    }
+
+    write_wdl(dir, name, &pieces, &wdl);
+
+    // Cursed values always count moves rather than plies, and the stored wins
+    // are all mates in an odd number of plies.
+    let mut dtz = stored_values(fixtures, TableKind::Dtz, name, &material);
+
+    for value in dtz.iter_mut().flatten().flatten() {
+        *value /= 2;
+    }
+
+    write_dtz(dir, name, &pieces, 0, &dtz);
+}
+
+fn write_wdl(dir: &Path, name: &str, pieces: &[u8], values: &[Vec<Vec<u16>>]) {
+    let material = Material::parse(name).unwrap();
+    let bytes = write(TableKind::Wdl, &material, pieces, 0, values, Coding::Pairs);
+
+    fs::write(dir.join(format!("{name}.rtbw")), bytes).unwrap();
+}
+
+fn write_dtz(dir: &Path, name: &str, pieces: &[u8], flags: u8, values: &[Vec<Vec<u16>>]) {
+    let material = Material::parse(name).unwrap();
+    let max = values
+        .iter()
+        .flatten()
+        .flatten()
+        .copied()
+        .max()
+        .unwrap_or(0);
+    let bits = (u16::BITS - max.leading_zeros()).max(1) as u8;
+    let bytes = write(
+        TableKind::Dtz,
+        &material,
+        pieces,
+        flags,
+        values,
+        Coding::Plain(bits),
+    );
+
+    fs::write(dir.join(format!("{name}.rtbz")), bytes).unwrap();
+}
+
+/// Every value of a table file, by side to move, file of the leading pawn and
+/// position index.
+fn stored_values(
+    dir: &Path,
+    kind: TableKind,
+    name: &str,
+    material: &Material,
+) -> Vec<Vec<Vec<u16>>> {
+    let path = dir.join(format!("{name}.{}", kind.extension()));
+    let table = Table::load(&path, kind, material).unwrap();
+
+    table
+        .pairs
+        .iter()
+        .map(|side| {
+            side.iter()
+                .map(|pairs| {
+                    (0..table_size(pairs))
+                        .map(|index| table.decompress(pairs, index).unwrap())
+                        .collect()
+                })
+                .collect()
+        })
+        .collect()
+}
+
+fn table_size(pairs: &PairsData) -> u64 {
+    let groups = pairs.group_len.iter().position(|&len| len == 0).unwrap();
+    pairs.group_idx[groups]
+}
+
+/// A table that only knows how to index positions, for laying out the values.
+fn indexer(kind: TableKind, material: &Material, pieces: &[u8]) -> Table {
+    let mut table = Table {
+        kind,
+        material: material.clone(),
+        bytes: Vec::new(),
+        pairs: Vec::new(),
+        map: 0,
+    };
+
+    let both_pawns = material.has_pawns() && material.pawn_count[1] > 0;
+    let order = [0, if both_pawns { 1 } else { 0xf }];
+
+    table.pairs = (0..table.sides())
+        .map(|_| {
+            (0..table.files())
+                .map(|file| {
+                    let mut pairs = PairsData::default();
+                    pairs.pieces[..pieces.len()].copy_from_slice(pieces);
+                    set_groups(&mut pairs, material, order, file);
+                    pairs
+                })
+                .collect()
+        })
+        .collect();
+
+    table
+}
+
+/// The values of the table indexed by side to move, file of the leading pawn
+/// and position index, with `value` giving `None` for a draw.
+fn values(
+    kind: TableKind,
+    material: &Material,
+    pieces: &[u8],
+    value: impl Fn(&Board) -> Option<u16>,
+) -> Vec<Vec<Vec<u16>>> {
+    let table = indexer(kind, material, pieces);
+    let draw = match kind {
+        TableKind::Wdl => 2,
+        TableKind::Dtz => 0,
+    };
+
+    let mut values: Vec<Vec<Vec<u16>>> = table
+        .pairs
+        .iter()
+        .map(|side| {
+            side.iter()
+                .map(|pairs| vec![draw; table_size(pairs) as usize])
+                .collect()
+        })
+        .collect();
+
+    let mut squares = Vec::with_capacity(pieces.len());
+
+    for_each_placement(pieces, &mut squares, &mut |squares| {
+        for white_to_move in [true, false] {
+            let board = Board::from_fen(&fen(pieces, squares, white_to_move)).unwrap();
+
+            let mut other_side = board.clone();
+            other_side.make_null_move();
+
+            if other_side.is_in_check() {
+                continue;
+            }
+
+            let position = TbPosition::from_board(&board).unwrap();
+
+            if let Some((_, file, index)) = table.index(&position) {
+                let side = table.orient(&position).unwrap().side;
+                values[side][file][index as usize] = value(&board).unwrap_or(draw);
+            }
+        }
+    });
+
+    values
+}
+
+fn for_each_placement(pieces: &[u8], squares: &mut Vec<u8>, f: &mut impl FnMut(&[u8])) {
+    if squares.len() == pieces.len() {
+        f(squares);
+        return;
+    }
+
+    let pawn = pieces[squares.len()] & 7 == 1;
+
+    for square in 0..64 {
+        if squares.contains(&square) || (pawn && !(8..56).contains(&square)) {
+            continue;
+        }
+
+        squares.push(square);
+        for_each_placement(pieces, squares, f);
+        squares.pop();
+    }
+}
+
+fn fen(pieces: &[u8], squares: &[u8], white_to_move: bool) -> String {
+    let mut fen = String::new();
+
+    for rank in (0..8).rev() {
+        let mut empty = 0;
+
+        for file in 0..8 {
+            let piece = squares
+                .iter()
+                .position(|&square| square == rank * 8 + file)
+                .map(|i| pieces[i]);
+
+            match piece {
+                Some(code) => {
+                    if empty > 0 {
+                        fen.push_str(&empty.to_string());
+                        empty = 0;
+                    }
+
+                    let c = b" PNBRQK"[(code & 7) as usize] as char;
+                    fen.push(if code & 8 != 0 {
+                        c.to_ascii_lowercase()
+                    } else {
+                        c
+                    });
+                }
+
+                None => empty += 1,
+            }
+        }
+
+        if empty > 0 {
+            fen.push_str(&empty.to_string());
+        }
+
+        if rank > 0 {
+            fen.push('/');
+        }
+    }
+
+    fen.push_str(if white_to_move {
+        " w - - 0 1"
+    } else {
+        " b - - 0 1"
+    });
+    fen
+}
+
+/// How values are turned into Huffman symbols.
+#[derive(Clone, Copy)]
+enum Coding {
+    /// One symbol of this many bits per value.
+    Plain(u8),
+    /// WDL values 0 to 4 as four-bit symbols 0 to 4, with three-bit symbols
+    /// for the pairs 2 2, 0 0, 4 4 and 2 4, and 9 for four draws.
+    Pairs,
+}
+
+impl Coding {
+    /// The entries of the symbol tree: a pair of symbols, or a value and
+    /// 0xfff.
+    fn tree(self) -> Vec<(u16, u16)> {
+        match self {
+            Self::Plain(bits) => (0..1 << bits).map(|value| (value, 0xfff)).collect(),
+            Self::Pairs => vec![
+                (0, 0xfff),
+                (1, 0xfff),
+                (2, 0xfff),
+                (3, 0xfff),
+                (4, 0xfff),
+                (2, 2),
+                (0, 0),
+                (4, 4),
+                (2, 4),
+                (5, 5),
+            ],
+        }
+    }
+
+    /// The shortest and longest code and the lowest symbol of each length.
+    fn lengths(self) -> (u8, u8, Vec<u16>) {
+        match self {
+            Self::Plain(bits) => (bits, bits, vec![0]),
+            Self::Pairs => (3, 4, vec![6, 0]),
+        }
+    }
+
+    /// The code and length of the symbol for the values at the start of
+    /// `values`, and how many of them it covers.
+    fn next(self, values: &[u16]) -> (u32, u8, usize) {
+        let bits = match self {
+            Self::Plain(bits) => return (values[0] as u32, bits, 1),
+            Self::Pairs => 4,
+        };
+
+        let starts = |prefix: &[u16]| values.starts_with(prefix);
+
+        let (symbol, count) = if starts(&[2, 2, 2, 2]) {
+            (9, 4)
+        } else if starts(&[2, 2]) {
+            (5, 2)
+        } else if starts(&[0, 0]) {
+            (6, 2)
+        } else if starts(&[4, 4]) {
+            (7, 2)
+        } else if starts(&[2, 4]) {
+            (8, 2)
+        } else {
+            (values[0] as u32, 1)
+        };
+
+        // Three-bit codes start at 3, after the four-bit ones in 0000-0101.
+        if symbol >= 6 {
+            (symbol - 3, 3, count)
+        } else {
+            (symbol, bits, count)
+        }
+    }
+}
+
+/// A file with `values[side][file]` for each table, compressed with `coding`.
+fn write(
+    kind: TableKind,
+    material: &Material,
+    pieces: &[u8],
+    flags: u8,
+    values: &[Vec<Vec<u16>>],
+    coding: Coding,
+) -> Vec<u8> {
+    let files = values[0].len();
+    let both_pawns = material.has_pawns() && material.pawn_count[1] > 0;
+
+    let mut bytes = kind.magic().to_vec();
+    bytes.push((values.len() == 2) as u8 | (material.has_pawns() as u8) << 1);
+
+    for _ in 0..files {
+        bytes.push(0x00);
+
+        if both_pawns {
+            bytes.push(0x11);
+        }
+
+        bytes.extend(pieces.iter().map(|&piece| piece | piece << 4));
+    }
+
+    pad(&mut bytes, 2);
+
+    // Each table's blocks with the number of values in each.
+    let mut tables = Vec::new();
+
+    for file in 0..files {
+        for side_values in values {
+            let blocks = blocks(&side_values[file], coding);
+
+            let (min_len, max_len, lowest) = coding.lengths();
+            let tree = coding.tree();
+
+            bytes.push(flags);
+            bytes.extend([BLOCK_SIZE_LOG, SPAN_LOG, 0]);
+            bytes.extend((blocks.len() as u32).to_le_bytes());
+            bytes.extend([max_len, min_len]);
+            bytes.extend(lowest.iter().flat_map(|sym| sym.to_le_bytes()));
+            bytes.extend((tree.len() as u16).to_le_bytes());
+
+            for (left, right) in tree {
+                bytes.push(left as u8);
+                bytes.push((left >> 8) as u8 | (right << 4) as u8);
+                bytes.push((right >> 4) as u8);
+            }
+
+            pad(&mut bytes, 2);
+            tables.push((side_values[file].len(), blocks));
+        }
+    }
+
+    if kind == TableKind::Dtz {
+        pad(&mut bytes, 2);
+    }
+
+    let span = 1 << SPAN_LOG;
+
+    for (size, blocks) in &tables {
+        let starts: Vec<usize> = blocks
+            .iter()
+            .scan(0, |start, (_, count)| {
+                let block_start = *start;
+                *start += count;
+                Some(block_start)
+            })
+            .collect();
+
+        for k in 0..size.div_ceil(span) {
+            let middle = k * span + span / 2;
+            let block = starts.iter().rposition(|&start| start <= middle).unwrap();
+
+            bytes.extend((block as u32).to_le_bytes());
+            bytes.extend(((middle - starts[block]) as u16).to_le_bytes());
+        }
+    }
+
+    for (_, blocks) in &tables {
+        for (_, count) in blocks {
+            bytes.extend(((count - 1) as u16).to_le_bytes());
+        }
+    }
+
+    for (_, blocks) in tables {
+        pad(&mut bytes, 64);
+
+        for (block, _) in blocks {
+            bytes.extend(block);
+        }
+    }
+
+    bytes
+}
+
+/// Packs the symbols of `values` into blocks, each with how many values it
+/// holds.
+fn blocks(values: &[u16], coding: Coding) -> Vec<(Vec<u8>, usize)> {
+    let block_bits = 8 << BLOCK_SIZE_LOG;
+    let mut blocks = Vec::new();
+    let mut block = vec![0; block_bits / 8];
+    let mut used = 0;
+    let mut count = 0;
+    let mut i = 0;
+
+    while i < values.len() {
+        let (code, bits, covered) = coding.next(&values[i..]);
+        let bits = bits as usize;
+
+        if used + bits > block_bits {
+            blocks.push((
+                std::mem::replace(&mut block, vec![0; block_bits / 8]),
+                count,
+            ));
+            used = 0;
+            count = 0;
+        }
+
+        for bit in 0..bits {
+            if code >> (bits - 1 - bit) & 1 == 1 {
+                block[(used + bit) / 8] |= 0x80 >> ((used + bit) % 8);
+            }
+        }
+
+        used += bits;
+        count += covered;
+        i += covered;
+    }
+
+    blocks.push((block, count));
+    blocks
+}
+
+fn pad(bytes: &mut Vec<u8>, alignment: usize) {
+    bytes.resize(bytes.len().next_multiple_of(alignment), 0);
+}
+
+#[test]
+#[ignore = "rewrites tests/fixtures/syzygy"]
+fn write_fixtures() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/syzygy");
+
+    for (name, ..) in FIXTURES {
+        write_fixture(&dir, name);
+    }
+}
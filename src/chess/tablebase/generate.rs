@@ -0,0 +1,298 @@
+use std::thread;
+
+use super::layout::{is_attacked, Layout, PlacedPiece, Placement, MAX_PIECES};
+use super::{encode_value, parent_value, Signature, Tablebase, MATE};
+use crate::chess::board::chess_move::PROMOTION_KINDS;
+use crate::chess::board::{Color, PieceKind};
+
+// Generation works backwards from the mates. Every legal position starts out
+// unknown, except stalemates and positions whose only moves leave the table,
+// which are valued from the smaller tables right away. Valued positions wait
+// in buckets by their distance to mate and are settled in that order, so the
+// first value a position gets is its fastest mate.
+//
+// Settling a loss makes each position leading to it a win one ply further
+// away. Settling a win refutes one move of each position leading to it; once
+// all of a position's moves inside the table are refuted and none of its
+// captures or promotions holds, it is lost as slowly as its last refutation
+// allows. Positions never settled are draws.
+
+const ILLEGAL: i16 = i16::MIN;
+const UNKNOWN: i16 = i16::MIN + 1;
+
+/// Best value over the moves leaving the table when there are none.
+const NO_EXIT: i16 = i16::MIN;
+
+/// Longest mate a table byte can hold.
+const MAX_PLIES: usize = 253;
+
+/// How a position starts out before any position is settled.
+struct Start {
+    value: i16,
+    best_exit: i16,
+    /// Distinct positions inside the table its moves lead to.
+    children: u8,
+    /// Value to settle it with in order of distance to mate.
+    pending: Option<i16>,
+}
+
+/// Where a move leads: a placement of the same table, or the pieces of a
+/// smaller one after a capture or a promotion.
+enum Successor {
+    Inside(Placement),
+    Exit([PlacedPiece; MAX_PIECES], usize),
+}
+
+/// The table bytes for `signature`. The tables of its captures and promotions
+/// must be in `tablebase`.
+pub(super) fn generate(signature: &Signature, tablebase: &Tablebase) -> Vec<u8> {
+    let layout = signature.layout();
+    let size = layout.size();
+
+    let mut values = vec![UNKNOWN; size];
+    let mut best_exits = vec![NO_EXIT; size];
+    let mut remaining = vec![0u8; size];
+
+    let threads = thread::available_parallelism().map_or(1, |threads| threads.get());
+    let chunk_size = size.div_ceil(threads);
+
+    let pending: Vec<Vec<(u32, i16)>> = thread::scope(|scope| {
+        let workers: Vec<_> = values
+            .chunks_mut(chunk_size)
+            .zip(best_exits.chunks_mut(chunk_size))
+            .zip(remaining.chunks_mut(chunk_size))
+            .enumerate()
+            .map(|(chunk, ((values, best_exits), remaining))| {
+                let layout = &layout;
+
+                scope.spawn(move || {
+                    let mut pending = Vec::new();
+
+                    for offset in 0..values.len() {
+                        let index = chunk * chunk_size + offset;
+                        let start = classify(layout, tablebase, index);
+
+                        values[offset] = start.value;
+                        best_exits[offset] = start.best_exit;
+                        remaining[offset] = start.children;
+                        pending.extend(start.pending.map(|value| (index as u32, value)));
+                    }
+
+                    pending
+                })
+            })
+            .collect();
+
+        workers
+            .into_iter()
+            .map(|worker| worker.join().expect("Tablebase generation thread panicked"))
+            .collect()
+    });
+
+    let mut buckets: Vec<Vec<(u32, i16)>> = Vec::new();
+
+    for (index, value) in pending.into_iter().flatten() {
+        push(&mut buckets, index as usize, value);
+    }
+
+    let mut plies = 0;
+
+    while plies < buckets.len() {
+        for (index, value) in std::mem::take(&mut buckets[plies]) {
+            let index = index as usize;
+
+            if values[index] != UNKNOWN {
+                continue;
+            }
+
+            values[index] = value;
+
+            let mut parents: Vec<usize> = layout
+                .unmoves(&layout.placement(index))
+                .iter()
+                .filter(|parent| layout.is_legal(parent))
+                .filter_map(|parent| layout.index(parent))
+                .collect();
+
+            parents.sort_unstable();
+            parents.dedup();
+
+            for parent in parents {
+                if values[parent] != UNKNOWN {
+                    continue;
+                }
+
+                if value < 0 {
+                    push(&mut buckets, parent, parent_value(value));
+                } else {
+                    remaining[parent] -= 1;
+
+                    if remaining[parent] == 0 && best_exits[parent] < 0 {
+                        push(
+                            &mut buckets,
+                            parent,
+                            parent_value(value).max(best_exits[parent]),
+                        );
+                    }
+                }
+            }
+        }
+
+        plies += 1;
+    }
+
+    assert!(
+        buckets.len() <= MAX_PLIES + 1,
+        "{signature} has mates longer than {MAX_PLIES} plies"
+    );
+
+    values
+        .into_iter()
+        .map(|value| match value {
+            ILLEGAL | UNKNOWN => 0,
+            _ => encode_value(value),
+        })
+        .collect()
+}
+
+fn push(buckets: &mut Vec<Vec<(u32, i16)>>, index: usize, value: i16) {
+    let plies = (MATE - value.abs()) as usize;
+
+    if buckets.len() <= plies {
+        buckets.resize_with(plies + 1, Vec::new);
+    }
+
+    buckets[plies].push((index as u32, value));
+}
+
+fn classify(layout: &Layout, tablebase: &Tablebase, index: usize) -> Start {
+    let placement = layout.placement(index);
+
+    if layout.index(&placement) != Some(index) || !layout.is_legal(&placement) {
+        return Start {
+            value: ILLEGAL,
+            best_exit: NO_EXIT,
+            children: 0,
+            pending: None,
+        };
+    }
+
+    let side = if placement.white_to_move {
+        Color::White
+    } else {
+        Color::Black
+    };
+
+    let mut children = Vec::new();
+    let mut best_exit = NO_EXIT;
+
+    let successors = layout
+        .moves(&placement)
+        .into_iter()
+        .flat_map(|(piece, target)| successors(layout, &placement, piece, target));
+
+    for successor in successors {
+        match successor {
+            Successor::Inside(child) => {
+                if layout.is_legal(&child) {
+                    children.push(layout.index(&child).unwrap());
+                }
+            }
+
+            Successor::Exit(pieces, count) => {
+                let pieces = &pieces[..count];
+                let king_square = pieces
+                    .iter()
+                    .find(|&&(color, kind, _)| color == side && kind == PieceKind::King);
+
+                if is_attacked(pieces, king_square.unwrap().2, side.opposite()) {
+                    continue;
+                }
+
+                let value = tablebase
+                    .probe_pieces(pieces, !placement.white_to_move)
+                    .unwrap_or_else(|| {
+                        panic!("Missing table for {}", Signature::from_pieces(pieces))
+                    });
+
+                best_exit = best_exit.max(parent_value(value));
+            }
+        }
+    }
+
+    children.sort_unstable();
+    children.dedup();
+
+    let (value, pending) = if !children.is_empty() {
+        (UNKNOWN, (best_exit > 0).then_some(best_exit))
+    } else if best_exit != NO_EXIT {
+        if best_exit == 0 {
+            (0, None)
+        } else {
+            (UNKNOWN, Some(best_exit))
+        }
+    } else if layout.in_check(&placement) {
+        (UNKNOWN, Some(-MATE))
+    } else {
+        (0, None)
+    };
+
+    Start {
+        value,
+        best_exit,
+        children: children.len() as u8,
+        pending,
+    }
+}
+
+/// Where moving `piece` to `target` leads, once for each piece a pawn may
+/// promote to.
+fn successors(layout: &Layout, placement: &Placement, piece: usize, target: u8) -> Vec<Successor> {
+    let (color, kind) = layout.pieces[piece];
+    let captured = placement.squares[..layout.len()]
+        .iter()
+        .position(|&square| square == target);
+
+    let promotion_rank = match color {
+        Color::White => 0,
+        Color::Black => 7,
+    };
+
+    let promotes = kind == PieceKind::Pawn && target / 8 == promotion_rank;
+
+    if captured.is_none() && !promotes {
+        let mut child = *placement;
+        child.squares[piece] = target;
+        child.white_to_move = !placement.white_to_move;
+        return vec![Successor::Inside(child)];
+    }
+
+    let mut pieces = layout.placed(placement);
+    pieces[piece].2 = target;
+
+    let mut count = layout.len();
+
+    if let Some(captured) = captured {
+        pieces.copy_within(captured + 1..count, captured);
+        count -= 1;
+    }
+
+    if !promotes {
+        return vec![Successor::Exit(pieces, count)];
+    }
+
+    // The mover keeps its slot unless the captured piece came before it.
+    let mover = match captured {
+        Some(captured) if captured < piece => piece - 1,
+        _ => piece,
+    };
+
+    PROMOTION_KINDS
+        .iter()
+        .map(|&promotion| {
+            let mut pieces = pieces;
+            pieces[mover].1 = promotion;
+            Successor::Exit(pieces, count)
+        })
+        .collect()
+}
@@ -0,0 +1,442 @@
+use crate::chess::board::{Color, PieceKind};
+
+// Tablebase positions are lists of pieces on squares `rank_index * 8 + file`,
+// as in `Board::state`. The white king comes first, then the black king, then
+// the other white pieces and the other black pieces, each from the most to
+// the least valuable, so identical pieces are next to each other.
+//
+// Each position has one index. Symmetric positions share it: tables without
+// pawns may rotate and mirror the board, tables with pawns only mirror it left
+// to right. Of all equivalent placements, the one with the smallest squares,
+// compared piece by piece, is the one stored.
+
+pub const MAX_PIECES: usize = 4;
+
+/// A piece and its square, for positions outside any one table.
+pub type PlacedPiece = (Color, PieceKind, u8);
+
+/// A placement of a table's pieces with a side to move.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Placement {
+    pub squares: [u8; MAX_PIECES],
+    pub white_to_move: bool,
+}
+
+/// The pieces of a table and how its positions are indexed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Layout {
+    pub pieces: Vec<(Color, PieceKind)>,
+    pub has_pawns: bool,
+    king_slots: usize,
+}
+
+/// The squares the white king is put on for tables without pawns: the
+/// a8-d8-d5 triangle.
+const TRIANGLE: [u8; 10] = [0, 1, 2, 3, 9, 10, 11, 18, 19, 27];
+
+impl Layout {
+    pub fn new(pieces: Vec<(Color, PieceKind)>) -> Self {
+        let has_pawns = pieces.iter().any(|&(_, kind)| kind == PieceKind::Pawn);
+
+        Self {
+            pieces,
+            has_pawns,
+            king_slots: if has_pawns { 32 } else { TRIANGLE.len() },
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.pieces.len()
+    }
+
+    /// Number of indices, including ones of illegal placements.
+    pub fn size(&self) -> usize {
+        2 * self.king_slots * 64usize.pow(self.pieces.len() as u32 - 1)
+    }
+
+    fn king_slot(&self, square: u8) -> Option<usize> {
+        if self.has_pawns {
+            let file = square % 8;
+            (file < 4).then_some((square / 8 * 4 + file) as usize)
+        } else {
+            TRIANGLE.iter().position(|&corner| corner == square)
+        }
+    }
+
+    fn slot_square(&self, slot: usize) -> u8 {
+        if self.has_pawns {
+            (slot / 4 * 8 + slot % 4) as u8
+        } else {
+            TRIANGLE[slot]
+        }
+    }
+
+    /// The index of `placement`, or `None` for overlapping pieces or pawns on
+    /// the first or last rank.
+    pub fn index(&self, placement: &Placement) -> Option<usize> {
+        let count = self.pieces.len();
+        let squares = &placement.squares[..count];
+
+        for (i, &square) in squares.iter().enumerate() {
+            if squares[..i].contains(&square) {
+                return None;
+            }
+
+            if self.pieces[i].1 == PieceKind::Pawn && !(8..56).contains(&square) {
+                return None;
+            }
+        }
+
+        let symmetries = if self.has_pawns { 2 } else { 8 };
+        let mut best: Option<[u8; MAX_PIECES]> = None;
+
+        for symmetry in 0..symmetries {
+            let mut transformed = [0; MAX_PIECES];
+
+            for (i, &square) in squares.iter().enumerate() {
+                transformed[i] = transform(square, symmetry);
+            }
+
+            self.sort_identical(&mut transformed);
+
+            if best.is_none_or(|best| transformed[..count] < best[..count]) {
+                best = Some(transformed);
+            }
+        }
+
+        let best = best.unwrap();
+        let mut index =
+            placement.white_to_move as usize * self.king_slots + self.king_slot(best[0])?;
+
+        for &square in &best[1..count] {
+            index = index * 64 + square as usize;
+        }
+
+        Some(index)
+    }
+
+    /// The placement stored at `index`. It is only a legal position when it
+    /// indexes back to `index` and passes `is_legal`.
+    pub fn placement(&self, mut index: usize) -> Placement {
+        let count = self.pieces.len();
+        let mut squares = [0; MAX_PIECES];
+
+        for i in (1..count).rev() {
+            squares[i] = (index % 64) as u8;
+            index /= 64;
+        }
+
+        squares[0] = self.slot_square(index % self.king_slots);
+
+        Placement {
+            squares,
+            white_to_move: index / self.king_slots == 1,
+        }
+    }
+
+    /// Sorts the squares of identical pieces, which can be swapped freely.
+    fn sort_identical(&self, squares: &mut [u8; MAX_PIECES]) {
+        let mut start = 0;
+
+        while start < self.pieces.len() {
+            let mut end = start + 1;
+
+            while end < self.pieces.len() && self.pieces[end] == self.pieces[start] {
+                end += 1;
+            }
+
+            squares[start..end].sort_unstable();
+            start = end;
+        }
+    }
+
+    /// The pieces of `placement` with their squares, padded with white kings
+    /// past `len()`.
+    pub fn placed(&self, placement: &Placement) -> [PlacedPiece; MAX_PIECES] {
+        let mut placed = [(Color::White, PieceKind::King, 0); MAX_PIECES];
+
+        for (i, &(color, kind)) in self.pieces.iter().enumerate() {
+            placed[i] = (color, kind, placement.squares[i]);
+        }
+
+        placed
+    }
+
+    pub fn occupancy(&self, placement: &Placement) -> u64 {
+        placement.squares[..self.pieces.len()]
+            .iter()
+            .fold(0, |occupancy, &square| occupancy | 1 << square)
+    }
+
+    pub fn color_occupancy(&self, placement: &Placement, color: Color) -> u64 {
+        self.pieces
+            .iter()
+            .zip(placement.squares)
+            .filter(|((piece_color, _), _)| *piece_color == color)
+            .fold(0, |occupancy, (_, square)| occupancy | 1 << square)
+    }
+
+    pub fn is_attacked(&self, placement: &Placement, square: u8, by: Color) -> bool {
+        is_attacked(&self.placed(placement)[..self.pieces.len()], square, by)
+    }
+
+    /// Whether the king of the side that just moved is safe.
+    pub fn is_legal(&self, placement: &Placement) -> bool {
+        let (king, attacker) = if placement.white_to_move {
+            (placement.squares[1], Color::White)
+        } else {
+            (placement.squares[0], Color::Black)
+        };
+
+        !self.is_attacked(placement, king, attacker)
+    }
+
+    pub fn in_check(&self, placement: &Placement) -> bool {
+        let (king, attacker) = if placement.white_to_move {
+            (placement.squares[0], Color::Black)
+        } else {
+            (placement.squares[1], Color::White)
+        };
+
+        self.is_attacked(placement, king, attacker)
+    }
+
+    /// The placements the position can have been reached from by a move that
+    /// neither captured nor promoted. Not all of them are legal.
+    pub fn unmoves(&self, placement: &Placement) -> Vec<Placement> {
+        let mover = if placement.white_to_move {
+            Color::Black
+        } else {
+            Color::White
+        };
+
+        let occupancy = self.occupancy(placement);
+        let mut unmoves = Vec::new();
+
+        for (i, &(color, kind)) in self.pieces.iter().enumerate() {
+            if color != mover {
+                continue;
+            }
+
+            let square = placement.squares[i];
+
+            let origins = if kind == PieceKind::Pawn {
+                pawn_origins(color, square, occupancy)
+            } else {
+                attacks(kind, color, square, occupancy) & !occupancy
+            };
+
+            for origin in bits(origins) {
+                let mut unmove = *placement;
+                unmove.squares[i] = origin;
+                unmove.white_to_move = !placement.white_to_move;
+                unmoves.push(unmove);
+            }
+        }
+
+        unmoves
+    }
+
+    /// The pseudo-legal moves of the side to move as the moving piece, its
+    /// origin and target square.
+    pub fn moves(&self, placement: &Placement) -> Vec<(usize, u8)> {
+        let side = if placement.white_to_move {
+            Color::White
+        } else {
+            Color::Black
+        };
+
+        let occupancy = self.occupancy(placement);
+        let own = self.color_occupancy(placement, side);
+        let enemy = occupancy & !own;
+        let mut moves = Vec::new();
+
+        for (i, &(color, kind)) in self.pieces.iter().enumerate() {
+            if color != side {
+                continue;
+            }
+
+            let square = placement.squares[i];
+
+            let targets = if kind == PieceKind::Pawn {
+                attacks(kind, color, square, occupancy) & enemy
+                    | pawn_pushes(color, square, occupancy)
+            } else {
+                attacks(kind, color, square, occupancy) & !own
+            };
+
+            moves.extend(bits(targets).map(|target| (i, target)));
+        }
+
+        moves
+    }
+}
+
+/// Whether `by` attacks `square` among `pieces`.
+pub fn is_attacked(pieces: &[PlacedPiece], square: u8, by: Color) -> bool {
+    let occupancy = pieces
+        .iter()
+        .fold(0, |occupancy, &(_, _, from)| occupancy | 1 << from);
+
+    pieces.iter().any(|&(color, kind, from)| {
+        color == by && attacks(kind, color, from, occupancy) >> square & 1 == 1
+    })
+}
+
+/// One of the eight symmetries of the board. The first two keep pawns moving
+/// the same way.
+fn transform(square: u8, symmetry: usize) -> u8 {
+    let (mut rank, mut file) = (square / 8, square % 8);
+
+    if symmetry & 1 == 1 {
+        file = 7 - file;
+    }
+
+    if symmetry & 2 == 2 {
+        rank = 7 - rank;
+    }
+
+    if symmetry & 4 == 4 {
+        (rank, file) = (file, rank);
+    }
+
+    rank * 8 + file
+}
+
+pub fn bits(mut bitboard: u64) -> impl Iterator<Item = u8> {
+    std::iter::from_fn(move || {
+        if bitboard == 0 {
+            return None;
+        }
+
+        let square = bitboard.trailing_zeros() as u8;
+        bitboard &= bitboard - 1;
+        Some(square)
+    })
+}
+
+fn step(square: u8, (rank_step, file_step): (i8, i8)) -> Option<u8> {
+    let rank = (square / 8) as i8 + rank_step;
+    let file = (square % 8) as i8 + file_step;
+
+    ((0..8).contains(&rank) && (0..8).contains(&file)).then_some((rank * 8 + file) as u8)
+}
+
+const KING_STEPS: [(i8, i8); 8] = [
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+    (0, -1),
+    (0, 1),
+    (1, -1),
+    (1, 0),
+    (1, 1),
+];
+const KNIGHT_STEPS: [(i8, i8); 8] = [
+    (-2, -1),
+    (-2, 1),
+    (-1, -2),
+    (-1, 2),
+    (1, -2),
+    (1, 2),
+    (2, -1),
+    (2, 1),
+];
+const BISHOP_STEPS: [(i8, i8); 4] = [(-1, -1), (-1, 1), (1, -1), (1, 1)];
+const ROOK_STEPS: [(i8, i8); 4] = [(-1, 0), (1, 0), (0, -1), (0, 1)];
+
+/// White pawns move towards rank index 0.
+fn forward(color: Color) -> i8 {
+    match color {
+        Color::White => -1,
+        Color::Black => 1,
+    }
+}
+
+pub fn attacks(kind: PieceKind, color: Color, square: u8, occupancy: u64) -> u64 {
+    let leaper = |steps: &[(i8, i8)]| {
+        steps
+            .iter()
+            .filter_map(|&offset| step(square, offset))
+            .fold(0, |attacks, target| attacks | 1 << target)
+    };
+
+    let slider = |steps: &[(i8, i8)]| {
+        let mut attacks = 0;
+
+        for &offset in steps {
+            let mut current = square;
+
+            while let Some(target) = step(current, offset) {
+                attacks |= 1 << target;
+
+                if occupancy >> target & 1 == 1 {
+                    break;
+                }
+
+                current = target;
+            }
+        }
+
+        attacks
+    };
+
+    match kind {
+        PieceKind::King => leaper(&KING_STEPS),
+        PieceKind::Knight => leaper(&KNIGHT_STEPS),
+        PieceKind::Bishop => slider(&BISHOP_STEPS),
+        PieceKind::Rook => slider(&ROOK_STEPS),
+        PieceKind::Queen => slider(&BISHOP_STEPS) | slider(&ROOK_STEPS),
+        PieceKind::Pawn => leaper(&[(forward(color), -1), (forward(color), 1)]),
+    }
+}
+
+fn pawn_pushes(color: Color, square: u8, occupancy: u64) -> u64 {
+    let start_rank = match color {
+        Color::White => 6,
+        Color::Black => 1,
+    };
+
+    let mut pushes = 0;
+
+    if let Some(single) = step(square, (forward(color), 0)).filter(|&s| occupancy >> s & 1 == 0) {
+        pushes |= 1 << single;
+
+        if square / 8 == start_rank {
+            if let Some(double) =
+                step(single, (forward(color), 0)).filter(|&s| occupancy >> s & 1 == 0)
+            {
+                pushes |= 1 << double;
+            }
+        }
+    }
+
+    pushes
+}
+
+/// The squares a pawn on `square` can have been pushed from.
+fn pawn_origins(color: Color, square: u8, occupancy: u64) -> u64 {
+    let (start_rank, double_rank) = match color {
+        Color::White => (6, 4),
+        Color::Black => (1, 3),
+    };
+
+    let mut origins = 0;
+    let back = (-forward(color), 0);
+
+    if let Some(single) = step(square, back).filter(|&s| occupancy >> s & 1 == 0) {
+        if (1..7).contains(&(single / 8)) {
+            origins |= 1 << single;
+        }
+
+        if square / 8 == double_rank {
+            if let Some(double) = step(single, back).filter(|&s| occupancy >> s & 1 == 0) {
+                if double / 8 == start_rank {
+                    origins |= 1 << double;
+                }
+            }
+        }
+    }
+
+    origins
+}
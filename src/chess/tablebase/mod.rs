@@ -0,0 +1,704 @@
+//! Endgame tablebases generated by retrograde analysis.
+//!
+//! A table holds every position of one material signature, such as KQvKR,
+//! with the distance to mate under perfect play. Tables are generated here
+//! rather than downloaded, from the mates backwards, with captures and
+//! promotions looked up in the smaller tables they lead to. Positions with
+//! castling rights or an en passant capture are never in a table.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use self::layout::{Layout, PlacedPiece, Placement};
+use super::board::chess_move::{Move, PROMOTION_KINDS};
+use super::board::{Board, Color, PieceKind};
+
+mod generate;
+mod layout;
+
+pub use self::layout::MAX_PIECES;
+
+// A table file is
+//
+//   0..4      magic, "CETB"
+//   4         format version, `TABLE_VERSION`
+//   5         length n of the signature
+//   6..6+n    the signature, such as "KQvKR"
+//   6+n..     one byte per position index
+//
+// and a position byte is
+//
+//   0         a draw, or an index without a legal position
+//   1..=127   the side to move mates in that many moves
+//   128..     the side to move is mated in (byte - 128) moves
+
+const MAGIC: &[u8; 4] = b"CETB";
+
+/// Bumped whenever the layout, the indexing or the meaning of the values
+/// changes. Version 1 tables only knew queen promotions.
+pub const TABLE_VERSION: u8 = 2;
+
+pub const TABLE_EXTENSION: &str = "cetb";
+
+/// Positions are valued like search scores while generating: `MATE - plies`
+/// for a mate in `plies`, its negation for getting mated and 0 for a draw.
+const MATE: i16 = 1000;
+
+/// Non-king pieces from the most to the least valuable.
+const KINDS: [PieceKind; 5] = [
+    PieceKind::Queen,
+    PieceKind::Rook,
+    PieceKind::Bishop,
+    PieceKind::Knight,
+    PieceKind::Pawn,
+];
+
+fn kind_order(kind: PieceKind) -> usize {
+    KINDS.iter().position(|&k| k == kind).unwrap_or(KINDS.len())
+}
+
+fn kind_char(kind: PieceKind) -> char {
+    match kind {
+        PieceKind::Pawn => 'P',
+        PieceKind::Knight => 'N',
+        PieceKind::Bishop => 'B',
+        PieceKind::Rook => 'R',
+        PieceKind::Queen => 'Q',
+        PieceKind::King => 'K',
+    }
+}
+
+/// The value of a position from the value of the position a move leads to.
+fn parent_value(child: i16) -> i16 {
+    match child {
+        0 => 0,
+        _ if child > 0 => -child + 1,
+        _ => -child - 1,
+    }
+}
+
+fn encode_value(value: i16) -> u8 {
+    match value {
+        0 => 0,
+        _ if value > 0 => ((MATE - value + 1) / 2) as u8,
+        _ => 128 + ((MATE + value) / 2) as u8,
+    }
+}
+
+fn decode_value(byte: u8) -> i16 {
+    match byte {
+        0 => 0,
+        1..=127 => MATE - (2 * byte as i16 - 1),
+        _ => -(MATE - 2 * (byte as i16 - 128)),
+    }
+}
+
+/// Outcome with perfect play, for the side to move.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Wdl {
+    Loss,
+    Draw,
+    Win,
+}
+
+/// Distance to mate with perfect play, in plies, for the side to move.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Dtm {
+    Win(u32),
+    Draw,
+    /// `Loss(0)` is checkmate.
+    Loss(u32),
+}
+
+impl Dtm {
+    fn from_value(value: i16) -> Self {
+        match value {
+            0 => Self::Draw,
+            _ if value > 0 => Self::Win((MATE - value) as u32),
+            _ => Self::Loss((MATE + value) as u32),
+        }
+    }
+
+    pub fn wdl(self) -> Wdl {
+        match self {
+            Self::Win(_) => Wdl::Win,
+            Self::Draw => Wdl::Draw,
+            Self::Loss(_) => Wdl::Loss,
+        }
+    }
+}
+
+/// The material of a table: the non-king pieces of each side, most valuable
+/// first. Written as "KQvKR", white first.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Signature {
+    white: Vec<PieceKind>,
+    black: Vec<PieceKind>,
+}
+
+impl Signature {
+    pub fn parse(signature: &str) -> Result<Self, TablebaseError> {
+        let invalid = || TablebaseError::InvalidSignature(signature.to_string());
+
+        let (white, black) = signature.split_once('v').ok_or_else(invalid)?;
+
+        let side = |pieces: &str| -> Option<Vec<PieceKind>> {
+            let mut kinds = pieces
+                .strip_prefix('K')?
+                .chars()
+                .map(|c| KINDS.iter().copied().find(|&kind| kind_char(kind) == c))
+                .collect::<Option<Vec<_>>>()?;
+
+            kinds.sort_by_key(|&kind| kind_order(kind));
+            Some(kinds)
+        };
+
+        let signature = Self {
+            white: side(white).ok_or_else(invalid)?,
+            black: side(black).ok_or_else(invalid)?,
+        };
+
+        if signature.piece_count() > MAX_PIECES {
+            return Err(TablebaseError::TooManyPieces(signature.to_string()));
+        }
+
+        Ok(signature)
+    }
+
+    /// Every signature with up to `max_pieces` pieces and more than the two
+    /// kings, each in its `canonical` form.
+    pub fn all(max_pieces: usize) -> Vec<Self> {
+        let max_pieces = max_pieces.min(MAX_PIECES);
+        let mut signatures: Vec<Self> = Vec::new();
+
+        for count in 1..=max_pieces.saturating_sub(2) {
+            for white_count in 0..=count {
+                for white in multisets(white_count) {
+                    for black in multisets(count - white_count) {
+                        let signature = Self {
+                            white: white.clone(),
+                            black,
+                        }
+                        .canonical();
+
+                        if !signatures.contains(&signature) {
+                            signatures.push(signature);
+                        }
+                    }
+                }
+            }
+        }
+
+        signatures
+    }
+
+    fn from_pieces(pieces: &[PlacedPiece]) -> Self {
+        let side = |side: Color| {
+            let mut kinds: Vec<PieceKind> = pieces
+                .iter()
+                .filter(|&&(color, kind, _)| color == side && kind != PieceKind::King)
+                .map(|&(_, kind, _)| kind)
+                .collect();
+
+            kinds.sort_by_key(|&kind| kind_order(kind));
+            kinds
+        };
+
+        Self {
+            white: side(Color::White),
+            black: side(Color::Black),
+        }
+    }
+
+    pub fn piece_count(&self) -> usize {
+        2 + self.white.len() + self.black.len()
+    }
+
+    /// The same material with the colours swapped.
+    pub fn mirrored(&self) -> Self {
+        Self {
+            white: self.black.clone(),
+            black: self.white.clone(),
+        }
+    }
+
+    /// The orientation tables are generated in, with the side that has more
+    /// or stronger pieces as white.
+    pub fn canonical(&self) -> Self {
+        let strength = |kinds: &[PieceKind]| {
+            (
+                std::cmp::Reverse(kinds.len()),
+                kinds
+                    .iter()
+                    .map(|&kind| kind_order(kind))
+                    .collect::<Vec<_>>(),
+            )
+        };
+
+        if strength(&self.white) <= strength(&self.black) {
+            self.clone()
+        } else {
+            self.mirrored()
+        }
+    }
+
+    fn layout(&self) -> Layout {
+        let mut pieces = vec![
+            (Color::White, PieceKind::King),
+            (Color::Black, PieceKind::King),
+        ];
+        pieces.extend(self.white.iter().map(|&kind| (Color::White, kind)));
+        pieces.extend(self.black.iter().map(|&kind| (Color::Black, kind)));
+
+        Layout::new(pieces)
+    }
+
+    /// The signatures a capture, a promotion or both lead to, except bare
+    /// kings.
+    fn successors(&self) -> Vec<Self> {
+        let remove = |kinds: &[PieceKind], i: usize| {
+            let mut kinds = kinds.to_vec();
+            kinds.remove(i);
+            kinds
+        };
+
+        let promote = |kinds: &[PieceKind], promotion: PieceKind| {
+            let i = kinds.iter().position(|&kind| kind == PieceKind::Pawn)?;
+            let mut kinds = remove(kinds, i);
+            let at = kinds.partition_point(|&kind| kind_order(kind) <= kind_order(promotion));
+            kinds.insert(at, promotion);
+            Some(kinds)
+        };
+
+        let mut successors: Vec<Self> = Vec::new();
+
+        for white_moves in [true, false] {
+            let (mover, other) = if white_moves {
+                (&self.white, &self.black)
+            } else {
+                (&self.black, &self.white)
+            };
+
+            let mut changes = Vec::new();
+
+            for promotion in PROMOTION_KINDS {
+                if let Some(promoted) = promote(mover, promotion) {
+                    changes.push((promoted.clone(), other.clone()));
+
+                    for i in 0..other.len() {
+                        changes.push((promoted.clone(), remove(other, i)));
+                    }
+                }
+            }
+
+            for i in 0..other.len() {
+                changes.push((mover.clone(), remove(other, i)));
+            }
+
+            for (mover, other) in changes {
+                let successor = if white_moves {
+                    Self {
+                        white: mover,
+                        black: other,
+                    }
+                } else {
+                    Self {
+                        white: other,
+                        black: mover,
+                    }
+                };
+
+                if successor.piece_count() > 2 && !successors.contains(&successor) {
+                    successors.push(successor);
+                }
+            }
+        }
+
+        successors
+    }
+}
+
+impl fmt::Display for Signature {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let side = |kinds: &[PieceKind]| -> String {
+            std::iter::once('K')
+                .chain(kinds.iter().map(|&kind| kind_char(kind)))
+                .collect()
+        };
+
+        write!(f, "{}v{}", side(&self.white), side(&self.black))
+    }
+}
+
+/// Multisets of `count` non-king pieces, most valuable first.
+fn multisets(count: usize) -> Vec<Vec<PieceKind>> {
+    fn extend(prefix: Vec<PieceKind>, from: usize, count: usize, out: &mut Vec<Vec<PieceKind>>) {
+        if prefix.len() == count {
+            out.push(prefix);
+            return;
+        }
+
+        for (i, &kind) in KINDS.iter().enumerate().skip(from) {
+            let mut next = prefix.clone();
+            next.push(kind);
+            extend(next, i, count, out);
+        }
+    }
+
+    let mut multisets = Vec::new();
+    extend(Vec::new(), 0, count, &mut multisets);
+    multisets
+}
+
+/// The values of every position of one signature.
+pub struct Table {
+    signature: Signature,
+    layout: Layout,
+    data: Vec<u8>,
+}
+
+impl Table {
+    fn new(signature: Signature, data: Vec<u8>) -> Self {
+        Self {
+            layout: signature.layout(),
+            signature,
+            data,
+        }
+    }
+
+    pub fn signature(&self) -> &Signature {
+        &self.signature
+    }
+
+    /// The file name the table is saved under in a tablebase directory.
+    pub fn file_name(&self) -> String {
+        format!("{}.{TABLE_EXTENSION}", self.signature)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, TablebaseError> {
+        let bytes = fs::read(path)?;
+
+        if bytes.len() < 6 || &bytes[0..4] != MAGIC {
+            return Err(TablebaseError::NotATable);
+        }
+
+        if bytes[4] != TABLE_VERSION {
+            return Err(TablebaseError::UnsupportedVersion(bytes[4]));
+        }
+
+        let data_start = 6 + bytes[5] as usize;
+
+        let signature = bytes
+            .get(6..data_start)
+            .and_then(|signature| std::str::from_utf8(signature).ok())
+            .ok_or(TablebaseError::NotATable)?;
+
+        let table = Self::new(Signature::parse(signature)?, bytes[data_start..].to_vec());
+
+        if table.data.len() != table.layout.size() {
+            return Err(TablebaseError::TruncatedTable(table.signature.to_string()));
+        }
+
+        Ok(table)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), TablebaseError> {
+        let signature = self.signature.to_string();
+
+        let mut bytes = Vec::with_capacity(6 + signature.len() + self.data.len());
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(TABLE_VERSION);
+        bytes.push(signature.len() as u8);
+        bytes.extend_from_slice(signature.as_bytes());
+        bytes.extend_from_slice(&self.data);
+
+        fs::write(path, bytes)?;
+        Ok(())
+    }
+
+    fn value(&self, placement: &Placement) -> Option<i16> {
+        let index = self.layout.index(placement)?;
+        Some(decode_value(self.data[index]))
+    }
+}
+
+/// A set of tables, each probed for its signature and the mirrored one.
+#[derive(Default)]
+pub struct Tablebase {
+    tables: HashMap<String, Table>,
+}
+
+impl Tablebase {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loads every table file in `dir`.
+    pub fn load_dir(dir: impl AsRef<Path>) -> Result<Self, TablebaseError> {
+        let mut tablebase = Self::new();
+
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+
+            if path
+                .extension()
+                .is_some_and(|extension| extension == TABLE_EXTENSION)
+            {
+                tablebase.insert(Table::load(&path)?);
+            }
+        }
+
+        Ok(tablebase)
+    }
+
+    pub fn insert(&mut self, table: Table) {
+        self.tables.insert(table.signature.to_string(), table);
+    }
+
+    pub fn tables(&self) -> impl Iterator<Item = &Table> {
+        self.tables.values()
+    }
+
+    pub fn table(&self, signature: &Signature) -> Option<&Table> {
+        self.tables.get(&signature.to_string())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tables.is_empty()
+    }
+
+    /// Whether positions of `signature` can be probed, from its own table or
+    /// the mirrored one.
+    pub fn contains(&self, signature: &Signature) -> bool {
+        self.table(signature).is_some() || self.table(&signature.mirrored()).is_some()
+    }
+
+    /// The most pieces of any table.
+    pub fn max_pieces(&self) -> usize {
+        self.tables
+            .values()
+            .map(|table| table.signature.piece_count())
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Generates the table for `signature`, after any smaller table it needs
+    /// that is missing. Returns the signatures of the new tables, smallest
+    /// first.
+    pub fn generate(&mut self, signature: &Signature) -> Vec<Signature> {
+        let mut generated = Vec::new();
+        self.generate_missing(signature, &mut generated);
+        generated
+    }
+
+    fn generate_missing(&mut self, signature: &Signature, generated: &mut Vec<Signature>) {
+        if self.contains(signature) {
+            return;
+        }
+
+        for successor in signature.successors() {
+            self.generate_missing(&successor.canonical(), generated);
+        }
+
+        let data = generate::generate(signature, self);
+        self.insert(Table::new(signature.clone(), data));
+        generated.push(signature.clone());
+    }
+
+    pub fn probe_wdl(&self, board: &Board) -> Option<Wdl> {
+        self.probe_dtm(board).map(Dtm::wdl)
+    }
+
+    pub fn probe_dtm(&self, board: &Board) -> Option<Dtm> {
+        let pieces = board_pieces(board)?;
+        self.probe_pieces(&pieces, board.active_turn == Color::White)
+            .map(Dtm::from_value)
+    }
+
+    /// The legal move with the best distance to mate: the fastest mate when
+    /// winning and the slowest one when losing. Any drawing move is played in
+    /// a draw.
+    pub fn best_move(&self, board: &Board) -> Option<(Move, Dtm)> {
+        let pieces = board_pieces(board)?;
+        let white_to_move = board.active_turn == Color::White;
+        self.probe_pieces(&pieces, white_to_move)?;
+
+        let mut best: Option<(Move, i16)> = None;
+
+        for m in board.get_legal_moves() {
+            let mut child = board.clone();
+            child.make_move_unchecked(m);
+
+            let value = parent_value(self.probe_pieces(&board_pieces(&child)?, !white_to_move)?);
+
+            if best.is_none_or(|(_, best_value)| value > best_value) {
+                best = Some((m, value));
+            }
+        }
+
+        best.map(|(m, value)| (m, Dtm::from_value(value)))
+    }
+
+    /// The value of a position given as its pieces. Bare kings are a draw
+    /// without a table.
+    fn probe_pieces(&self, pieces: &[PlacedPiece], white_to_move: bool) -> Option<i16> {
+        let signature = Signature::from_pieces(pieces);
+
+        if signature.piece_count() == 2 {
+            return Some(0);
+        }
+
+        let (table, mirror) = match self.table(&signature) {
+            Some(table) => (table, false),
+            None => (self.table(&signature.mirrored())?, true),
+        };
+
+        // A mirrored position swaps the colours and turns the board upside
+        // down.
+        let mut placement = Placement {
+            squares: [0; MAX_PIECES],
+            white_to_move: white_to_move != mirror,
+        };
+
+        let mut used = [false; MAX_PIECES];
+
+        for (slot, &(color, kind)) in table.layout.pieces.iter().enumerate() {
+            let color = if mirror { color.opposite() } else { color };
+
+            let i = (0..pieces.len())
+                .find(|&i| !used[i] && pieces[i].0 == color && pieces[i].1 == kind)?;
+
+            used[i] = true;
+            placement.squares[slot] = if mirror {
+                pieces[i].2 ^ 56
+            } else {
+                pieces[i].2
+            };
+        }
+
+        table.value(&placement)
+    }
+}
+
+/// The pieces of `board`, or `None` when it has castling rights, an en
+/// passant capture or more pieces than any table.
+fn board_pieces(board: &Board) -> Option<Vec<PlacedPiece>> {
+    let castle_flags = &board.castle_flags;
+
+    // The tables only hold positions without an en passant capture.
+    if board.capturable_en_passant_file().is_some() {
+        return None;
+    }
+
+    if castle_flags.white_kingside
+        || castle_flags.white_queenside
+        || castle_flags.black_kingside
+        || castle_flags.black_queenside
+    {
+        return None;
+    }
+
+    let mut pieces = Vec::with_capacity(MAX_PIECES);
+
+    for (r_index, rank) in board.state.iter().enumerate() {
+        for (f_index, square) in rank.iter().enumerate() {
+            if let Some(piece) = square {
+                if pieces.len() == MAX_PIECES {
+                    return None;
+                }
+
+                pieces.push((piece.color, piece.piece_kind, (r_index * 8 + f_index) as u8));
+            }
+        }
+    }
+
+    Some(pieces)
+}
+
+#[derive(Debug)]
+pub enum TablebaseError {
+    Io(io::Error),
+    InvalidSignature(String),
+    TooManyPieces(String),
+    /// The file does not start with a table header.
+    NotATable,
+    UnsupportedVersion(u8),
+    /// The file has fewer or more positions than its signature.
+    TruncatedTable(String),
+}
+
+impl From<io::Error> for TablebaseError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl fmt::Display for TablebaseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Io(err) => {
+                write!(f, "I/O error: {err}")
+            }
+
+            Self::InvalidSignature(signature) => {
+                write!(f, "Invalid material signature: '{signature}'")
+            }
+
+            Self::TooManyPieces(signature) => {
+                write!(f, "{signature} has more than {MAX_PIECES} pieces")
+            }
+
+            Self::NotATable => {
+                write!(f, "Not a tablebase file")
+            }
+
+            Self::UnsupportedVersion(version) => {
+                write!(f, "Unsupported tablebase version {version}")
+            }
+
+            Self::TruncatedTable(signature) => {
+                write!(f, "Table {signature} does not have one byte per position")
+            }
+        }
+    }
+}
+
+impl std::error::Error for TablebaseError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn refuses_positions_with_an_en_passant_capture() {
+        // Generating KPvKP takes too long here, so every position is a draw.
+        let signature = Signature::parse("KPvKP").unwrap();
+        let data = vec![encode_value(0); signature.layout().size()];
+
+        let mut tablebase = Tablebase::new();
+        tablebase.insert(Table::new(signature, data));
+
+        let capturable = Board::from_fen("8/8/8/3pP3/8/8/8/K6k w - d6 0 1").unwrap();
+        let not_capturable = Board::from_fen("8/8/8/3p2P1/8/8/8/K6k w - d6 0 1").unwrap();
+
+        assert!(tablebase.probe_wdl(&capturable).is_none());
+        assert!(tablebase.best_move(&capturable).is_none());
+        assert_eq!(tablebase.probe_wdl(&not_capturable), Some(Wdl::Draw));
+    }
+
+    #[test]
+    fn wins_that_need_an_underpromotion() {
+        let mut tablebase = Tablebase::new();
+        tablebase.generate(&Signature::parse("KPvK").unwrap());
+
+        // Queening stalemates, while a rook mates.
+        let board = Board::from_fen("8/k1P5/8/K7/8/8/8/8 w - - 0 1").unwrap();
+        let (m, dtm) = tablebase.best_move(&board).unwrap();
+
+        assert_eq!(dtm.wdl(), Wdl::Win);
+        assert_eq!(m, Move::from_uci(&board, "c7c8r").unwrap());
+    }
+}
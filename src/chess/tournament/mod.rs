@@ -0,0 +1,474 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::Instant;
+
+use super::board::chess_move::{Move, MoveError};
+use super::board::{Board, Color, GameStatus, PieceKind};
+use super::options::EngineOptions;
+use super::search::{SearchLimits, Searcher};
+
+pub mod sprt;
+pub mod uci;
+
+/// How much thinking each side gets per move or per game.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TimeControl {
+    Depth(u8),
+    Nodes(u64),
+    MoveTime(u64),
+    /// Base time and increment in milliseconds, with a loss on time.
+    Clock {
+        base: u64,
+        increment: u64,
+    },
+}
+
+impl TimeControl {
+    fn limits(&self, clocks: [u64; 2]) -> SearchLimits {
+        match *self {
+            Self::Depth(depth) => SearchLimits {
+                depth: Some(depth),
+                ..Default::default()
+            },
+            Self::Nodes(nodes) => SearchLimits {
+                nodes: Some(nodes),
+                ..Default::default()
+            },
+            Self::MoveTime(movetime) => SearchLimits {
+                movetime: Some(movetime),
+                ..Default::default()
+            },
+            Self::Clock { increment, .. } => SearchLimits {
+                wtime: Some(clocks[0]),
+                btime: Some(clocks[1]),
+                winc: Some(increment),
+                binc: Some(increment),
+                ..Default::default()
+            },
+        }
+    }
+}
+
+/// The moves played so far from a starting position.
+pub struct Game {
+    pub start: Board,
+    pub board: Board,
+    pub moves: Vec<Move>,
+    hashes: Vec<u64>,
+}
+
+impl Game {
+    pub fn new(start: &Board) -> Self {
+        Self {
+            start: start.clone(),
+            board: start.clone(),
+            moves: Vec::new(),
+            hashes: vec![start.hash()],
+        }
+    }
+
+    /// The moves in UCI notation, as sent after `position fen <start> moves`.
+    pub fn uci_moves(&self) -> Vec<String> {
+        let mut side = self.start.active_turn;
+
+        self.moves
+            .iter()
+            .map(|m| {
+                let uci = m.to_uci(side);
+                side = side.opposite();
+                uci
+            })
+            .collect()
+    }
+
+    /// Hashes of the positions before the current one, oldest first.
+    pub fn previous_hashes(&self) -> &[u64] {
+        &self.hashes[..self.hashes.len() - 1]
+    }
+
+    pub(crate) fn play(&mut self, m: Move) {
+        self.board.make_move_unchecked(m);
+        self.moves.push(m);
+        self.hashes.push(self.board.hash());
+    }
+
+    fn is_threefold_repetition(&self) -> bool {
+        let current = self.board.hash();
+
+        // Only positions since the last capture or pawn move can repeat.
+        self.hashes
+            .iter()
+            .rev()
+            .take(self.board.halfmove_clock as usize + 1)
+            .step_by(2)
+            .filter(|&&hash| hash == current)
+            .count()
+            >= 3
+    }
+}
+
+/// Anything that can pick a move in a match game.
+pub trait Player {
+    fn name(&self) -> &str;
+
+    fn new_game(&mut self) -> Result<(), MatchError>;
+
+    fn choose_move(&mut self, game: &Game, limits: &SearchLimits) -> Result<Move, MatchError>;
+}
+
+/// This engine, searching in process with its own options.
+pub struct EnginePlayer {
+    name: String,
+    searcher: Searcher,
+}
+
+impl EnginePlayer {
+    pub fn new(name: &str, options: EngineOptions) -> Self {
+        Self {
+            name: name.to_string(),
+            searcher: Searcher::new(options),
+        }
+    }
+
+    /// Files named in the options that failed to load. See `Searcher::warnings`.
+    pub fn warnings(&self) -> &[String] {
+        self.searcher.warnings()
+    }
+}
+
+impl Player for EnginePlayer {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn new_game(&mut self) -> Result<(), MatchError> {
+        self.searcher.clear_hash();
+        Ok(())
+    }
+
+    fn choose_move(&mut self, game: &Game, limits: &SearchLimits) -> Result<Move, MatchError> {
+        self.searcher.set_game_history(game.previous_hashes());
+
+        self.searcher
+            .search(&game.board, limits)
+            .best_move
+            .ok_or_else(|| MatchError::NoMove(self.name.clone()))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Termination {
+    Checkmate,
+    Stalemate,
+    FiftyMoveRule,
+    Repetition,
+    InsufficientMaterial,
+    /// The game reached the ply limit and was scored as a draw.
+    MoveLimit,
+    TimeForfeit,
+    IllegalMove,
+}
+
+impl fmt::Display for Termination {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Checkmate => write!(f, "checkmate"),
+            Self::Stalemate => write!(f, "stalemate"),
+            Self::FiftyMoveRule => write!(f, "fifty move rule"),
+            Self::Repetition => write!(f, "threefold repetition"),
+            Self::InsufficientMaterial => write!(f, "insufficient material"),
+            Self::MoveLimit => write!(f, "move limit"),
+            Self::TimeForfeit => write!(f, "time forfeit"),
+            Self::IllegalMove => write!(f, "illegal move"),
+        }
+    }
+}
+
+pub struct GameRecord {
+    pub result: GameStatus,
+    pub termination: Termination,
+    pub moves: Vec<Move>,
+}
+
+/// Plays one game from `opening` and adjudicates it with `get_game_status`,
+/// plus the draw rules the board does not track itself.
+pub fn play_game(
+    white: &mut dyn Player,
+    black: &mut dyn Player,
+    opening: &Board,
+    time_control: TimeControl,
+    max_plies: usize,
+) -> Result<GameRecord, MatchError> {
+    white.new_game()?;
+    black.new_game()?;
+
+    let mut game = Game::new(opening);
+
+    let mut clocks = match time_control {
+        TimeControl::Clock { base, .. } => [base, base],
+        _ => [0, 0],
+    };
+
+    let loss_for = |side: Color| match side {
+        Color::White => GameStatus::BlackWin,
+        Color::Black => GameStatus::WhiteWin,
+    };
+
+    let (result, termination) = loop {
+        let side = game.board.active_turn;
+
+        if let Some(outcome) = adjudicate(&game, max_plies) {
+            break outcome;
+        }
+
+        let player: &mut dyn Player = match side {
+            Color::White => &mut *white,
+            Color::Black => &mut *black,
+        };
+
+        let start = Instant::now();
+
+        let m = match player.choose_move(&game, &time_control.limits(clocks)) {
+            Ok(m) => m,
+            Err(MatchError::IllegalMove(..)) => break (loss_for(side), Termination::IllegalMove),
+            Err(err) => return Err(err),
+        };
+
+        if let TimeControl::Clock { increment, .. } = time_control {
+            let clock = match side {
+                Color::White => &mut clocks[0],
+                Color::Black => &mut clocks[1],
+            };
+            let elapsed = start.elapsed().as_millis() as u64;
+
+            if elapsed > *clock {
+                break (loss_for(side), Termination::TimeForfeit);
+            }
+
+            *clock = *clock - elapsed + increment;
+        }
+
+        if !game.board.is_legal_move(m) {
+            break (loss_for(side), Termination::IllegalMove);
+        }
+
+        game.play(m);
+    };
+
+    Ok(GameRecord {
+        result,
+        termination,
+        moves: game.moves,
+    })
+}
+
+/// The result of `game` if it is over by the rules or by reaching `max_plies`.
+pub(crate) fn adjudicate(game: &Game, max_plies: usize) -> Option<(GameStatus, Termination)> {
+    match game.board.get_game_status() {
+        GameStatus::Ongoing => (),
+        GameStatus::Draw => return Some((GameStatus::Draw, Termination::Stalemate)),
+        result => return Some((result, Termination::Checkmate)),
+    }
+
+    if game.board.halfmove_clock >= 100 {
+        return Some((GameStatus::Draw, Termination::FiftyMoveRule));
+    }
+
+    if game.is_threefold_repetition() {
+        return Some((GameStatus::Draw, Termination::Repetition));
+    }
+
+    if is_insufficient_material(&game.board) {
+        return Some((GameStatus::Draw, Termination::InsufficientMaterial));
+    }
+
+    if game.moves.len() >= max_plies {
+        return Some((GameStatus::Draw, Termination::MoveLimit));
+    }
+
+    None
+}
+
+/// Neither side can mate: bare kings, or a single minor piece against a bare king.
+fn is_insufficient_material(board: &Board) -> bool {
+    let mut minor_pieces = 0;
+
+    for piece in board.state.iter().flatten().flatten() {
+        match piece.piece_kind {
+            PieceKind::King => (),
+            PieceKind::Knight | PieceKind::Bishop => minor_pieces += 1,
+            _ => return false,
+        }
+    }
+
+    minor_pieces <= 1
+}
+
+/// Reads an opening suite of one FEN or EPD position per line. Blank lines and
+/// lines starting with `#` are skipped.
+pub fn load_openings(path: impl AsRef<Path>) -> Result<Vec<Board>, MatchError> {
+    let contents = fs::read_to_string(path)?;
+    let mut openings = Vec::new();
+
+    for line in contents.lines() {
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let fields: Vec<&str> = line.split_whitespace().collect();
+
+        if fields.len() < 4 {
+            return Err(MatchError::MalformedOpening(line.to_string()));
+        }
+
+        // EPD lines carry operations instead of the move counters.
+        let counters = match fields.get(4..6) {
+            Some(counters) if counters.iter().all(|field| field.parse::<u16>().is_ok()) => {
+                counters.join(" ")
+            }
+            _ => String::from("0 1"),
+        };
+
+        let fen = format!("{} {counters}", fields[..4].join(" "));
+
+        let board =
+            Board::from_fen(&fen).map_err(|_| MatchError::MalformedOpening(line.to_string()))?;
+
+        openings.push(board);
+    }
+
+    Ok(openings)
+}
+
+#[derive(Debug)]
+pub enum MatchError {
+    Io(io::Error),
+    MalformedOpening(String),
+    /// A player answered with a move that does not parse or is not legal.
+    IllegalMove(String, MoveError),
+    NoMove(String),
+    EngineExited(String),
+}
+
+impl From<io::Error> for MatchError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl fmt::Display for MatchError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Io(err) => {
+                write!(f, "I/O error: {err}")
+            }
+
+            Self::MalformedOpening(line) => {
+                write!(f, "Malformed opening position: '{line}'")
+            }
+
+            Self::IllegalMove(name, err) => {
+                write!(f, "{name} played an illegal move: {err}")
+            }
+
+            Self::NoMove(name) => {
+                write!(f, "{name} returned no move in an ongoing game")
+            }
+
+            Self::EngineExited(name) => {
+                write!(f, "{name} exited unexpectedly")
+            }
+        }
+    }
+}
+
+impl std::error::Error for MatchError {}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    /// Writes `contents` to a file that is removed when dropped.
+    struct TempFile(PathBuf);
+
+    impl TempFile {
+        fn new(name: &str, contents: &str) -> Self {
+            let path = std::env::temp_dir().join(format!("{}-{name}", std::process::id()));
+            fs::write(&path, contents).unwrap();
+            Self(path)
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    #[test]
+    fn hundred_quiet_plies_are_a_draw() {
+        let start = Board::from_fen("1n2k3/8/8/8/8/8/8/1N2K3 w - - 0 1").unwrap();
+        let mut game = Game::new(&start);
+
+        for lan in ["b1c3", "b8c6", "c3b1", "c6b8"].iter().cycle().take(100) {
+            game.play(Move::from_lan(&game.board, lan).unwrap());
+        }
+
+        assert_eq!(game.board.halfmove_clock, 100);
+        assert_eq!(
+            adjudicate(&game, 1000),
+            Some((GameStatus::Draw, Termination::FiftyMoveRule))
+        );
+    }
+
+    #[test]
+    fn loads_fen_and_epd_openings() {
+        let file = TempFile::new(
+            "openings.epd",
+            "# comment\n\
+             4k3/8/8/8/8/8/4P3/4K3 w - - 3 20\n\
+             \n\
+             4k3/8/8/8/8/8/4P3/4K3 b - - bm Kd7;\n",
+        );
+
+        let fens: Vec<String> = load_openings(&file.0)
+            .unwrap()
+            .iter()
+            .map(Board::to_fen)
+            .collect();
+
+        assert_eq!(
+            fens,
+            [
+                "4k3/8/8/8/8/8/4P3/4K3 w - - 3 20",
+                "4k3/8/8/8/8/8/4P3/4K3 b - - 0 1"
+            ]
+        );
+    }
+
+    #[test]
+    fn malformed_opening_is_an_error() {
+        for (index, line) in [
+            "4k3/8/8/8/8/8/4P3/4K3 w -",
+            "4k3/8/8/8/8/8/4P3/4KX2 w - - 0 1",
+            "4k3/8/8/8/8/8/4P3/8 w - - 0 1",
+            "4k3/8/8/8/8/8/4P3/4K3 w - z9 0 1",
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let file = TempFile::new(&format!("malformed-{index}.epd"), line);
+
+            assert!(
+                matches!(load_openings(&file.0), Err(MatchError::MalformedOpening(_))),
+                "{line:?} was accepted"
+            );
+        }
+    }
+}
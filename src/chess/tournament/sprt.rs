@@ -0,0 +1,153 @@
+use std::fmt;
+
+/// Wins, draws and losses from the first engine's point of view.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct MatchScore {
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+}
+
+impl MatchScore {
+    pub fn games(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+
+    /// Average points per game, between 0.0 and 1.0.
+    pub fn score(&self) -> f64 {
+        if self.games() == 0 {
+            return 0.5;
+        }
+
+        (self.wins as f64 + self.draws as f64 / 2.0) / self.games() as f64
+    }
+
+    /// Variance of the points scored in a single game.
+    fn variance(&self) -> f64 {
+        let games = self.games() as f64;
+        let score = self.score();
+
+        (self.wins as f64 * (1.0 - score).powi(2)
+            + self.draws as f64 * (0.5 - score).powi(2)
+            + self.losses as f64 * score.powi(2))
+            / games
+    }
+
+    /// Elo difference and the half width of its 95% confidence interval, or
+    /// `None` until both a point has been won and a point has been dropped.
+    pub fn elo(&self) -> Option<(f64, f64)> {
+        let score = self.score();
+
+        if self.games() == 0 || score <= 0.0 || score >= 1.0 {
+            return None;
+        }
+
+        let deviation = (self.variance() / self.games() as f64).sqrt();
+
+        let lower = score_to_elo((score - 1.96 * deviation).max(f64::EPSILON));
+        let upper = score_to_elo((score + 1.96 * deviation).min(1.0 - f64::EPSILON));
+
+        Some((score_to_elo(score), (upper - lower) / 2.0))
+    }
+}
+
+impl fmt::Display for MatchScore {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "+{} ={} -{}", self.wins, self.draws, self.losses)?;
+
+        match self.elo() {
+            Some((elo, margin)) => write!(f, " Elo {elo:+.1} +/- {margin:.1}"),
+            None => write!(f, " Elo n/a"),
+        }
+    }
+}
+
+pub fn score_to_elo(score: f64) -> f64 {
+    -400.0 * (1.0 / score - 1.0).log10()
+}
+
+pub fn elo_to_score(elo: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-elo / 400.0))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SprtVerdict {
+    /// The first engine is at least `elo1` stronger, accept H1.
+    Accept,
+    /// The first engine is at most `elo0` stronger, accept H0.
+    Reject,
+    Continue,
+}
+
+impl fmt::Display for SprtVerdict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Accept => write!(f, "H1 accepted"),
+            Self::Reject => write!(f, "H0 accepted"),
+            Self::Continue => write!(f, "continue"),
+        }
+    }
+}
+
+/// Sequential probability ratio test of H0: `elo = elo0` against H1: `elo = elo1`.
+#[derive(Debug, Clone, Copy)]
+pub struct Sprt {
+    pub elo0: f64,
+    pub elo1: f64,
+    pub alpha: f64,
+    pub beta: f64,
+}
+
+impl Default for Sprt {
+    fn default() -> Self {
+        Self {
+            elo0: 0.0,
+            elo1: 5.0,
+            alpha: 0.05,
+            beta: 0.05,
+        }
+    }
+}
+
+impl Sprt {
+    /// The log likelihood ratio thresholds below which H0 and above which H1
+    /// is accepted.
+    pub fn bounds(&self) -> (f64, f64) {
+        (
+            (self.beta / (1.0 - self.alpha)).ln(),
+            ((1.0 - self.beta) / self.alpha).ln(),
+        )
+    }
+
+    /// Log likelihood ratio of the results so far, using the normal
+    /// approximation of the per game score distribution.
+    pub fn llr(&self, results: &MatchScore) -> f64 {
+        if results.wins == 0 || results.losses == 0 {
+            return 0.0;
+        }
+
+        let (score0, score1) = (elo_to_score(self.elo0), elo_to_score(self.elo1));
+
+        let variance = results.variance();
+
+        if variance <= 0.0 {
+            return 0.0;
+        }
+
+        results.games() as f64 * (score1 - score0) * (2.0 * results.score() - score0 - score1)
+            / (2.0 * variance)
+    }
+
+    pub fn verdict(&self, results: &MatchScore) -> SprtVerdict {
+        let llr = self.llr(results);
+        let (lower, upper) = self.bounds();
+
+        if llr >= upper {
+            SprtVerdict::Accept
+        } else if llr <= lower {
+            SprtVerdict::Reject
+        } else {
+            SprtVerdict::Continue
+        }
+    }
+}
@@ -0,0 +1,147 @@
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+
+use super::{Game, MatchError, Player};
+use crate::chess::board::chess_move::Move;
+use crate::chess::search::SearchLimits;
+
+/// An engine running as a UCI subprocess, e.g. another build of this engine.
+pub struct UciEngine {
+    name: String,
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+}
+
+impl UciEngine {
+    /// Starts `command` and sets each of `options` once the handshake is done.
+    pub fn spawn(command: &str, options: &[(String, String)]) -> Result<Self, MatchError> {
+        let mut child = Command::new(command)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+
+        let stdin = child.stdin.take().expect("stdin is piped");
+        let stdout = BufReader::new(child.stdout.take().expect("stdout is piped"));
+
+        let mut engine = Self {
+            name: command.to_string(),
+            child,
+            stdin,
+            stdout,
+        };
+
+        engine.send("uci")?;
+
+        for line in engine.read_until("uciok")? {
+            if let Some(name) = line.strip_prefix("id name ") {
+                engine.name = name.trim().to_string();
+            }
+        }
+
+        for (name, value) in options {
+            engine.send(&format!("setoption name {name} value {value}"))?;
+        }
+
+        engine.send("isready")?;
+        engine.read_until("readyok")?;
+
+        Ok(engine)
+    }
+
+    fn send(&mut self, command: &str) -> Result<(), MatchError> {
+        writeln!(self.stdin, "{command}")?;
+        self.stdin.flush()?;
+        Ok(())
+    }
+
+    /// Reads lines up to and including the first one starting with `token`.
+    fn read_until(&mut self, token: &str) -> Result<Vec<String>, MatchError> {
+        let mut lines = Vec::new();
+
+        loop {
+            let mut line = String::new();
+
+            if self.stdout.read_line(&mut line)? == 0 {
+                return Err(MatchError::EngineExited(self.name.clone()));
+            }
+
+            let line = line.trim().to_string();
+            let done = line.starts_with(token);
+
+            lines.push(line);
+
+            if done {
+                return Ok(lines);
+            }
+        }
+    }
+}
+
+impl Player for UciEngine {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn new_game(&mut self) -> Result<(), MatchError> {
+        self.send("ucinewgame")?;
+        self.send("isready")?;
+        self.read_until("readyok")?;
+        Ok(())
+    }
+
+    fn choose_move(&mut self, game: &Game, limits: &SearchLimits) -> Result<Move, MatchError> {
+        let mut position = format!("position fen {}", game.start.to_fen());
+
+        if !game.moves.is_empty() {
+            position.push_str(" moves ");
+            position.push_str(&game.uci_moves().join(" "));
+        }
+
+        self.send(&position)?;
+        self.send(&go_command(limits))?;
+
+        let lines = self.read_until("bestmove")?;
+
+        let best_move = lines
+            .last()
+            .and_then(|line| line.split_whitespace().nth(1))
+            .unwrap_or_default();
+
+        Move::from_uci(&game.board, best_move)
+            .map_err(|err| MatchError::IllegalMove(self.name.clone(), err))
+    }
+}
+
+impl Drop for UciEngine {
+    fn drop(&mut self) {
+        if self.send("quit").is_err() {
+            let _ = self.child.kill();
+        }
+
+        let _ = self.child.wait();
+    }
+}
+
+fn go_command(limits: &SearchLimits) -> String {
+    let mut command = String::from("go");
+
+    let fields = [
+        ("depth", limits.depth.map(u64::from)),
+        ("nodes", limits.nodes),
+        ("movetime", limits.movetime),
+        ("wtime", limits.wtime),
+        ("btime", limits.btime),
+        ("winc", limits.winc),
+        ("binc", limits.binc),
+        ("movestogo", limits.movestogo),
+    ];
+
+    for (name, value) in fields {
+        if let Some(value) = value {
+            command.push_str(&format!(" {name} {value}"));
+        }
+    }
+
+    command
+}
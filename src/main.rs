@@ -1,13 +1,56 @@
-#[allow(dead_code)]
-#[allow(unused)]
-mod chess;
+use std::env;
+use std::process;
+use std::time::{Duration, SystemTime};
 
-use chess::board::Board;
-use rand::{thread_rng, Rng};
+use chess_engine_rust::chess::board::{Board, Color, GameStatus};
+use chess_engine_rust::chess::options::EngineOptions;
+use chess_engine_rust::chess::pgn::writer::pgn_date;
+use chess_engine_rust::chess::pgn::{MoveAnnotation, PgnGame, PgnMove};
+use chess_engine_rust::chess::search::pv::Score;
+use chess_engine_rust::chess::search::{SearchLimits, Searcher};
+use chess_engine_rust::chess::solver::solve_mate;
 use tungstenite::{connect, Message};
 use url::Url;
 
+const INITIAL_CLOCK_MS: u64 = 5 * 60 * 1000;
+const INCREMENT_MS: u64 = 2000;
+
 fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    match args.first().map(String::as_str) {
+        Some("solve") => solve(&args[1..]),
+        _ => play(),
+    }
+}
+
+/// `solve <fen> <moves>`: proves or refutes a forced mate in `moves`.
+fn solve(args: &[String]) {
+    let (fen, moves) = match args {
+        [fen, moves] => (fen, moves),
+        _ => {
+            eprintln!("Usage: solve <fen> <moves>");
+            process::exit(1);
+        }
+    };
+
+    let board = Board::from_fen(fen).unwrap_or_else(|err| {
+        eprintln!("{err}");
+        process::exit(1);
+    });
+
+    let moves: u32 = moves.parse().unwrap_or_else(|_| {
+        eprintln!("Invalid number of moves: {moves}");
+        process::exit(1);
+    });
+
+    match solve_mate(&board, moves) {
+        Some(solution) => print!("{solution}"),
+        None => println!("No forced mate in {moves}"),
+    }
+}
+
+fn play() {
     let mut board = match Board::from_fen("start") {
         Ok(b) => b,
         Err(err) => panic!("Error initializing board: {err}"),
@@ -19,20 +62,79 @@ fn main() {
     println!("Connected to ws server.");
     println!("STATUS: {}", response.status());
 
-    let mut rng = thread_rng();
+    let mut searcher = Searcher::new(EngineOptions::default());
+
+    for warning in searcher.warnings() {
+        eprintln!("{warning}");
+    }
+
+    let mut history = Vec::new();
+
+    let mut white_clock = INITIAL_CLOCK_MS;
+    let mut black_clock = INITIAL_CLOCK_MS;
+
+    let mut game = PgnGame::new(&board);
+    game.set_tag("Event", "Websocket game");
+    game.set_tag("Date", &pgn_date(SystemTime::now()));
+    game.set_tag("White", "chess_engine_rust");
+    game.set_tag("Black", "chess_engine_rust");
+    game.set_tag(
+        "TimeControl",
+        &format!("{}+{}", INITIAL_CLOCK_MS / 1000, INCREMENT_MS / 1000),
+    );
 
     for _ in 0..1000 {
         socket.write_message(Message::Text(board.to_fen())).unwrap();
 
-        let legal_moves = board.get_legal_moves();
-        let random_index = rng.gen_range(0..legal_moves.len());
+        if board.get_game_status() != GameStatus::Ongoing {
+            break;
+        }
+
+        let limits = SearchLimits {
+            wtime: Some(white_clock),
+            btime: Some(black_clock),
+            winc: Some(INCREMENT_MS),
+            binc: Some(INCREMENT_MS),
+            ..Default::default()
+        };
 
-        println!("Legal Moves: {}", legal_moves.len());
+        searcher.set_game_history(&history);
+        let result = searcher.search(&board, &limits);
 
-        board
-            .move_piece(legal_moves[random_index])
-            .expect("Failed to move piece");
+        println!(
+            "Depth: {} Score: {} Nodes: {} Time: {} ms",
+            result.depth,
+            result.score,
+            result.nodes,
+            result.time.as_millis()
+        );
 
-        std::thread::sleep(std::time::Duration::from_millis(1500));
+        let clock = match board.active_turn {
+            Color::White => &mut white_clock,
+            Color::Black => &mut black_clock,
+        };
+
+        *clock = clock.saturating_sub(result.time.as_millis() as u64) + INCREMENT_MS;
+
+        let score = match board.active_turn {
+            Color::White => result.score,
+            Color::Black => -result.score,
+        };
+
+        let annotation = MoveAnnotation {
+            eval: Some(Score::from_search_score(score)),
+            clock: Some(Duration::from_millis(*clock)),
+        };
+
+        let best_move = result.best_move.expect("No legal move found");
+        history.push(board.hash());
+        board.move_piece(best_move).expect("Failed to move piece");
+
+        let mut pgn_move = PgnMove::new(best_move);
+        pgn_move.comments.push(annotation.to_string());
+        game.moves.push(pgn_move);
     }
+
+    game.set_result(board.get_game_status());
+    println!("{}", game.to_pgn());
 }
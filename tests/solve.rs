@@ -0,0 +1,46 @@
+use std::process::{Command, Output};
+
+fn solve(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_chess_engine_rust"))
+        .arg("solve")
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn prints_the_solution() {
+    let output = solve(&["k7/8/2K5/8/8/8/8/1R6 w - - 0 1", "2"]);
+
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "Mate in 2\n1. Kc7!\n  1... Ka7 2. Ra1#\n"
+    );
+}
+
+#[test]
+fn reports_no_mate() {
+    let output = solve(&["8/8/8/8/8/5K2/8/5k1R w - - 0 1", "2"]);
+
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "No forced mate in 2\n"
+    );
+}
+
+#[test]
+fn rejects_bad_arguments() {
+    for args in [
+        &["k7/8/2K5/8/8/8/8/1R6 w - - 0 1"][..],
+        &["k7/8/2K5/8/8/8/8/1R6 w - - 0 1", "two"],
+        &["k7/8/2K5/8/8/8/8/1X6 w - - 0 1", "2"],
+    ] {
+        let output = solve(args);
+
+        assert_eq!(output.status.code(), Some(1), "{args:?}");
+        assert!(output.stdout.is_empty());
+        assert!(!output.stderr.is_empty());
+    }
+}